@@ -1,25 +1,270 @@
-use serenity::framework::standard::CommandResult;
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use serenity::framework::standard::{Args, CommandResult};
 use serenity::framework::standard::macros::command;
 use serenity::prelude::Context;
 use serenity::model::channel::Message;
-use crate::persistence::edge::requests::create::create_discord_user;
+use serenity::model::user::User;
+use serenity::utils::MessageBuilder;
+use crate::constants::economy::UNREGISTER_CONFIRMATION_TIMEOUT;
+use crate::persistence::edge::requests::create::ensure_discord_user;
+use crate::persistence::edge::requests::delete::{delete_discord_user, DeleteAccountOutcome};
+use crate::persistence::edge::requests::read::{get_discord_user_info, get_owned_roles, get_profile, get_user_portfolio};
+use crate::persistence::edge::requests::update::set_user_timezone;
+use crate::persistence::edge_models::{OwnedRole, PortfolioUser, Profile};
 use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::portfolio::{aggregate_positions, fetch_held_prices, net_worth};
+use crate::utils::shortcuts::remove_member_role_or_gone;
+use super::economy::refuse_if_frozen;
+
+/// Opens an account for the author of the message if they have none yet, which every stocks and
+/// economy command does before running. Returns the money of the account if it was just created
+/// (`Some(None)` when it already existed), None on error.
+pub(crate) async fn ensure_registered(ctx: &Context, msg: &Message) -> Option<Option<f64>> {
+    // The nickname is only known in guilds
+    let display_name: String = msg.author_nick(&ctx.http).await.unwrap_or_else(|| msg.author.name.clone());
+
+    // Most commands come from existing accounts, which only need a read
+    match get_discord_user_info(msg.author.id.to_string()).await {
+        Ok(Some(_)) => return Some(None),
+        Ok(None) => (),
+        Err(err) => {
+            log_error(format!("(register) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return None;
+        }
+    }
+
+    match ensure_discord_user(msg.author.id.to_string(), msg.author.name.clone(), display_name).await {
+        Ok(opening_money) => Some(opening_money),
+        Err(err) => {
+            log_error(format!("(register) Could not register the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            None
+        }
+    }
+}
 
 #[command]
+#[description("Opens your account. Not needed anymore: your first stocks or economy command opens it.")]
+#[example("!register")]
+#[num_args(0)]
 pub async fn register(ctx: &Context, msg: &Message) -> CommandResult {
-    // let sm = SanitizedMessage::from(msg);
-    let user = msg.author.clone();
-    let user_display_name = user.nick_in(&ctx, msg.guild_id.unwrap()).await.unwrap_or(String::from("Unknown"));
-
-    let insert_result = create_discord_user(user.name.clone(), user_display_name, user.id.to_string()).await;
-    
-    if insert_result.is_some() {
-        let why = insert_result.unwrap();
-        log_error(format!("Could not insert a new user in the DB: {}", why), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
-        let _ = msg.reply_mention(&ctx, format!("Could not insert a new user in the DB: {}", why)).await;
-    }
-    else {
-        let _ = msg.reply_mention(&ctx, format!("User {} registered", user.name.clone())).await;
+    match ensure_registered(ctx, msg).await {
+        Some(Some(money)) => {
+            let _ = msg.reply_mention(&ctx, format!("User {} registered, you start with €{:.2}.", msg.author.name, money)).await;
+        },
+        Some(None) => {
+            let _ = msg.reply_mention(&ctx, "You are already registered.").await;
+        },
+        None => {
+            let _ = msg.reply_mention(&ctx, "Could not register you, please try again later.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Shows your account, or the one of the mentioned user: money, net worth, positions, streak and settings.")]
+#[example("!profile @Oscuro")]
+#[usage("!profile [optional @user]")]
+#[max_args(1)]
+#[help_available]
+pub async fn profile(ctx: &Context, msg: &Message) -> CommandResult {
+    let user: &User = msg.mentions.first().unwrap_or(&msg.author);
+
+    let profile: Profile = match get_profile(user.id.to_string()).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, format!("{} is not registered yet.", user.name)).await;
+            return Ok(());
+        },
+        Err(err) => {
+            log_error(format!("(profile) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    let portfolio_user: Option<PortfolioUser> = match get_user_portfolio(user.id.to_string()).await {
+        Ok(portfolio_user) => portfolio_user,
+        Err(err) => {
+            log_error(format!("(profile) Could not query the portfolio: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let timezone: Tz = profile.timezone.parse().unwrap_or(chrono_tz::Europe::Brussels);
+    let registered: String = timezone
+        .timestamp_opt(profile.registered_epoch as i64, 0)
+        .single()
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| String::from("?"));
+
+    let mut builder = MessageBuilder::new();
+    builder
+        .push_bold_line(profile.display_name.clone())
+        .push_line(format!("Money: €{:.2}", profile.money));
+    if let Some(portfolio_user) = portfolio_user.as_ref() {
+        let prices = fetch_held_prices(std::slice::from_ref(portfolio_user)).await;
+        let positions: usize = portfolio_user.portfolio.as_ref().map(|portfolio| aggregate_positions(&portfolio.lines).len()).unwrap_or(0);
+        builder
+            .push_line(format!("Net worth: €{:.2}", net_worth(portfolio_user, &prices)))
+            .push_line(format!("Positions: {} long, {} short", positions, portfolio_user.shorts.len()));
+    }
+    builder
+        .push_line(format!("Daily streak: {}", profile.daily_streak))
+        .push_line(format!(
+            "Pending orders: {}, price alerts: {}, watched tickers: {}",
+            profile.pending_orders, profile.price_alerts, profile.watched_tickers
+        ))
+        .push_line(format!("Timezone: {}", profile.timezone))
+        .push_line(format!("Registered on {}", registered));
+    if profile.frozen {
+        builder.push_italic_line(format!(
+            "The account is frozen{}",
+            profile.frozen_reason.as_ref().map(|reason| format!(": {}", reason)).unwrap_or_default()
+        ));
+    }
+    let _ = msg.reply(&ctx.http, builder.build()).await;
+
+    Ok(())
+}
+
+#[command]
+#[description("Deletes your account, after you confirm it: money, portfolio, orders, alerts, watchlist and shop items are lost. Accounts in debt or with short positions cannot be deleted.")]
+#[example("!unregister")]
+#[num_args(0)]
+#[help_available]
+pub async fn unregister(ctx: &Context, msg: &Message) -> CommandResult {
+    let user_id: String = msg.author.id.to_string();
+
+    let account: PortfolioUser = match get_user_portfolio(user_id.clone()).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "You have no account.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            log_error(format!("(unregister) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+    // A new account would wipe the debt out
+    if account.money < 0.0 || !account.shorts.is_empty() {
+        let _ = msg.reply(&ctx.http, format!(
+            "Pay your margin loan back and cover your short positions first (money: €{:.2}, {} short lots).",
+            account.money, account.shorts.len()
+        )).await;
+        return Ok(());
+    }
+
+    let _ = msg.reply(&ctx.http, format!(
+        "This deletes your account with its €{:.2}, portfolio, orders, alerts, watchlist and shop items, a new account would start with €0. Answer \"yes\" within {} seconds to confirm.",
+        account.money, UNREGISTER_CONFIRMATION_TIMEOUT.as_secs()
+    )).await;
+    let confirmed: bool = match msg.author.await_reply(ctx).channel_id(msg.channel_id).timeout(UNREGISTER_CONFIRMATION_TIMEOUT).await {
+        Some(answer) => matches!(answer.content.trim().to_lowercase().as_str(), "yes" | "y"),
+        None => false,
+    };
+    if !confirmed {
+        let _ = msg.reply(&ctx.http, "Your account was kept.").await;
+        return Ok(());
+    }
+
+    if !take_back_owned_roles(ctx, msg).await {
+        let _ = msg.reply(&ctx.http, "Could not take back the roles of your shop items, your account was kept. Please try again later.").await;
+        return Ok(());
+    }
+
+    match delete_discord_user(user_id).await {
+        Ok(DeleteAccountOutcome::Deleted) => {
+            let _ = msg.reply(&ctx.http, "Your account was deleted. Your next stocks or economy command opens a new one, with €0.").await;
+        },
+        Ok(DeleteAccountOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "You have no account.").await;
+        },
+        Ok(DeleteAccountOutcome::InDebt) => {
+            let _ = msg.reply(&ctx.http, "Your account went into debt in the meantime, it was kept.").await;
+        },
+        Ok(DeleteAccountOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account was frozen in the meantime, it was kept.").await;
+        },
+        Err(err) => {
+            log_error(format!("(unregister) Could not delete the account: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes the roles of the shop items of the author off, in every guild. False if one could not be taken back.
+async fn take_back_owned_roles(ctx: &Context, msg: &Message) -> bool {
+    let owned_roles: Vec<OwnedRole> = match get_owned_roles(msg.author.id.to_string()).await {
+        Ok(owned_roles) => owned_roles,
+        Err(err) => {
+            log_error(format!("(unregister) Could not query the shop roles: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return false;
+        }
+    };
+
+    for owned_role in owned_roles.iter() {
+        let ids = (
+            owned_role.guild_id.parse::<u64>(),
+            owned_role.role_id.as_ref().map(|role_id| role_id.parse::<u64>()),
+        );
+        if let (Ok(guild_id), Some(Ok(role_id))) = ids {
+            if let Err(err) = remove_member_role_or_gone(ctx, guild_id, msg.author.id.into(), role_id, "Account deleted").await {
+                log_error(format!("(unregister) Could not take role {} of \"{}\" back from {}: {}.", role_id, owned_role.item_name, msg.author.id, err), LogErrorLevel::WARN, msg.channel_id.to_string(), false).await;
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+#[command]
+#[description("Shows or changes the timezone used to show you dates and times, such as when a market opens.")]
+#[example("!timezone America/New_York")]
+#[usage("!timezone [IANA timezone name]")]
+#[max_args(1)]
+pub async fn timezone(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let user_id: String = msg.author.id.to_string();
+
+    if args.is_empty() {
+        match get_discord_user_info(user_id).await {
+            Ok(Some(user)) => {
+                let _ = msg.reply(&ctx.http, format!("Your timezone is {}.", user.timezone)).await;
+            },
+            Ok(None) => {
+                let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            },
+            Err(err) => {
+                log_error(format!("(timezone) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        }
+        return Ok(());
+    }
+
+    let timezone: Tz = match args.rest().trim().parse() {
+        Ok(timezone) => timezone,
+        Err(_) => {
+            let _ = msg.reply(&ctx.http, "Unknown timezone, use a name such as Europe/Brussels or America/New_York.").await;
+            return Ok(());
+        }
+    };
+
+    match set_user_timezone(user_id, timezone.name().to_string()).await {
+        Ok(true) => {
+            let _ = msg.reply(&ctx.http, format!("Your timezone is now {}.", timezone.name())).await;
+        },
+        Ok(false) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Err(err) => {
+            log_error(format!("(timezone) Could not change the timezone: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
     }
 
     Ok(())
@@ -0,0 +1,341 @@
+use chrono::Utc;
+use chrono_tz::Europe::Brussels;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::model::user::User;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+use crate::constants::channels::ERRORS;
+use crate::constants::economy::{AUDIT_PAGE_SIZE, RECONCILIATION_REPORT_SIZE};
+use crate::datastructs::CEmbedData;
+use crate::persistence::edge::requests::create::{create_economy_audit, create_season, save_guild_trading_settings};
+use crate::persistence::edge::requests::read::{get_active_season, get_economy_audit, get_ledger_balances};
+use crate::persistence::edge::requests::update::{admin_adjust_money, set_account_frozen};
+use crate::persistence::edge::requests::EconomyAction;
+use crate::persistence::edge_models::{EconomyAuditEntry, GuildTradingSettings};
+use crate::plugins::scheduled::seasons::close_season;
+use crate::utils::economy::{parse_amount, parse_balance};
+use crate::utils::ledger::{find_mismatches, BalanceMismatch};
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::margin::{guild_margin_rules, MarginRules};
+use crate::utils::seasons::parse_season_end;
+use crate::utils::shortcuts::send_embed_or_discord_error;
+use crate::utils::stock_utils::parse_quantity;
+
+#[command]
+#[description("Shows or changes the short selling and margin rules of this server. Percentages are given as numbers, such as 8 for 8%.")]
+#[example("!trading_rules shorts on")]
+#[usage("!trading_rules [shorts|margin on|off] [interest|initial|maintenance <percent>]")]
+#[max_args(2)]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[help_available]
+pub async fn trading_rules(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+    let mut rules: MarginRules = guild_margin_rules(Some(guild_id.clone())).await;
+
+    if !args.is_empty() {
+        let setting: String = args.single::<String>().unwrap_or_default().to_lowercase();
+        let value: String = args.single::<String>().unwrap_or_default().to_lowercase();
+        let enabled: Option<bool> = match value.as_str() {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        };
+        // Given in percent, stored as a fraction
+        let rate: Option<f64> = value
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .ok()
+            .filter(|rate| rate.is_finite() && *rate >= 0.0 && *rate <= 100.0)
+            .map(|rate| rate / 100.0);
+
+        match (setting.as_str(), enabled, rate) {
+            ("shorts", Some(enabled), _) => rules.short_selling = enabled,
+            ("margin", Some(enabled), _) => rules.margin = enabled,
+            ("interest", _, Some(rate)) => rules.interest_rate = rate,
+            ("initial", _, Some(rate)) if rate > 0.0 => rules.initial_margin = rate,
+            ("maintenance", _, Some(rate)) if rate > 0.0 => rules.maintenance_margin = rate,
+            _ => {
+                let _ = msg.reply(&ctx.http, "Usage: !trading_rules shorts on|off, margin on|off, interest 8, initial 50 or maintenance 30.").await;
+                return Ok(());
+            }
+        }
+
+        if rules.maintenance_margin > rules.initial_margin {
+            let _ = msg.reply(&ctx.http, "The maintenance margin cannot be higher than the initial margin.").await;
+            return Ok(());
+        }
+
+        let settings = GuildTradingSettings {
+            guild_id,
+            short_selling: rules.short_selling,
+            margin: rules.margin,
+            interest_rate: rules.interest_rate,
+            initial_margin: rules.initial_margin,
+            maintenance_margin: rules.maintenance_margin,
+        };
+        if let Err(err) = save_guild_trading_settings(settings).await {
+            let _ = log_error(format!("(trading_rules) Could not save the trading rules: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not save the trading rules.").await;
+            return Ok(());
+        }
+    }
+
+    let reply = MessageBuilder::new()
+        .push_bold_line("Trading rules of this server")
+        .push_line(format!("Short selling: {}", if rules.short_selling { "on" } else { "off" }))
+        .push_line(format!("Margin buying: {}", if rules.margin { "on" } else { "off" }))
+        .push_line(format!("Interest on borrowed money and shares: {:.2}% a year", rules.interest_rate * 100.0))
+        .push_line(format!("Initial margin: {:.0}%", rules.initial_margin * 100.0))
+        .push_line(format!("Maintenance margin: {:.0}% (positions are liquidated below it)", rules.maintenance_margin * 100.0))
+        .build();
+    let _ = msg.reply(&ctx.http, reply).await;
+
+    Ok(())
+}
+
+#[command]
+#[description("Starts a trading season on this server: everyone who joins gets a fresh season account with the starting balance, their main account is left untouched. The final standings are announced in this channel at the end of the given day (Europe/Brussels).")]
+#[example("!start_season 10000 2026-11-30")]
+#[usage("!start_season [starting balance] [YYYY-MM-DD]")]
+#[num_args(2)]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[help_available]
+pub async fn start_season(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+    let starting_balance: Option<f64> = parse_quantity(&args.single::<String>().unwrap_or_default());
+    let ends = parse_season_end(&args.single::<String>().unwrap_or_default(), Utc::now());
+
+    let (starting_balance, ends) = match (starting_balance, ends) {
+        (Some(starting_balance), Ok(ends)) => (starting_balance, ends),
+        (None, _) => {
+            let _ = msg.reply(&ctx.http, "Usage: !start_season 10000 2026-11-30 (the starting balance must be a positive number).").await;
+            return Ok(());
+        }
+        (_, Err(error)) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+
+    match create_season(guild_id, msg.channel_id.to_string(), starting_balance, ends.timestamp() as f64).await {
+        Ok(Some(number)) => {
+            let reply = MessageBuilder::new()
+                .push_bold_line(format!("Season #{} has started!", number))
+                .push_line(format!(
+                    "Everyone starts from €{:.2}, the season ends on {}.",
+                    starting_balance,
+                    ends.with_timezone(&Brussels).format("%a %Y-%m-%d %H:%M (Europe/Brussels)")
+                ))
+                .push_line("Join with !season join, then trade with !season buy and !season sell. Your main account is not affected.")
+                .build();
+            let _ = msg.channel_id.say(&ctx.http, reply).await;
+        }
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "A season is already running on this server, end it first with !end_season.").await;
+        }
+        Err(err) => {
+            let _ = log_error(format!("(start_season) Could not start the season: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not start the season.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Ends the running season of this server now, and announces its final standings.")]
+#[example("!end_season")]
+#[num_args(0)]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[help_available]
+pub async fn end_season(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+
+    match get_active_season(guild_id).await {
+        Ok(Some(season)) => close_season(ctx, &season).await,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "No season is running on this server.").await;
+        }
+        Err(err) => {
+            let _ = log_error(format!("(end_season) Could not query the running season: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Economy administration for the owners of the bot: changes or freezes accounts, checks the balances against the ledger and shows the audit log. Every change is recorded in the audit log with its reason.")]
+#[example("!eco grant @Oscuro 500 refund of the lost shares")]
+#[usage("!eco [grant|revoke|set] [@user] [amount] [reason], !eco [freeze|unfreeze] [@user] [reason], !eco reconcile [@user], !eco audit [@user]")]
+#[min_args(1)]
+#[owners_only]
+#[help_available]
+pub async fn eco(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let action: String = args.single::<String>().unwrap_or_default().to_lowercase();
+    let target: Option<&User> = msg.mentions.first();
+
+    match (action.as_str(), target) {
+        ("grant" | "revoke" | "set", Some(target)) => {
+            let action: EconomyAction = match action.as_str() {
+                "grant" => EconomyAction::Grant,
+                "revoke" => EconomyAction::Revoke,
+                _ => EconomyAction::Set,
+            };
+            // The mention comes first
+            args.advance();
+            let amount: Option<f64> = match action {
+                EconomyAction::Set => args.current().and_then(parse_balance),
+                _ => args.current().and_then(parse_amount),
+            };
+            args.advance();
+            let reason: String = args.rest().trim().to_string();
+
+            match amount {
+                Some(amount) if !reason.is_empty() => eco_adjust(ctx, msg, target, action, amount, reason).await,
+                _ => {
+                    let _ = msg.reply(&ctx.http, format!("Usage: !eco {} @user [amount] [reason]", action)).await;
+                }
+            }
+        },
+        ("freeze" | "unfreeze", Some(target)) => {
+            args.advance();
+            let reason: String = args.rest().trim().to_string();
+            if reason.is_empty() {
+                let _ = msg.reply(&ctx.http, format!("Usage: !eco {} @user [reason]", action)).await;
+                return Ok(());
+            }
+
+            eco_freeze(ctx, msg, target, action == "freeze", reason).await;
+        },
+        ("reconcile", target) => eco_reconcile(ctx, msg, target).await,
+        ("audit", target) => eco_audit(ctx, msg, target).await,
+        _ => {
+            let _ = msg.reply(&ctx.http, "Usage: !eco grant|revoke|set @user [amount] [reason], !eco freeze|unfreeze @user [reason], !eco reconcile [@user] or !eco audit [@user]").await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn eco_adjust(ctx: &Context, msg: &Message, target: &User, action: EconomyAction, amount: f64, reason: String) -> () {
+    match admin_adjust_money(msg.author.id.to_string(), target.id.to_string(), action, amount, reason).await {
+        Ok(Some((before, after))) => {
+            let _ = msg.reply(&ctx.http, format!("{}'s money went from €{:.2} to €{:.2} ({:+.2}).", target.name, before, after, after - before)).await;
+        },
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, format!("{} is not registered.", target.name)).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(eco) Could not {} money: {}.", action, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+}
+
+async fn eco_freeze(ctx: &Context, msg: &Message, target: &User, frozen: bool, reason: String) -> () {
+    match set_account_frozen(msg.author.id.to_string(), target.id.to_string(), frozen, reason).await {
+        Ok(true) => {
+            let state: &str = if frozen { "frozen: they cannot trade nor move money anymore" } else { "unfrozen" };
+            let _ = msg.reply(&ctx.http, format!("{}'s account is {}.", target.name, state)).await;
+        },
+        Ok(false) => {
+            let _ = msg.reply(&ctx.http, format!("{} is not registered.", target.name)).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(eco) Could not change the frozen state: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+}
+
+/// Recomputes the balances from the ledger and lists the accounts whose money does not match.
+async fn eco_reconcile(ctx: &Context, msg: &Message, target: Option<&User>) -> () {
+    let user_id: Option<String> = target.map(|target| target.id.to_string());
+    let balances = match get_ledger_balances(user_id.clone()).await {
+        Ok(balances) => balances,
+        Err(err) => {
+            let _ = log_error(format!("(eco) Could not query the ledger balances: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return;
+        }
+    };
+    let mismatches: Vec<BalanceMismatch> = find_mismatches(&balances);
+
+    let mut builder = MessageBuilder::new();
+    if mismatches.is_empty() {
+        builder.push_line(format!("The {} checked accounts match their ledger.", balances.len()));
+    } else {
+        builder.push_line(format!("{} of the {} checked accounts do not match their ledger:", mismatches.len(), balances.len()));
+    }
+    for mismatch in mismatches.iter().take(RECONCILIATION_REPORT_SIZE) {
+        let last_balance: String = match mismatch.last_balance {
+            Some(last_balance) => format!(", latest entry €{:.2}", last_balance),
+            None => String::from(", no entry"),
+        };
+        builder.push_line(format!(
+            "{}: money €{:.2}, ledger €{:.2} ({:+.2}){}",
+            mismatch.display_name, mismatch.money, mismatch.expected, mismatch.difference(), last_balance
+        ));
+    }
+    if mismatches.len() > RECONCILIATION_REPORT_SIZE {
+        builder.push_italic_line(format!("...and {} more.", mismatches.len() - RECONCILIATION_REPORT_SIZE));
+    }
+
+    let summary: String = format!("{} mismatches out of {} accounts", mismatches.len(), balances.len());
+    if let Err(err) = create_economy_audit(msg.author.id.to_string(), user_id, EconomyAction::Reconcile, summary).await {
+        let _ = log_error(format!("(eco) Could not record the reconciliation: {}.", err), LogErrorLevel::WARN, msg.channel_id.to_string(), false).await;
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = String::from("Ledger reconciliation");
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
+
+async fn eco_audit(ctx: &Context, msg: &Message, target: Option<&User>) -> () {
+    let entries: Vec<EconomyAuditEntry> = match get_economy_audit(target.map(|target| target.id.to_string()), AUDIT_PAGE_SIZE).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            let _ = log_error(format!("(eco) Could not query the audit log: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return;
+        }
+    };
+
+    let mut builder = MessageBuilder::new();
+    if entries.is_empty() {
+        builder.push_line("Nothing recorded yet.");
+    }
+    for entry in entries.iter() {
+        let mut line: String = format!("{} | {}", entry.created_local, entry.action);
+        if let Some(user_id) = entry.user_id.as_ref() {
+            line = format!("{} <@{}>", line, user_id);
+        }
+        if let Some(amount) = entry.amount {
+            line = format!("{} €{:.2}", line, amount);
+        }
+        if let (Some(before), Some(after)) = (entry.balance_before, entry.balance_after) {
+            if before != after {
+                line = format!("{} | €{:.2} → €{:.2}", line, before, after);
+            }
+        }
+        builder.push_line(format!("{} | by <@{}> | {}", line, entry.owner_id, entry.reason));
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = String::from("Economy audit log");
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
@@ -0,0 +1,188 @@
+use chrono::Utc;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::model::user::User;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+use crate::constants::economy::{DAILY_COOLDOWN_HOURS, PAY_CONFIRMATION_TIMEOUT};
+use crate::persistence::edge::requests::read::{get_daily_state, is_account_frozen};
+use crate::persistence::edge::requests::update::{claim_daily, transfer_money, DailyOutcome, TransferOutcome};
+use crate::persistence::edge_models::DailyState;
+use crate::utils::economy::{daily_amount, daily_claim, format_wait, parse_amount, DailyClaim};
+use crate::utils::logging::db_log::*;
+
+/// Tells the author their account is frozen, in which case the command must stop there.
+pub(crate) async fn refuse_if_frozen(ctx: &Context, msg: &Message) -> bool {
+    match is_account_frozen(msg.author.id.to_string()).await {
+        Ok(true) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+            true
+        },
+        Ok(false) => false,
+        Err(err) => {
+            let _ = log_error(format!("(economy) Could not check whether the account is frozen: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            true
+        }
+    }
+}
+
+#[command]
+#[description("Claims your daily allowance. Claiming on consecutive days builds a streak which raises the allowance.")]
+#[example("!daily")]
+#[num_args(0)]
+#[help_available]
+pub async fn daily(ctx: &Context, msg: &Message) -> CommandResult {
+    let user_id: String = msg.author.id.to_string();
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let state: DailyState = match get_daily_state(user_id.clone()).await {
+        Ok(Some(state)) => state,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            let _ = log_error(format!("(daily) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let now: f64 = Utc::now().timestamp() as f64;
+    let (amount, streak) = match daily_claim(state.last_daily_epoch, state.daily_streak, now) {
+        DailyClaim::Claimable { amount, streak } => (amount, streak),
+        DailyClaim::TooEarly { next_claim } => {
+            let _ = msg.reply(&ctx.http, format!("You already claimed your allowance, come back {}.", format_wait(next_claim - now))).await;
+            return Ok(());
+        }
+    };
+
+    match claim_daily(user_id, amount, streak, DAILY_COOLDOWN_HOURS).await {
+        Ok(DailyOutcome::Claimed { money }) => {
+            let mut builder = MessageBuilder::new();
+            builder
+                .push_line(format!("You claimed €{:.2}, your money: €{:.2}", amount, money))
+                .push_line(format!("Streak: {} {}", streak, if streak == 1 { "day" } else { "days" }));
+            if daily_amount(streak + 1) > amount {
+                builder.push_italic_line(format!("Come back tomorrow for €{:.2}.", daily_amount(streak + 1)));
+            }
+            let _ = msg.reply(&ctx.http, builder.build()).await;
+        },
+        Ok(DailyOutcome::NotClaimable) => {
+            // Another claim went through in the meantime
+            let _ = msg.reply(&ctx.http, "You already claimed your allowance.").await;
+        },
+        Ok(DailyOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(daily) Could not credit the allowance: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases(give)]
+#[description("Sends money to another user, after you confirm the transfer.")]
+#[example("!pay @Oscuro 50 for the pizza")]
+#[usage("!pay [@user] [amount] [optional note]")]
+#[min_args(2)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn pay(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let recipient: &User = match msg.mentions.first() {
+        Some(recipient) => recipient,
+        None => {
+            let _ = msg.reply(&ctx.http, "Please mention who to pay, for example: !pay @someone 50").await;
+            return Ok(());
+        }
+    };
+    if recipient.id == msg.author.id || recipient.bot {
+        let _ = msg.reply(&ctx.http, "You can only pay another user.").await;
+        return Ok(());
+    }
+
+    // The mention comes first
+    args.advance();
+    let amount: f64 = match args.current().and_then(parse_amount) {
+        Some(amount) => amount,
+        None => {
+            let _ = msg.reply(&ctx.http, "Please give a positive amount, for example: !pay @someone 50").await;
+            return Ok(());
+        }
+    };
+    args.advance();
+    let note: Option<String> = Some(args.rest().trim().to_string()).filter(|note| !note.is_empty());
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let _ = msg.reply(&ctx.http, format!(
+        "Send €{:.2} to {}? Answer \"yes\" within {} seconds to confirm.",
+        amount, recipient.name, PAY_CONFIRMATION_TIMEOUT.as_secs()
+    )).await;
+    let confirmed: bool = match msg.author.await_reply(ctx).channel_id(msg.channel_id).timeout(PAY_CONFIRMATION_TIMEOUT).await {
+        Some(answer) => matches!(answer.content.trim().to_lowercase().as_str(), "yes" | "y"),
+        None => false,
+    };
+    if !confirmed {
+        let _ = msg.reply(&ctx.http, "Transfer cancelled.").await;
+        return Ok(());
+    }
+
+    match transfer_money(msg.author.id.to_string(), recipient.id.to_string(), amount, note).await {
+        Ok(TransferOutcome::Transferred { sender_money, .. }) => {
+            let _ = msg.reply(&ctx.http, format!("Sent €{:.2} to {}, your money: €{:.2}", amount, recipient.name, sender_money)).await;
+        },
+        Ok(TransferOutcome::InsufficientFunds { money }) => {
+            let _ = msg.reply(&ctx.http, format!("Not enough money: you only have €{:.2}.", money.max(0.0))).await;
+        },
+        Ok(TransferOutcome::SenderNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Ok(TransferOutcome::RecipientNotFound) => {
+            let _ = msg.reply(&ctx.http, format!("{} is not registered yet.", recipient.name)).await;
+        },
+        Ok(TransferOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Transfers from or to a frozen account are not possible.").await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(pay) Could not transfer the money: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases(bal)]
+#[description("Shows your money and daily streak, or the ones of the mentioned user.")]
+#[example("!balance @Oscuro")]
+#[usage("!balance [optional @user]")]
+#[max_args(1)]
+#[help_available]
+pub async fn balance(ctx: &Context, msg: &Message) -> CommandResult {
+    let user: &User = msg.mentions.first().unwrap_or(&msg.author);
+
+    match get_daily_state(user.id.to_string()).await {
+        Ok(Some(state)) => {
+            let _ = msg.reply(&ctx.http, format!(
+                "{} has €{:.2} (daily streak: {}).",
+                user.name, state.money, state.daily_streak
+            )).await;
+        },
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, format!("{} is not registered yet.", user.name)).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(balance) Could not query the user: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
@@ -3,9 +3,17 @@ mod helpers;
 mod stocks;
 mod utilities;
 mod account;
+mod admin;
+mod seasons;
+mod economy;
+mod shop;
 
 pub use fun::*;
 pub use helpers::*;
 pub use stocks::*;
 pub use utilities::*;
 pub use account::*;
+pub use admin::*;
+pub use seasons::*;
+pub use economy::*;
+pub use shop::*;
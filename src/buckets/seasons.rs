@@ -0,0 +1,332 @@
+use chrono::{TimeZone, Utc};
+use chrono_tz::Europe::Brussels;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+use crate::constants::channels::ERRORS;
+use crate::constants::trading::SEASON_STANDINGS_SIZE;
+use crate::datastructs::CEmbedData;
+use crate::persistence::edge::requests::create::join_season;
+use crate::persistence::edge::requests::read::{get_active_season, get_guild_seasons, get_season_participants};
+use crate::persistence::edge::requests::update::{season_buy, season_sell, BuyOutcome, SellOutcome};
+use crate::persistence::edge_models::{PortfolioUser, Season, SeasonParticipant};
+use crate::utils::fx::get_fx_rate;
+use crate::utils::logging::db_log::*;
+use crate::utils::market_hours::market_status;
+use crate::utils::portfolio::{fetch_held_prices, net_worth, value_positions, PositionValuation};
+use crate::utils::seasons::{archived_standings, format_standings, rank_participants, RankedParticipant};
+use crate::utils::shortcuts::send_embed_or_discord_error;
+use crate::utils::stock_utils::{get_stock_price, normalize_ticker, parse_quantity};
+
+#[command]
+#[description("Trading seasons: a separate account everyone starts with the same balance, ranked when the season ends. Your main account is left untouched.")]
+#[example("!season buy $NVDA 3")]
+#[usage("!season [join|buy|sell|portfolio|standings|results [number]]")]
+#[only_in(guilds)]
+#[help_available]
+pub async fn season(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let arguments: Vec<&str> = args.raw().collect();
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+    let subcommand: String = arguments.first().map(|argument| argument.to_lowercase()).unwrap_or_default();
+
+    // Past seasons can be browsed without one running
+    if subcommand == "results" {
+        season_results(ctx, msg, guild_id, arguments.get(1).copied()).await;
+        return Ok(());
+    }
+
+    let season: Season = match get_active_season(guild_id).await {
+        Ok(Some(season)) => season,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "No season is running on this server. Past seasons: !season results").await;
+            return Ok(());
+        }
+        Err(err) => {
+            let _ = log_error(format!("(season) Could not query the running season: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    match subcommand.as_str() {
+        "join" => season_join(ctx, msg, &season).await,
+        "buy" | "sell" => season_trade(ctx, msg, &season, subcommand == "buy", &arguments[1..]).await,
+        "portfolio" => season_portfolio(ctx, msg, &season).await,
+        "standings" | "" => season_standings(ctx, msg, &season).await,
+        _ => {
+            let _ = msg.reply(&ctx.http, "Usage: !season [join|buy|sell|portfolio|standings|results [number]]").await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn season_join(ctx: &Context, msg: &Message, season: &Season) -> () {
+    let display_name: String = match msg.guild_id {
+        Some(guild_id) => msg.author.nick_in(&ctx, guild_id).await.unwrap_or(msg.author.name.clone()),
+        None => msg.author.name.clone(),
+    };
+
+    match join_season(season.number, msg.author.id.to_string(), display_name).await {
+        Ok(true) => {
+            let _ = msg.reply(&ctx.http, format!(
+                "Welcome to season #{}! Your season account starts with €{:.2}.",
+                season.number, season.starting_balance
+            )).await;
+        }
+        Ok(false) => {
+            let _ = msg.reply(&ctx.http, format!("You already take part in season #{}.", season.number)).await;
+        }
+        Err(err) => {
+            let _ = log_error(format!("(season) Could not join season #{}: {}.", season.number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+}
+
+async fn season_trade(ctx: &Context, msg: &Message, season: &Season, buying: bool, arguments: &[&str]) -> () {
+    let ticker: String = normalize_ticker(arguments.first().copied().unwrap_or_default());
+    let raw_quantity: &str = arguments.get(1).copied().unwrap_or_default();
+    // None means "sell everything"
+    let quantity: Option<f64> = parse_quantity(raw_quantity);
+
+    if ticker.is_empty() || (quantity.is_none() && (buying || !raw_quantity.eq_ignore_ascii_case("all"))) {
+        let _ = msg.reply(&ctx.http, "Usage: !season buy $NVDA 3 or !season sell $NVDA [quantity|all]").await;
+        return;
+    }
+
+    let stock_info = match get_stock_price(ticker.clone()).await {
+        Ok(stock_info) => stock_info,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return;
+        }
+    };
+    if !market_status(&stock_info, Utc::now()).is_open() {
+        let _ = msg.reply(&ctx.http, format!("The market of ${} is closed, season trades only go through while it is open.", ticker)).await;
+        return;
+    }
+    let fx_rate: f64 = match get_fx_rate(&stock_info.currency).await {
+        Ok(fx_rate) => fx_rate,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, format!("Could not convert {} into €: {}", stock_info.currency, error)).await;
+            return;
+        }
+    };
+    let user_id: String = msg.author.id.to_string();
+
+    if buying {
+        let quantity: f64 = quantity.unwrap_or_default();
+        let cost: f64 = quantity * stock_info.price * fx_rate;
+
+        match season_buy(season.number, user_id, ticker.clone(), quantity, stock_info.price, stock_info.currency.clone(), fx_rate).await {
+            Ok(BuyOutcome::Bought { remaining_money }) => {
+                let _ = msg.reply(&ctx.http, format!(
+                    "Season #{}: bought {} ${} at {:.2} {} for €{:.2}. Season money: €{:.2}",
+                    season.number, quantity, ticker, stock_info.price, stock_info.currency, cost, remaining_money
+                )).await;
+            }
+            Ok(BuyOutcome::InsufficientFunds { money, .. }) => {
+                let _ = msg.reply(&ctx.http, format!("Not enough season money: this costs €{:.2} but you only have €{:.2}.", cost, money)).await;
+            }
+            Ok(BuyOutcome::UserNotFound) => {
+                let _ = msg.reply(&ctx.http, "You do not take part in this season, or it is already over. Use !season join to take part.").await;
+            }
+            Ok(BuyOutcome::AccountFrozen) => {
+                let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+            }
+            // Only pending order fills can find their order closed
+            Ok(BuyOutcome::OrderClosed) => {}
+            Err(err) => {
+                let _ = log_error(format!("(season) Could not buy {} ${} in season #{}: {}.", quantity, ticker, season.number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                let _ = msg.reply(&ctx.http, "Could not complete the purchase, nothing was debited.").await;
+            }
+        }
+    } else {
+        match season_sell(season.number, user_id, ticker.clone(), quantity, stock_info.price, fx_rate).await {
+            Ok(SellOutcome::Sold { lots, proceeds, remaining_money }) => {
+                let sold: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                let profit: f64 = lots.iter().map(|lot| lot.profit).sum();
+                let _ = msg.reply(&ctx.http, format!(
+                    "Season #{}: sold {} ${} at {:.2} {} for €{:.2} (profit/loss €{:+.2}). Season money: €{:.2}",
+                    season.number, sold, ticker, stock_info.price, stock_info.currency, proceeds, profit, remaining_money
+                )).await;
+            }
+            Ok(SellOutcome::Oversold { owned }) => {
+                let _ = msg.reply(&ctx.http, format!("You cannot sell more than you own: you have {} ${} this season.", owned, ticker)).await;
+            }
+            Ok(SellOutcome::UserNotFound) => {
+                let _ = msg.reply(&ctx.http, "You do not take part in this season, or it is already over. Use !season join to take part.").await;
+            }
+            Ok(SellOutcome::AccountFrozen) => {
+                let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+            }
+            // Only pending order fills can find their order closed
+            Ok(SellOutcome::OrderClosed) => {}
+            Err(err) => {
+                let _ = log_error(format!("(season) Could not sell ${} in season #{}: {}.", ticker, season.number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                let _ = msg.reply(&ctx.http, "Could not complete the sale, nothing was sold.").await;
+            }
+        }
+    }
+}
+
+async fn season_portfolio(ctx: &Context, msg: &Message, season: &Season) -> () {
+    let participant: SeasonParticipant = match get_season_participants(season.number, Some(msg.author.id.to_string())).await {
+        Ok(participants) if !participants.is_empty() => participants[0].clone(),
+        Ok(_) => {
+            let _ = msg.reply(&ctx.http, "You do not take part in this season, or it is already over. Use !season join to take part.").await;
+            return;
+        }
+        Err(err) => {
+            let _ = log_error(format!("(season) Could not query the season account: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return;
+        }
+    };
+
+    let account: PortfolioUser = participant.as_portfolio_user();
+    let prices = fetch_held_prices(std::slice::from_ref(&account)).await;
+    let valuations: Vec<PositionValuation> = value_positions(&participant.lines, &prices);
+    let worth: f64 = net_worth(&account, &prices);
+
+    let mut builder = MessageBuilder::new();
+    if valuations.is_empty() {
+        builder.push_line("You do not own anything this season yet.");
+    }
+    for valuation in valuations.iter() {
+        builder.push_line(format!(
+            "${}: {} at {:.2} {} on average, worth €{:.2} ({:+.2}%)",
+            valuation.position.ticker,
+            valuation.position.quantity,
+            valuation.position.average_cost(),
+            valuation.position.currency,
+            valuation.market_value(),
+            valuation.unrealized_profit_percent()
+        ));
+    }
+    builder
+        .push_line("")
+        .push_line(format!("Season money: €{:.2}", participant.money))
+        .push_bold_line(format!(
+            "Net worth: €{:.2} ({:+.2}% since the start)",
+            worth,
+            if season.starting_balance > 0.0 { (worth / season.starting_balance - 1.0) * 100.0 } else { 0.0 }
+        ));
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("{}'s season #{} portfolio", msg.author.name, season.number);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
+
+async fn season_standings(ctx: &Context, msg: &Message, season: &Season) -> () {
+    let participants: Vec<SeasonParticipant> = match get_season_participants(season.number, None).await {
+        Ok(participants) => participants,
+        Err(err) => {
+            let _ = log_error(format!("(season) Could not query the participants: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return;
+        }
+    };
+
+    let accounts: Vec<PortfolioUser> = participants.iter().map(|participant| participant.as_portfolio_user()).collect();
+    let prices = fetch_held_prices(&accounts).await;
+    let ranked: Vec<RankedParticipant> = rank_participants(&participants, &prices);
+
+    let mut builder = MessageBuilder::new();
+    builder.push_line(format!(
+        "Started with €{:.2}, ends on {}.",
+        season.starting_balance,
+        format_epoch(season.ends_epoch)
+    ));
+    if ranked.is_empty() {
+        builder.push_line("Nobody joined yet, use !season join.");
+    } else {
+        builder.push_line(format_standings(&ranked, season.starting_balance, SEASON_STANDINGS_SIZE));
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("Season #{} standings", season.number);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
+
+/// Lists the finished seasons of the guild, or the final standings of one of them.
+async fn season_results(ctx: &Context, msg: &Message, guild_id: String, number: Option<&str>) -> () {
+    let seasons: Vec<Season> = match get_guild_seasons(guild_id, true).await {
+        Ok(seasons) => seasons,
+        Err(err) => {
+            let _ = log_error(format!("(season) Could not query the past seasons: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return;
+        }
+    };
+
+    let mut embed_data = CEmbedData::default();
+    let mut builder = MessageBuilder::new();
+
+    match number.map(|number| number.trim_start_matches('#').parse::<i64>()) {
+        None => {
+            embed_data.title = String::from("Past seasons");
+            if seasons.is_empty() {
+                builder.push_line("No season has ended on this server yet.");
+            }
+            for season in seasons.iter() {
+                builder.push_line(format!(
+                    "#{}: {} to {}, starting from €{:.2}",
+                    season.number,
+                    format_epoch(season.started_epoch),
+                    format_epoch(season.ends_epoch),
+                    season.starting_balance
+                ));
+            }
+            builder.push_italic_line("See the final standings of one with !season results [number].");
+        }
+        Some(Ok(number)) => {
+            let season: &Season = match seasons.iter().find(|season| season.number == number) {
+                Some(season) => season,
+                None => {
+                    let _ = msg.reply(&ctx.http, format!("No finished season #{} on this server.", number)).await;
+                    return;
+                }
+            };
+            let participants: Vec<SeasonParticipant> = match get_season_participants(number, None).await {
+                Ok(participants) => participants,
+                Err(err) => {
+                    let _ = log_error(format!("(season) Could not query the results of season #{}: {}.", number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                    return;
+                }
+            };
+            let standings: Vec<RankedParticipant> = archived_standings(&participants);
+
+            embed_data.title = format!("Season #{} final standings", number);
+            builder.push_line(format!(
+                "{} to {}, starting from €{:.2}",
+                format_epoch(season.started_epoch),
+                format_epoch(season.ends_epoch),
+                season.starting_balance
+            ));
+            if standings.is_empty() {
+                builder.push_line("Nobody took part.");
+            } else {
+                builder.push_line(format_standings(&standings, season.starting_balance, standings.len()));
+            }
+        }
+        Some(Err(_)) => {
+            let _ = msg.reply(&ctx.http, "Usage: !season results [number]").await;
+            return;
+        }
+    }
+
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
+
+fn format_epoch(epoch: f64) -> String {
+    match Brussels.timestamp_opt(epoch as i64, 0).single() {
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => String::from("?"),
+    }
+}
@@ -0,0 +1,462 @@
+use chrono_tz::Tz;
+use chrono_tz::Europe::Brussels;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+use super::economy::refuse_if_frozen;
+use crate::constants::channels::ERRORS;
+use crate::datastructs::CEmbedData;
+use crate::persistence::edge::requests::create::create_shop_item;
+use crate::persistence::edge::requests::delete::delete_shop_item;
+use crate::persistence::edge::requests::read::{get_discord_user_info, get_inventory, get_shop_items};
+use crate::persistence::edge::requests::update::{
+    buy_shop_item, edit_shop_item, refund_shop_purchase, use_consumable, ShopItemEdit, ShopPurchaseOutcome,
+};
+use crate::persistence::edge_models::{InventoryEntry, ShopItem};
+use crate::utils::economy::{parse_amount, parse_balance};
+use crate::utils::logging::db_log::*;
+use crate::utils::shop::{describe_inventory_entry, describe_item, normalize_item_name, parse_colour, ShopItemKind};
+use crate::utils::shortcuts::{delete_role_or_gone, send_embed_or_discord_error};
+
+#[command]
+#[description("Lists what can be bought with your money in this server's shop.")]
+#[example("!shop")]
+#[num_args(0)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn shop(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+
+    let items: Vec<ShopItem> = match get_shop_items(guild_id, false).await {
+        Ok(items) => items,
+        Err(err) => {
+            let _ = log_error(format!("(shop) Could not query the shop items: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    if items.is_empty() {
+        let _ = msg.reply(&ctx.http, "The shop of this server is empty.").await;
+        return Ok(());
+    }
+
+    let mut builder = MessageBuilder::new();
+    for item in items.iter() {
+        builder.push_line(describe_item(item));
+    }
+    builder.push_italic_line("Buy with !buy [item].");
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = String::from("Shop");
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+#[command]
+#[description("Buys an item of this server's shop with your money. Roles are given right away.")]
+#[example("!buy vip")]
+#[usage("!buy [item]")]
+#[num_args(1)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn buy(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id: GuildId = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+    let name: String = normalize_item_name(args.rest());
+    let user_id: String = msg.author.id.to_string();
+
+    let (item, remaining_money) = match buy_shop_item(guild_id.to_string(), user_id.clone(), name.clone()).await {
+        Ok(ShopPurchaseOutcome::Bought { item, remaining_money }) => (item, remaining_money),
+        Ok(ShopPurchaseOutcome::ItemNotFound) => {
+            let _ = msg.reply(&ctx.http, format!("There is no \"{}\" in the shop, see !shop.", name)).await;
+            return Ok(());
+        },
+        Ok(ShopPurchaseOutcome::SoldOut) => {
+            let _ = msg.reply(&ctx.http, format!("\"{}\" is sold out.", name)).await;
+            return Ok(());
+        },
+        Ok(ShopPurchaseOutcome::AlreadyOwned) => {
+            let _ = msg.reply(&ctx.http, format!("You already own \"{}\".", name)).await;
+            return Ok(());
+        },
+        Ok(ShopPurchaseOutcome::InsufficientFunds { money }) => {
+            let _ = msg.reply(&ctx.http, format!("Not enough money: you only have €{:.2}.", money.max(0.0))).await;
+            return Ok(());
+        },
+        Ok(ShopPurchaseOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            return Ok(());
+        },
+        Ok(ShopPurchaseOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            let _ = log_error(format!("(buy) Could not buy the shop item: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let kind: Option<ShopItemKind> = ShopItemKind::parse(&item.kind);
+    if matches!(kind, Some(kind) if kind.gives_role()) {
+        let given: bool = match kind {
+            Some(ShopItemKind::Colour) => wear_colour(ctx, guild_id, msg, &item.name, item.role_id.as_ref()).await,
+            _ => give_role(ctx, guild_id, msg, item.role_id.as_ref()).await,
+        };
+
+        // The money is only kept once the role is there
+        if !given {
+            if let Err(err) = refund_shop_purchase(guild_id.to_string(), user_id, item.clone()).await {
+                let _ = log_error(format!("(buy) Could not refund \"{}\" to {}: {}.", item.name, msg.author.id, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+            let _ = msg.reply(&ctx.http, "Could not give you the role of this item, you were refunded.").await;
+            return Ok(());
+        }
+    }
+
+    let _ = msg.reply(&ctx.http, format!("You bought \"{}\" for €{:.2}, your money: €{:.2}", item.name, item.price, remaining_money)).await;
+
+    Ok(())
+}
+
+#[command]
+#[aliases(inv)]
+#[description("Shows what you bought in this server's shop.")]
+#[example("!inventory")]
+#[num_args(0)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn inventory(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+
+    let entries: Vec<InventoryEntry> = match get_inventory(msg.author.id.to_string(), guild_id).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            let _ = log_error(format!("(inventory) Could not query the inventory: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    if entries.is_empty() {
+        let _ = msg.reply(&ctx.http, "Your inventory is empty, see !shop.").await;
+        return Ok(());
+    }
+
+    let timezone: Tz = match get_discord_user_info(msg.author.id.to_string()).await {
+        Ok(Some(user)) => user.timezone.parse().unwrap_or(Brussels),
+        _ => Brussels,
+    };
+    let mut builder = MessageBuilder::new();
+    for entry in entries.iter() {
+        builder.push_line(describe_inventory_entry(entry, &timezone));
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("{}'s inventory", msg.author.name);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+#[command("use")]
+#[description("Uses a consumable of your inventory, or wears one of your colours.")]
+#[example("!use cookie")]
+#[usage("!use [item]")]
+#[num_args(1)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn use_item(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id: GuildId = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    let name: String = normalize_item_name(args.rest());
+
+    let entries: Vec<InventoryEntry> = match get_inventory(msg.author.id.to_string(), guild_id.to_string()).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            let _ = log_error(format!("(use) Could not query the inventory: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    let entry: &InventoryEntry = match entries.iter().find(|entry| entry.item_name == name) {
+        Some(entry) => entry,
+        None => {
+            let _ = msg.reply(&ctx.http, format!("You do not own \"{}\", see !inventory.", name)).await;
+            return Ok(());
+        }
+    };
+
+    match ShopItemKind::parse(&entry.kind) {
+        Some(ShopItemKind::Consumable) => {
+            match use_consumable(msg.author.id.to_string(), guild_id.to_string(), name.clone()).await {
+                Ok(Some(left)) => {
+                    let _ = msg.channel_id.say(&ctx.http, format!("{} used a {} ({} left).", msg.author.name, name, left)).await;
+                },
+                Ok(None) => {
+                    let _ = msg.reply(&ctx.http, format!("You have no \"{}\" left.", name)).await;
+                },
+                Err(err) => {
+                    let _ = log_error(format!("(use) Could not use the consumable: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                }
+            }
+        },
+        Some(ShopItemKind::Colour) => {
+            if wear_colour(ctx, guild_id, msg, &name, entry.role_id.as_ref()).await {
+                let _ = msg.reply(&ctx.http, format!("You now wear {}.", name)).await;
+            } else {
+                let _ = msg.reply(&ctx.http, "Could not give you this colour.").await;
+            }
+        },
+        _ => {
+            let _ = msg.reply(&ctx.http, "Roles are given when bought, there is nothing to use.").await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn give_role(ctx: &Context, guild_id: GuildId, msg: &Message, role_id: Option<&String>) -> bool {
+    let role_id: u64 = match role_id.and_then(|role_id| role_id.parse::<u64>().ok()) {
+        Some(role_id) => role_id,
+        None => return false,
+    };
+
+    match ctx.http.add_member_role(guild_id.into(), msg.author.id.into(), role_id, Some("Bought in the shop")).await {
+        Ok(_) => true,
+        Err(err) => {
+            let _ = log_error(format!("(shop) Could not give role {} to {}: {}.", role_id, msg.author.id, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            false
+        }
+    }
+}
+
+/// Gives the role of a colour item and takes the other owned colours off, so that one colour is worn at a time.
+async fn wear_colour(ctx: &Context, guild_id: GuildId, msg: &Message, name: &str, role_id: Option<&String>) -> bool {
+    if let Ok(entries) = get_inventory(msg.author.id.to_string(), guild_id.to_string()).await {
+        let other_colours = entries
+            .iter()
+            .filter(|entry| entry.kind == ShopItemKind::Colour.to_string() && entry.item_name != name)
+            .filter_map(|entry| entry.role_id.as_ref().and_then(|role_id| role_id.parse::<u64>().ok()));
+        for other_role in other_colours {
+            let _ = ctx.http.remove_member_role(guild_id.into(), msg.author.id.into(), other_role, Some("Changed colour")).await;
+        }
+    }
+
+    give_role(ctx, guild_id, msg, role_id).await
+}
+
+#[command]
+#[description("Manages the shop of this server. Colour items get a new role of that colour, the other roles must exist already. Items can be edited (price, description, stock, on/off) or removed, what was already bought is kept except colours, whose role is deleted.")]
+#[example("!shop_item add temp_role booster 250 @Booster 24 Shiny name for a day")]
+#[usage("!shop_item add role|temp_role|colour|consumable [name] [price] [@role|@role hours|#hex|] [description], !shop_item edit [name] price|description|stock|enabled [value], !shop_item remove [name], !shop_item list")]
+#[min_args(1)]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[help_available]
+pub async fn shop_item(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id: GuildId = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    let action: String = args.single::<String>().unwrap_or_default().to_lowercase();
+
+    match action.as_str() {
+        "add" => add_shop_item(ctx, msg, guild_id, args).await,
+        "edit" => {
+            let name: String = normalize_item_name(&args.single::<String>().unwrap_or_default());
+            let field: String = args.single::<String>().unwrap_or_default().to_lowercase();
+            let value: String = args.rest().trim().to_string();
+            let edit: Option<ShopItemEdit> = match field.as_str() {
+                "price" => parse_amount(&value).map(ShopItemEdit::Price),
+                "description" => Some(ShopItemEdit::Description(value.clone())),
+                "stock" if value.eq_ignore_ascii_case("unlimited") => Some(ShopItemEdit::Stock(None)),
+                "stock" => value.parse::<i64>().ok().filter(|stock| *stock >= 0).map(|stock| ShopItemEdit::Stock(Some(stock))),
+                "enabled" => match value.to_lowercase().as_str() {
+                    "on" | "yes" | "true" => Some(ShopItemEdit::Enabled(true)),
+                    "off" | "no" | "false" => Some(ShopItemEdit::Enabled(false)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match edit {
+                Some(edit) => match edit_shop_item(guild_id.to_string(), name.clone(), edit).await {
+                    Ok(true) => {
+                        let _ = msg.reply(&ctx.http, format!("\"{}\" was updated.", name)).await;
+                    },
+                    Ok(false) => {
+                        let _ = msg.reply(&ctx.http, format!("There is no \"{}\" in the shop.", name)).await;
+                    },
+                    Err(err) => {
+                        let _ = log_error(format!("(shop_item) Could not edit the item: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                    }
+                },
+                None => {
+                    let _ = msg.reply(&ctx.http, "Usage: !shop_item edit [name] price [amount], description [text], stock [number|unlimited] or enabled [on|off]").await;
+                }
+            }
+        },
+        "remove" | "delete" => {
+            let name: String = normalize_item_name(args.rest());
+            if !delete_colour_role(ctx, msg, guild_id, &name).await {
+                return Ok(());
+            }
+            match delete_shop_item(guild_id.to_string(), name.clone()).await {
+                Ok(true) => {
+                    let _ = msg.reply(&ctx.http, format!("\"{}\" was removed from the shop.", name)).await;
+                },
+                Ok(false) => {
+                    let _ = msg.reply(&ctx.http, format!("There is no \"{}\" in the shop.", name)).await;
+                },
+                Err(err) => {
+                    let _ = log_error(format!("(shop_item) Could not remove the item: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                }
+            }
+        },
+        "list" => match get_shop_items(guild_id.to_string(), true).await {
+            Ok(items) => {
+                let mut builder = MessageBuilder::new();
+                if items.is_empty() {
+                    builder.push_line("The shop is empty.");
+                }
+                for item in items.iter() {
+                    builder.push_line(describe_item(item));
+                }
+                let mut embed_data = CEmbedData::default();
+                embed_data.title = String::from("Shop items");
+                embed_data.description = builder.build();
+                send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+            },
+            Err(err) => {
+                let _ = log_error(format!("(shop_item) Could not query the shop items: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        },
+        _ => {
+            let _ = msg.reply(&ctx.http, "Usage: !shop_item add, edit, remove or list. See !help shop_item.").await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes the role made for a colour item, before the item itself. False when the item could not be looked up
+/// or the role could not be deleted, the item is kept then.
+async fn delete_colour_role(ctx: &Context, msg: &Message, guild_id: GuildId, name: &str) -> bool {
+    let items: Vec<ShopItem> = match get_shop_items(guild_id.to_string(), true).await {
+        Ok(items) => items,
+        Err(err) => {
+            let _ = log_error(format!("(shop_item) Could not query the shop items: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return false;
+        }
+    };
+    let colour_role: Option<u64> = items
+        .iter()
+        .find(|item| item.name == name && item.kind == ShopItemKind::Colour.to_string())
+        .and_then(|item| item.role_id.as_ref())
+        .and_then(|role_id| role_id.parse::<u64>().ok());
+
+    if let Some(role_id) = colour_role {
+        if let Err(err) = delete_role_or_gone(ctx, guild_id.into(), role_id).await {
+            let _ = log_error(format!("(shop_item) Could not delete the colour role {}: {}.", role_id, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not delete the role of this colour, check my permissions. The item was kept.").await;
+            return false;
+        }
+    }
+
+    true
+}
+
+async fn add_shop_item(ctx: &Context, msg: &Message, guild_id: GuildId, mut args: Args) -> () {
+    let usage: &str = "Usage: !shop_item add role [name] [price] @role, temp_role [name] [price] @role [hours], colour [name] [price] #hex or consumable [name] [price], followed by an optional description";
+
+    let kind: Option<ShopItemKind> = ShopItemKind::parse(&args.single::<String>().unwrap_or_default());
+    let name: String = normalize_item_name(&args.single::<String>().unwrap_or_default());
+    let price: Option<f64> = parse_balance(&args.single::<String>().unwrap_or_default());
+    let (kind, price) = match (kind, price) {
+        (Some(kind), Some(price)) if !name.is_empty() => (kind, price),
+        _ => {
+            let _ = msg.reply(&ctx.http, usage).await;
+            return;
+        }
+    };
+
+    let mut role_id: Option<String> = None;
+    let mut duration_hours: Option<i64> = None;
+    let mut colour: Option<i64> = None;
+    match kind {
+        ShopItemKind::Role | ShopItemKind::TempRole => {
+            // The role mention comes first
+            role_id = msg.mention_roles.first().map(|role_id| role_id.to_string());
+            args.advance();
+            if kind == ShopItemKind::TempRole {
+                duration_hours = args.single::<i64>().ok().filter(|hours| *hours > 0);
+                if duration_hours.is_none() {
+                    role_id = None;
+                }
+            }
+        },
+        ShopItemKind::Colour => {
+            let parsed: Option<u32> = parse_colour(&args.single::<String>().unwrap_or_default());
+            if let Some(parsed) = parsed {
+                match guild_id.create_role(&ctx.http, |role| role.name(&name).colour(parsed as u64)).await {
+                    Ok(role) => {
+                        role_id = Some(role.id.to_string());
+                        colour = Some(parsed as i64);
+                    },
+                    Err(err) => {
+                        let _ = log_error(format!("(shop_item) Could not create the colour role: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                        let _ = msg.reply(&ctx.http, "Could not create the role of this colour, check my permissions.").await;
+                        return;
+                    }
+                }
+            }
+        },
+        ShopItemKind::Consumable => {},
+    }
+    if kind.gives_role() && role_id.is_none() {
+        let _ = msg.reply(&ctx.http, usage).await;
+        return;
+    }
+
+    let description: Option<String> = Some(args.rest().trim().to_string()).filter(|description| !description.is_empty());
+    let item = ShopItem {
+        name: name.clone(),
+        kind: kind.to_string(),
+        price,
+        description,
+        role_id,
+        duration_hours,
+        colour,
+        stock: None,
+        enabled: true,
+    };
+
+    match create_shop_item(guild_id.to_string(), item).await {
+        Ok(true) => {
+            let _ = msg.reply(&ctx.http, format!("\"{}\" is now in the shop for €{:.2}.", name, price)).await;
+        },
+        Ok(false) => {
+            let _ = msg.reply(&ctx.http, format!("The shop already has a \"{}\".", name)).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(shop_item) Could not add the item: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+}
@@ -1,62 +1,200 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::{Europe::Brussels, Tz};
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
-use serenity::model::channel::Message;
+use serenity::model::channel::{AttachmentType, Message};
 use serenity::prelude::*;
 use serenity::utils::MessageBuilder;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
-use crate::persistence::edge::requests::read::get_discord_user_info;
+use super::economy::refuse_if_frozen;
+use crate::constants::channels::ERRORS;
+use crate::constants::trading::{EARNINGS_WEEK_DAYS, LEADERBOARD_PAGE_SIZE, MAX_COMPARED_TICKERS, STARTING_MONEY};
+use crate::datastructs::stocks_config::{read_stocks_config, OffHoursTrades};
+use crate::datastructs::CEmbedData;
+use crate::persistence::edge_models::{PortfolioLines, PortfolioUser, WatchlistEntry};
+use crate::persistence::edge::requests::create::{create_pending_order, create_price_alert, create_watchlist_entry};
+use crate::persistence::edge::requests::delete::{delete_price_alert, delete_watchlist_entry};
+use crate::persistence::edge::requests::read::{
+    get_all_portfolios, get_all_watchlist_entries, get_discord_user_info, get_ledger_flows_since, get_pending_orders, get_price_alerts,
+    get_purchase_points, get_snapshots_since, get_user_portfolio, get_user_position_snapshots, get_user_snapshots, get_user_transactions,
+    get_watchlist,
+};
+use crate::persistence::edge::requests::update::{
+    buy_stock_line, cancel_pending_order, cover_short_position, open_short_position, sell_stock_lines,
+    BuyOutcome, CoverOutcome, SellOutcome, ShortOutcome,
+};
+use crate::utils::alerts::AlertCondition;
+use crate::utils::charts::{render_price_chart, render_value_chart, BuyPoint, ChartStyle};
+use crate::utils::earnings::{followers_by_ticker, upcoming_calls, EarningsCall};
+use crate::utils::fx::get_fx_rate;
+use crate::utils::indicators::{
+    summarize, TechnicalSummary, BOLLINGER_WIDTH, BOLLINGER_WINDOW, MACD_FAST, MACD_SIGNAL, MACD_SLOW, RSI_PERIOD,
+};
+use crate::utils::ledger::{format_transaction_line, transactions_to_csv};
 use crate::utils::logging::db_log::*;
-use crate::utils::stock_utils::{epoch_to_date, get_stock_price};
+use crate::utils::margin::{guild_margin_rules, margin_account, trade_margin_rules, MarginAccount, MarginRules, MarginState};
+use crate::utils::market_hours::{describe_market_status, market_status, MarketStatus};
+use crate::utils::orders::{parse_order_request, OrderRequest, OrderSide, OrderType};
+use crate::utils::performance::{
+    max_drawdown, position_returns, return_index, time_weighted_return, DailyValue, PositionReturn, EXTERNAL_FLOW_KINDS,
+};
+use crate::utils::period::{parse_period, Period};
+use crate::utils::portfolio::{
+    aggregate_positions, fetch_held_prices, net_worth, value_positions, value_shorts, HeldPrice, PositionValuation, ShortValuation,
+};
+use crate::utils::quotes::{quote_provider, AssetClass, Candle, HistoryRange, QuoteError};
+use crate::utils::shortcuts::send_embed_or_discord_error;
+use crate::utils::symbol_search::{describe_match, format_disambiguation, lookup_quotes, QuoteLookup, DISAMBIGUATION_SIZE};
+use crate::utils::stock_utils::{
+    epoch_to_date, format_comparison_table, format_quote_details, format_quotes_table, get_stock_history,
+    get_stock_price, get_stock_prices, get_stock_quotes, normalize_ticker, parse_quantity, StockInfo,
+};
 
 #[command]
-#[description = "Get the stock price of a given stock ticket."]
-#[usage = "!ticker $[stock ticker]"]
-#[example = "$AAPL"]
+#[description = "Get the stock price of a given stock ticket, or of a company name. Several tickers are compared side by side, --full shows the detailed figures (P/E, EPS, market cap, moving averages)."]
+#[usage = "!ticker $[stock ticker or company name] [more tickers] [--full]"]
+#[example = "$AAPL MSFT NVDA"]
 #[aliases("stock", "ticker")]
 pub async fn stocks(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let mut stock_name: String = args.rest().into();
+    let mut full: bool = false;
+    let mut words: Vec<&str> = vec![];
+    let mut tickers: Vec<String> = vec![];
 
-    // If stock_name starts with a "$", remove it.
-    if stock_name.starts_with("$") {
-        stock_name.remove(0);
+    for arg in args.raw() {
+        if arg.eq_ignore_ascii_case("--full") {
+            full = true;
+            continue;
+        }
+        words.push(arg);
+        let ticker: String = normalize_ticker(arg);
+        if !ticker.is_empty() && !tickers.contains(&ticker) {
+            tickers.push(ticker);
+        }
     }
 
-    if stock_name.is_empty() {
+    if tickers.is_empty() {
         msg.channel_id
             .say(&ctx.http, "Please provide a stock name.")
             .await?;
         return Ok(());
     }
+    if tickers.len() > MAX_COMPARED_TICKERS {
+        let _ = msg.reply(&ctx.http, format!("Compare at most {} tickers at once.", MAX_COMPARED_TICKERS)).await;
+        return Ok(());
+    }
 
-    let stock_price = get_stock_price(stock_name.clone()).await;
+    // Company names are resolved to tickers, ambiguous ones are listed for the user to pick
+    let lookup: QuoteLookup = match lookup_quotes(&tickers, &words.join(" ")).await {
+        Ok(lookup) => lookup,
+        Err(error) => {
+            msg.channel_id.say(&ctx.http, error.to_string()).await?;
+            return Ok(());
+        }
+    };
+    for (query, candidates) in lookup.ambiguous.iter() {
+        let _ = msg.reply(&ctx.http, format_disambiguation(query, candidates)).await;
+    }
+    if lookup.quotes.is_empty() {
+        if lookup.ambiguous.is_empty() {
+            msg.channel_id
+                .say(&ctx.http, QuoteError::NotFound(words.join(" ")).to_string())
+                .await?;
+        }
+        return Ok(());
+    }
 
-    // If stock_price is an error, return the error message
-    if stock_price.is_err() {
-        msg.channel_id
-            .say(&ctx.http, &stock_price.unwrap_err())
-            .await?;
+    if lookup.quotes.len() > 1 || full {
+        compare_stocks(ctx, msg, &lookup, full).await;
         return Ok(());
+    }
+    let stock_price: StockInfo = lookup.quotes[0].clone();
+
+    // Transform stock_price into a structured string (each field on a new line), with the following format: field: value
+    let stock_answer = if stock_price.asset_class() == AssetClass::Crypto {
+        // No analysts, earnings or exchange for crypto, and it trades around the clock
+        format!(
+            "Crypto info for ${}\nName: {}\nCurrent Trade Price: {} {}\nToday Price Change %: {:.3}\nDay Range: {:.2} - {:.2}",
+            stock_price.ticker,
+            stock_price.name,
+            stock_price.price,
+            stock_price.currency,
+            stock_price.regular_market_change_percent,
+            stock_price.regular_market_day_low,
+            stock_price.regular_market_day_high
+        )
     } else {
-        // Transform stock_price into a structured string (each field on a new line), with the following format: field: value
-        let stock_answer = match stock_price {
-            Ok(stock_price) => {
-                format!(
-                    "Stock info for ${}\nName: {}\nCurrent Trade Price: ${}\nToday Price Change %: {}\nAnalysts Sentiment: {}\nEarning Call Date: {}\nExchange: {}\nCurrency: {}",
-                    stock_price.ticker,
-                    stock_price.name,
-                    stock_price.price,
-                    // Round the price change to 3 decimal places
-                    format!("{:.3}", stock_price.regular_market_change_percent),
-                    stock_price.rating,
-                    epoch_to_date(stock_price.earning_call_date),
-                    stock_price.full_exchange_name,
-                    stock_price.currency
-                )
-            }
-            Err(error) => error,
-        };
-        // Send the stock price to the channel
-        msg.reply(&ctx.http, &stock_answer).await?;
+        let timezone: Tz = get_user_timezone(msg.author.id.to_string()).await;
+        format!(
+            "Stock info for ${}\nName: {}\nCurrent Trade Price: ${}\nToday Price Change %: {}\nAnalysts Sentiment: {}\nEarning Call Date: {}\nExchange: {}\nMarket: {}\nCurrency: {}",
+            stock_price.ticker,
+            stock_price.name,
+            stock_price.price,
+            // Round the price change to 3 decimal places
+            format!("{:.3}", stock_price.regular_market_change_percent),
+            stock_price.rating,
+            epoch_to_date(stock_price.earning_call_date),
+            stock_price.full_exchange_name,
+            describe_market_status(&market_status(&stock_price, Utc::now()), &timezone),
+            stock_price.currency
+        )
+    };
+    // Send the stock price to the channel
+    msg.reply(&ctx.http, &stock_answer).await?;
+
+    Ok(())
+}
+
+/// Shows the quotes found as a comparison table, or one detailed block per ticker.
+async fn compare_stocks(ctx: &Context, msg: &Message, lookup: &QuoteLookup, full: bool) -> () {
+    let mut builder = MessageBuilder::new();
+    if full {
+        for quote in lookup.quotes.iter() {
+            builder
+                .push_bold_line(format!("${} - {}", quote.ticker, quote.name))
+                .push_line(format_quote_details(quote))
+                .push_line("");
+        }
+    } else {
+        builder.push(format_comparison_table(&lookup.quotes));
+    }
+
+    if !lookup.missing.is_empty() {
+        builder.push_italic_line(format!("Not found: {}", lookup.missing.join(", ")));
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = lookup.quotes.iter().map(|quote| format!("${}", quote.ticker)).collect::<Vec<String>>().join(" vs ");
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+}
+
+#[command]
+#[aliases(search, lookup)]
+#[description("Finds the tickers matching a company name.")]
+#[example("!search_ticker apple")]
+#[usage("!search_ticker [company name]")]
+#[min_args(1)]
+#[help_available]
+pub async fn search_ticker(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query: &str = args.rest();
+
+    match quote_provider().search(query).await {
+        Ok(matches) if matches.is_empty() => {
+            let _ = msg.reply(&ctx.http, format!("Nothing matches \"{}\".", query.trim())).await;
+        }
+        Ok(matches) => {
+            let lines: Vec<String> = matches
+                .iter()
+                .take(DISAMBIGUATION_SIZE * 2)
+                .map(|candidate| format!("- {}", describe_match(candidate)))
+                .collect();
+            let _ = msg.reply(&ctx.http, lines.join("\n")).await;
+        }
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error.to_string()).await;
+        }
     }
 
     Ok(())
@@ -66,34 +204,613 @@ pub async fn stocks(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
 
 #[command]
 #[aliases(buyStock)]
+#[description("Buy shares of a stock, or crypto written as a pair such as BTC-EUR, with your wallet money at the current trade price. Fractional quantities are accepted.")]
 #[example = "!buy_stock $NVDA 3.1416"]
 #[usage = "!buy_stock $NVDA 3.1416"]
 #[num_args(2)]
 #[help_available]
-pub async fn buy_stock(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    // Needed arguments: ticker amount (float accepted)
-    // Example: !buy_stock $NVDA 9.15
+pub async fn buy_stock(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let ticker: String = normalize_ticker(&args.single::<String>().unwrap_or_default());
+    let quantity: Option<f64> = parse_quantity(&args.single::<String>().unwrap_or_default());
+
+    if ticker.is_empty() || quantity.is_none() {
+        let _ = msg.reply(&ctx.http, "Usage: !buy_stock $NVDA 3.1416 (the quantity must be a positive number).").await;
+        return Ok(());
+    }
+    let quantity: f64 = quantity.unwrap();
 
     // Get the ticker price
+    let stock_info = match get_stock_price(ticker.clone()).await {
+        Ok(stock_info) => stock_info,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+    let status: MarketStatus = market_status(&stock_info, Utc::now());
+    if !status.is_open() {
+        handle_closed_market(ctx, msg, OrderSide::Buy, &ticker, Some(quantity), &status).await;
+        return Ok(());
+    }
+
+    let fx_rate: f64 = match get_fx_rate(&stock_info.currency).await {
+        Ok(fx_rate) => fx_rate,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, format!("Could not convert {} into €: {}", stock_info.currency, error)).await;
+            return Ok(());
+        }
+    };
+    let cost: f64 = quantity * stock_info.price * fx_rate;
+
+    // Guilds allowing margin let the money go below zero, up to the initial requirement
+    let guild_id: Option<String> = msg.guild_id.map(|guild_id| guild_id.to_string());
+    let mut rules: MarginRules = guild_margin_rules(guild_id.clone()).await;
+    let mut prices: HashMap<String, HeldPrice> = HashMap::new();
+    if rules.margin {
+        if let Ok(Some(user)) = get_user_portfolio(msg.author.id.to_string()).await {
+            rules = trade_margin_rules(&user, guild_id.clone()).await;
+            prices = fetch_held_prices(std::slice::from_ref(&user)).await;
+        }
+    }
+    // Applied to the account as it is when the purchase goes through
+    let credit_limit = |user: &PortfolioUser| margin_account(user, &prices).credit_limit(&rules);
 
-    // Check if enough money in user account
+    // Check if enough money in user account & perform the operation
+    match buy_stock_line(msg.author.id.to_string(), guild_id, ticker.clone(), quantity, stock_info.price, stock_info.currency.clone(), fx_rate, None, credit_limit).await {
+        Ok(BuyOutcome::Bought { remaining_money }) => {
+            let mut builder = MessageBuilder::new();
+            builder
+                .push_line(format!("Bought {} ${} at {:.2} {} for a total of €{:.2}.", quantity, ticker, stock_info.price, stock_info.currency, cost))
+                .push_line(format!("Your money: €{:.2}", remaining_money));
 
-    // Perform the operation
+            if remaining_money < 0.0 {
+                builder.push_italic_line(format!(
+                    "Bought on margin: €{:.2} borrowed at {:.2}% a year. See !margin.",
+                    -remaining_money,
+                    rules.interest_rate * 100.0
+                ));
+            }
+            let _ = msg.reply(&ctx.http, builder.build()).await;
+        },
+        Ok(BuyOutcome::InsufficientFunds { money, credit_limit }) => {
+            let reply = if credit_limit > 0.0 {
+                format!("Not enough buying power: this costs €{:.2} but you only have €{:.2} plus €{:.2} of margin.", cost, money, credit_limit)
+            } else {
+                format!("Not enough money: this costs €{:.2} but you only have €{:.2}.", cost, money)
+            };
+            let _ = msg.reply(&ctx.http, reply).await;
+        },
+        Ok(BuyOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Ok(BuyOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+        },
+        // Only pending order fills can find their order closed
+        Ok(BuyOutcome::OrderClosed) => {},
+        Err(err) => {
+            let _ = log_error(format!("(buy_stock) Could not buy {} ${}: {}.", quantity, ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not complete the purchase, nothing was debited.").await;
+        }
+    }
 
-    unimplemented!()
-    //Ok(())
+    Ok(())
 }
 
 #[command]
-pub async fn sell_stock(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    unimplemented!()
-    // Ok(())
+#[aliases(sellStock)]
+#[description("Sell shares of a stock at the current trade price. Your oldest shares are sold first.")]
+#[example = "!sell_stock $NVDA 2"]
+#[usage = "!sell_stock $NVDA [quantity|all]"]
+#[num_args(2)]
+#[help_available]
+pub async fn sell_stock(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let ticker: String = normalize_ticker(&args.single::<String>().unwrap_or_default());
+    let raw_quantity: String = args.single::<String>().unwrap_or_default();
+    // None means "sell everything"
+    let quantity: Option<f64> = parse_quantity(&raw_quantity);
+
+    if ticker.is_empty() || (quantity.is_none() && !raw_quantity.eq_ignore_ascii_case("all")) {
+        let _ = msg.reply(&ctx.http, "Usage: !sell_stock $NVDA 2 (or \"all\" to sell every share you own).").await;
+        return Ok(());
+    }
+
+    let stock_info = match get_stock_price(ticker.clone()).await {
+        Ok(stock_info) => stock_info,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+
+    let status: MarketStatus = market_status(&stock_info, Utc::now());
+    if !status.is_open() {
+        handle_closed_market(ctx, msg, OrderSide::Sell, &ticker, quantity, &status).await;
+        return Ok(());
+    }
+
+    let fx_rate: f64 = match get_fx_rate(&stock_info.currency).await {
+        Ok(fx_rate) => fx_rate,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, format!("Could not convert {} into €: {}", stock_info.currency, error)).await;
+            return Ok(());
+        }
+    };
+
+    match sell_stock_lines(msg.author.id.to_string(), ticker.clone(), quantity, stock_info.price, stock_info.currency.clone(), fx_rate, None).await {
+        Ok(SellOutcome::Sold { lots, proceeds, remaining_money }) => {
+            let sold: f64 = lots.iter().map(|lot| lot.quantity).sum();
+            let total_profit: f64 = lots.iter().map(|lot| lot.profit).sum();
+            let total_fx_profit: f64 = lots.iter().map(|lot| lot.fx_profit).sum();
+            let mut builder = MessageBuilder::new();
+
+            builder.push_line(format!("Sold {} ${} at {:.2} {} for a total of €{:.2}.", sold, ticker, stock_info.price, stock_info.currency, proceeds));
+            for lot in lots.iter() {
+                builder.push_line(format!(
+                    "- {} bought at {:.2} {}: {:+.2} (price {:+.2}, FX {:+.2})",
+                    lot.quantity, lot.bought_at, stock_info.currency, lot.profit, lot.price_profit, lot.fx_profit
+                ));
+            }
+            builder
+                .push_bold_line(format!("Realized profit/loss: €{:+.2} (of which FX €{:+.2})", total_profit, total_fx_profit))
+                .push_line(format!("Your money: €{:.2}", remaining_money));
+
+            let _ = msg.reply(&ctx.http, builder.build()).await;
+        },
+        Ok(SellOutcome::Oversold { owned }) => {
+            let _ = msg.reply(&ctx.http, format!("You cannot sell more than you own: you have {} ${}.", owned, ticker)).await;
+        },
+        Ok(SellOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Ok(SellOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+        },
+        // Only pending order fills can find their order closed
+        Ok(SellOutcome::OrderClosed) => {},
+        Err(err) => {
+            let _ = log_error(format!("(sell_stock) Could not sell ${}: {}.", ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not complete the sale, nothing was sold.").await;
+        }
+    }
+
+    Ok(())
 }
 
 #[command]
-pub async fn consult_portfolio(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    unimplemented!()
-    //Ok(())
+#[description("Sell borrowed shares of a stock, to buy them back later (hopefully cheaper) with !cover. Only in guilds allowing short selling, and you must keep enough equity to cover the initial margin.")]
+#[example = "!short $NVDA 2"]
+#[usage = "!short $NVDA 2"]
+#[num_args(2)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn short(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let ticker: String = normalize_ticker(&args.single::<String>().unwrap_or_default());
+    let quantity: Option<f64> = parse_quantity(&args.single::<String>().unwrap_or_default());
+
+    if ticker.is_empty() || quantity.is_none() {
+        let _ = msg.reply(&ctx.http, "Usage: !short $NVDA 2 (the quantity must be a positive number).").await;
+        return Ok(());
+    }
+    let quantity: f64 = quantity.unwrap();
+
+    let guild_id: String = match msg.guild_id {
+        Some(guild_id) => guild_id.to_string(),
+        None => return Ok(()),
+    };
+    let rules: MarginRules = guild_margin_rules(Some(guild_id.clone())).await;
+    if !rules.short_selling {
+        let _ = msg.reply(&ctx.http, "Short selling is disabled on this server, an admin can enable it with !trading_rules.").await;
+        return Ok(());
+    }
+
+    let portfolio_user = match get_user_portfolio(msg.author.id.to_string()).await {
+        Ok(Some(portfolio_user)) => portfolio_user,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            let _ = log_error(format!("(short) Could not query the portfolio: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+    let holds_ticker: bool = portfolio_user
+        .portfolio
+        .as_ref()
+        .map(|portfolio| portfolio.lines.iter().any(|line| line.ticker == ticker))
+        .unwrap_or(false);
+    if holds_ticker {
+        let _ = msg.reply(&ctx.http, format!("You own ${}, sell it with !sell_stock before shorting it.", ticker)).await;
+        return Ok(());
+    }
+
+    let stock_info = match get_stock_price(ticker.clone()).await {
+        Ok(stock_info) => stock_info,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+    let status: MarketStatus = market_status(&stock_info, Utc::now());
+    if !status.is_open() {
+        let timezone: Tz = get_user_timezone(msg.author.id.to_string()).await;
+        let _ = msg.reply(&ctx.http, format!("The market of ${} is {}. Try again once it is open.", ticker, describe_market_status(&status, &timezone))).await;
+        return Ok(());
+    }
+
+    let fx_rate: f64 = match get_fx_rate(&stock_info.currency).await {
+        Ok(fx_rate) => fx_rate,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, format!("Could not convert {} into €: {}", stock_info.currency, error)).await;
+            return Ok(());
+        }
+    };
+    let proceeds: f64 = quantity * stock_info.price * fx_rate;
+
+    let rules: MarginRules = trade_margin_rules(&portfolio_user, Some(guild_id.clone())).await;
+    let prices = fetch_held_prices(std::slice::from_ref(&portfolio_user)).await;
+    // Applied to the account as it is when the sale goes through
+    let available_exposure = |user: &PortfolioUser| margin_account(user, &prices).available_exposure(&rules);
+
+    match open_short_position(msg.author.id.to_string(), guild_id, ticker.clone(), quantity, stock_info.price, stock_info.currency.clone(), fx_rate, available_exposure).await {
+        Ok(ShortOutcome::Shorted { remaining_money }) => {
+            let reply = MessageBuilder::new()
+                .push_line(format!("Shorted {} ${} at {:.2} {} for a total of €{:.2}.", quantity, ticker, stock_info.price, stock_info.currency, proceeds))
+                .push_line(format!("Your money: €{:.2}", remaining_money))
+                .push_italic_line(format!(
+                    "Borrowed shares cost {:.2}% a year. Buy them back with !cover ${}.",
+                    rules.interest_rate * 100.0,
+                    ticker
+                ))
+                .build();
+            let _ = msg.reply(&ctx.http, reply).await;
+        },
+        Ok(ShortOutcome::InsufficientMargin { available }) => {
+            let _ = msg.reply(&ctx.http, format!(
+                "Not enough equity: shorting €{:.2} would break the {:.0}% initial margin, you can short up to €{:.2}.",
+                proceeds,
+                rules.initial_margin * 100.0,
+                available
+            )).await;
+        },
+        Ok(ShortOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Ok(ShortOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(short) Could not short {} ${}: {}.", quantity, ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not complete the short sale, nothing was sold.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Buy back shorted shares at the current trade price. Your oldest short sales are covered first.")]
+#[example = "!cover $NVDA all"]
+#[usage = "!cover $NVDA [quantity|all]"]
+#[num_args(2)]
+#[help_available]
+pub async fn cover(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let ticker: String = normalize_ticker(&args.single::<String>().unwrap_or_default());
+    let raw_quantity: String = args.single::<String>().unwrap_or_default();
+    // None means "cover everything"
+    let quantity: Option<f64> = parse_quantity(&raw_quantity);
+
+    if ticker.is_empty() || (quantity.is_none() && !raw_quantity.eq_ignore_ascii_case("all")) {
+        let _ = msg.reply(&ctx.http, "Usage: !cover $NVDA 2 (or \"all\" to buy back every shorted share).").await;
+        return Ok(());
+    }
+
+    let stock_info = match get_stock_price(ticker.clone()).await {
+        Ok(stock_info) => stock_info,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+    let status: MarketStatus = market_status(&stock_info, Utc::now());
+    if !status.is_open() {
+        let timezone: Tz = get_user_timezone(msg.author.id.to_string()).await;
+        let _ = msg.reply(&ctx.http, format!("The market of ${} is {}. Try again once it is open.", ticker, describe_market_status(&status, &timezone))).await;
+        return Ok(());
+    }
+
+    let fx_rate: f64 = match get_fx_rate(&stock_info.currency).await {
+        Ok(fx_rate) => fx_rate,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, format!("Could not convert {} into €: {}", stock_info.currency, error)).await;
+            return Ok(());
+        }
+    };
+
+    match cover_short_position(msg.author.id.to_string(), ticker.clone(), quantity, stock_info.price, stock_info.currency.clone(), fx_rate).await {
+        Ok(CoverOutcome::Covered { quantity, cost, profit, remaining_money }) => {
+            let reply = MessageBuilder::new()
+                .push_line(format!("Covered {} ${} at {:.2} {} for a total of €{:.2}.", quantity, ticker, stock_info.price, stock_info.currency, cost))
+                .push_bold_line(format!("Realized profit/loss: €{:+.2}", profit))
+                .push_line(format!("Your money: €{:.2}", remaining_money))
+                .build();
+            let _ = msg.reply(&ctx.http, reply).await;
+        },
+        Ok(CoverOutcome::Overcovered { owed }) => {
+            let _ = msg.reply(&ctx.http, format!("You cannot cover more than you owe: you shorted {} ${}.", owed, ticker)).await;
+        },
+        Ok(CoverOutcome::UserNotFound) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+        },
+        Ok(CoverOutcome::AccountFrozen) => {
+            let _ = msg.reply(&ctx.http, "Your account is frozen, ask an owner of the bot.").await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(cover) Could not cover ${}: {}.", ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not complete the purchase, nothing was covered.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[num_args(0)]
+#[description("Shows your equity, borrowed money and shares, and how far you are from a margin call.")]
+#[example("!margin")]
+#[help_available]
+pub async fn margin(ctx: &Context, msg: &Message) -> CommandResult {
+    let portfolio_user = match get_user_portfolio(msg.author.id.to_string()).await {
+        Ok(Some(portfolio_user)) => portfolio_user,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            let _ = log_error(format!("(margin) Could not query the portfolio: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    // The rules of the guild the user borrowed in apply, the current guild's otherwise
+    let guild_id: Option<String> = portfolio_user
+        .margin_guild_id
+        .clone()
+        .or(msg.guild_id.map(|guild_id| guild_id.to_string()));
+    let rules: MarginRules = guild_margin_rules(guild_id).await;
+    let prices = fetch_held_prices(std::slice::from_ref(&portfolio_user)).await;
+    let account: MarginAccount = margin_account(&portfolio_user, &prices);
+
+    let state: &str = match account.state(&rules) {
+        MarginState::Healthy => "healthy",
+        MarginState::Warning => "close to a margin call, add money or reduce your positions",
+        MarginState::Liquidation => "below the maintenance margin, positions will be liquidated",
+    };
+
+    let mut builder = MessageBuilder::new();
+    builder
+        .push_line(format!("Short selling: {}, margin buying: {}", on_off(rules.short_selling), on_off(rules.margin)))
+        .push_line(format!(
+            "Interest {:.2}% a year, initial margin {:.0}%, maintenance margin {:.0}%",
+            rules.interest_rate * 100.0,
+            rules.initial_margin * 100.0,
+            rules.maintenance_margin * 100.0
+        ))
+        .push_line("")
+        .push_line(format!("Cash: €{:.2}", account.cash))
+        .push_line(format!("Long positions: €{:.2}", account.long_value))
+        .push_line(format!("Short positions: €{:.2}", account.short_value))
+        .push_bold_line(format!("Equity: €{:.2}", account.equity()))
+        .push_line(format!("Borrowed: €{:.2} of money, €{:.2} of shares", account.borrowed_money(), account.short_value))
+        .push_line(format!(
+            "Initial requirement: €{:.2}, maintenance requirement: €{:.2}",
+            account.initial_requirement(&rules),
+            account.maintenance_requirement(&rules)
+        ))
+        .push_line(format!("Can still open: €{:.2}", account.available_exposure(&rules)))
+        .push_bold_line(format!("Status: {}", state));
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("{}'s margin account", msg.author.name);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// The timezone the user chose, Europe/Brussels for unregistered users.
+async fn get_user_timezone(user_id: String) -> Tz {
+    match get_discord_user_info(user_id).await {
+        Ok(Some(user)) => user.timezone.parse().unwrap_or(Brussels),
+        _ => Brussels,
+    }
+}
+
+/// Refuses the trade or queues it as a market-on-open order, depending on the configuration.
+async fn handle_closed_market(ctx: &Context, msg: &Message, side: OrderSide, ticker: &String, quantity: Option<f64>, status: &MarketStatus) -> () {
+    let timezone: Tz = get_user_timezone(msg.author.id.to_string()).await;
+    let market: String = describe_market_status(status, &timezone);
+
+    match read_stocks_config().off_hours_trades {
+        OffHoursTrades::Reject => {
+            let _ = msg.reply(&ctx.http, format!("The market of ${} is {}. Try again once it is open.", ticker, market)).await;
+        },
+        OffHoursTrades::QueueOnOpen => {
+            match create_pending_order(
+                msg.author.id.to_string(),
+                msg.channel_id.to_string(),
+                side.to_string(),
+                ticker.clone(),
+                quantity,
+                OrderType::MarketOnOpen.to_string(),
+                None,
+            ).await {
+                Ok(number) => {
+                    let _ = msg.reply(&ctx.http, format!(
+                        "The market of ${} is {}. Your {} order was queued as #{} and will be filled at the first price once it opens (!order cancel {} to cancel it).",
+                        ticker, market, side, number, number
+                    )).await;
+                },
+                Err(err) => {
+                    let _ = log_error(format!("(market on open) Could not queue the order: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                    let _ = msg.reply(&ctx.http, "Could not queue the order.").await;
+                }
+            }
+        }
+    }
+}
+
+#[command]
+#[num_args(0)]
+#[aliases(portfolio, consultPortfolio)]
+#[description("Shows your positions valued at the current trade prices, with their unrealized profit/loss.")]
+#[example("!portfolio")]
+#[help_available]
+pub async fn consult_portfolio(ctx: &Context, msg: &Message) -> CommandResult {
+    let portfolio_user = match get_user_portfolio(msg.author.id.to_string()).await {
+        Ok(Some(portfolio_user)) => portfolio_user,
+        Ok(None) => {
+            let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
+            return Ok(());
+        },
+        Err(err) => {
+            let _ = log_error(format!("(consult_portfolio) Could not query the portfolio: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let prices = fetch_held_prices(std::slice::from_ref(&portfolio_user)).await;
+    let lines = portfolio_user.portfolio.as_ref().map(|portfolio| portfolio.lines.clone()).unwrap_or_default();
+    let valuations: Vec<PositionValuation> = value_positions(&lines, &prices);
+    let shorts: Vec<ShortValuation> = value_shorts(&portfolio_user.shorts, &prices);
+
+    let mut builder = MessageBuilder::new();
+    if valuations.is_empty() && shorts.is_empty() {
+        builder.push_line("You do not own any stock or crypto yet.");
+    }
+    for asset_class in [AssetClass::Equity, AssetClass::Crypto] {
+        let class_valuations: Vec<&PositionValuation> = valuations
+            .iter()
+            .filter(|valuation| AssetClass::of_ticker(&valuation.position.ticker) == asset_class)
+            .collect();
+        if class_valuations.is_empty() {
+            continue;
+        }
+
+        let units: &str = match asset_class {
+            AssetClass::Equity => "shares",
+            AssetClass::Crypto => "units",
+        };
+        builder.push_underline_line(format!(
+            "{}: €{:.2}",
+            asset_class.to_string().to_uppercase(),
+            class_valuations.iter().map(|valuation| valuation.market_value()).sum::<f64>()
+        ));
+
+        for valuation in class_valuations.iter() {
+            let currency: &str = &valuation.position.currency;
+            let price = match valuation.price {
+                Some(held) => format!("{:.2} {}", held.price, currency),
+                None => String::from("unavailable"),
+            };
+            builder
+                .push_bold_line(format!("${}", valuation.position.ticker))
+                .push_line(format!(
+                    "{} {}, average cost {:.2} {}, current price {}",
+                    valuation.position.quantity,
+                    units,
+                    valuation.position.average_cost(),
+                    currency,
+                    price
+                ))
+                .push_line(format!(
+                    "Market value €{:.2}, unrealized €{:+.2} ({:+.2}%): price €{:+.2}, FX €{:+.2}",
+                    valuation.market_value(),
+                    valuation.unrealized_profit(),
+                    valuation.unrealized_profit_percent(),
+                    valuation.price_impact(),
+                    valuation.fx_impact()
+                ));
+        }
+    }
+
+    if !shorts.is_empty() {
+        builder.push_underline_line(format!(
+            "SHORTS: €{:.2} owed",
+            shorts.iter().map(|short| short.market_value()).sum::<f64>()
+        ));
+    }
+    for short in shorts.iter() {
+        let price = match short.price {
+            Some(held) => format!("{:.2} {}", held.price, short.currency),
+            None => String::from("unavailable"),
+        };
+        builder
+            .push_bold_line(format!("${}", short.ticker))
+            .push_line(format!(
+                "{} borrowed, sold at {:.2} {} on average, current price {}",
+                short.quantity,
+                short.average_price(),
+                short.currency,
+                price
+            ))
+            .push_line(format!(
+                "Cost to cover €{:.2}, unrealized €{:+.2}",
+                short.market_value(),
+                short.unrealized_profit()
+            ));
+    }
+
+    let invested: f64 = valuations.iter().map(|valuation| valuation.market_value()).sum();
+    let unrealized: f64 = valuations.iter().map(|valuation| valuation.unrealized_profit()).sum();
+    let fx_impact: f64 = valuations.iter().map(|valuation| valuation.fx_impact()).sum();
+    builder
+        .push_line("")
+        .push_line(format!("Cash: €{:.2}", portfolio_user.money))
+        .push_line(format!("Invested: €{:.2} (unrealized €{:+.2}, of which FX €{:+.2})", invested, unrealized, fx_impact));
+    if !shorts.is_empty() {
+        builder.push_line(format!(
+            "Shorts: €{:.2} to cover (unrealized €{:+.2})",
+            shorts.iter().map(|short| short.market_value()).sum::<f64>(),
+            shorts.iter().map(|short| short.unrealized_profit()).sum::<f64>()
+        ));
+    }
+    builder.push_bold_line(format!("Net worth: €{:.2}", net_worth(&portfolio_user, &prices)));
+    if portfolio_user.money < 0.0 || !shorts.is_empty() {
+        builder.push_italic_line("You are borrowing money or shares, see !margin for your margin requirements.");
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("{}'s portfolio", msg.author.name);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
 }
 
 #[command]
@@ -116,8 +833,7 @@ pub async fn get_financial_infos(ctx: &Context, msg: &Message, args: Args) -> Co
                     let _ = msg.reply(&ctx.http, reply).await;
                 },
                 None => {
-                    let _ = log_error(format!("User not found."), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
-                    // return Err(format!("(get_financial_infos) User not found."));
+                    let _ = msg.reply(&ctx.http, "Your account could not be opened, please try again later.").await;
                 }
             }
         },
@@ -129,3 +845,883 @@ pub async fn get_financial_infos(ctx: &Context, msg: &Message, args: Args) -> Co
 
     Ok(())
 }
+
+#[command]
+#[aliases(trades, transactions)]
+#[description("Shows your latest trades and money movements, optionally for a single ticker and/or period (7d, 2w, 1m, 1y, all).")]
+#[example("!history $NVDA 1m")]
+#[usage("!history [ticker] [period]")]
+#[max_args(2)]
+#[help_available]
+pub async fn history(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let history_length: i64 = 15;
+    let mut ticker: Option<String> = None;
+    let mut period: Period = Period::All;
+
+    for arg in args.raw() {
+        match parse_period(arg) {
+            Some(parsed) => period = parsed,
+            None => ticker = Some(normalize_ticker(arg)),
+        }
+    }
+
+    match get_user_transactions(msg.author.id.to_string(), ticker, period.since_epoch(), Some(history_length)).await {
+        Ok(transactions) => {
+            let mut builder = MessageBuilder::new();
+            if transactions.is_empty() {
+                builder.push_line("No transaction found.");
+            }
+            for transaction in transactions.iter() {
+                builder.push_line(format_transaction_line(transaction));
+            }
+
+            let mut embed_data = CEmbedData::default();
+            embed_data.title = format!("{}'s history", msg.author.name);
+            embed_data.description = builder.build();
+            send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(history) Could not query the transactions: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Exports your whole transaction history as a CSV file.")]
+#[example("!statement")]
+#[num_args(0)]
+#[help_available]
+pub async fn statement(ctx: &Context, msg: &Message) -> CommandResult {
+    match get_user_transactions(msg.author.id.to_string(), None, None, None).await {
+        Ok(transactions) => {
+            let csv: String = transactions_to_csv(&transactions);
+            let attachment = AttachmentType::Bytes {
+                data: Cow::from(csv.into_bytes()),
+                filename: format!("statement_{}.csv", msg.author.name),
+            };
+
+            if let Err(err) = msg.channel_id.send_files(&ctx.http, vec![attachment], |m| {
+                m.content(format!("{} transaction(s) exported.", transactions.len()))
+            }).await {
+                let _ = log_error(format!("(statement) Could not send the statement: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        },
+        Err(err) => {
+            let _ = log_error(format!("(statement) Could not query the transactions: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases(lb, ranking)]
+#[description("Ranks the players by net worth. Give a period (week, month, 7d...) to rank them by their return over that period instead.")]
+#[example("!leaderboard week 2")]
+#[usage("!leaderboard [period] [page]")]
+#[max_args(2)]
+#[help_available]
+pub async fn leaderboard(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut period: Period = Period::All;
+    let mut page: usize = 1;
+
+    for arg in args.raw() {
+        if let Ok(parsed) = arg.parse::<usize>() {
+            page = parsed.max(1);
+        } else if let Some(parsed) = parse_period(arg) {
+            period = parsed;
+        }
+    }
+
+    let users = match get_all_portfolios().await {
+        Ok(users) => users,
+        Err(err) => {
+            let _ = log_error(format!("(leaderboard) Could not query the portfolios: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    // Net worth at the start of the period and its day, per user. Without any snapshot, users are compared to their starting money.
+    let mut baselines: HashMap<String, (String, f64)> = HashMap::new();
+    // Money brought in or taken out from outside the market since the baseline, which is not a return
+    let mut flows: HashMap<String, f64> = HashMap::new();
+    if let Some(since) = period.since_epoch() {
+        match (get_snapshots_since(since).await, get_ledger_flows_since(since, &EXTERNAL_FLOW_KINDS).await) {
+            (Ok(snapshots), Ok(ledger_flows)) => {
+                for snapshot in snapshots.into_iter() {
+                    baselines.entry(snapshot.user_id).or_insert((snapshot.day, snapshot.net_worth));
+                }
+                // The baseline snapshot is taken at the end of its day, the flows of that day are already in it
+                for flow in ledger_flows.into_iter() {
+                    let after_baseline: bool = match baselines.get(&flow.user_id) {
+                        Some((day, _)) => flow.day > *day,
+                        None => true,
+                    };
+                    if after_baseline {
+                        *flows.entry(flow.user_id).or_insert(0.0) += flow.amount;
+                    }
+                }
+            },
+            (Err(err), _) | (_, Err(err)) => {
+                let _ = log_error(format!("(leaderboard) Could not query the history: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                return Ok(());
+            }
+        }
+    }
+
+    let prices = fetch_held_prices(&users).await;
+    // (name, net worth, baseline, gain over the period)
+    let mut rows: Vec<(String, f64, f64, f64)> = users
+        .iter()
+        .map(|user| {
+            let baseline: f64 = baselines.get(&user.unique_id).map_or(STARTING_MONEY, |(_, net_worth)| *net_worth);
+            let worth: f64 = net_worth(user, &prices);
+            let gain: f64 = worth - baseline - flows.get(&user.unique_id).copied().unwrap_or(0.0);
+            (user.display_name.clone(), worth, baseline, gain)
+        })
+        .collect();
+
+    let return_percent = |gain: f64, baseline: f64| if baseline > 0.0 { gain / baseline * 100.0 } else { 0.0 };
+    match period {
+        Period::All => rows.sort_by(|a, b| b.1.total_cmp(&a.1)),
+        Period::Last(_) => rows.sort_by(|a, b| return_percent(b.3, b.2).total_cmp(&return_percent(a.3, a.2))),
+    }
+
+    let pages: usize = ((rows.len() + LEADERBOARD_PAGE_SIZE - 1) / LEADERBOARD_PAGE_SIZE).max(1);
+    let page: usize = page.min(pages);
+    let mut builder = MessageBuilder::new();
+
+    if rows.is_empty() {
+        builder.push_line("Nobody is playing yet, any stocks command opens an account.");
+    }
+    for (rank, (name, worth, baseline, gain)) in rows.iter().enumerate().skip((page - 1) * LEADERBOARD_PAGE_SIZE).take(LEADERBOARD_PAGE_SIZE) {
+        builder.push_line(format!(
+            "{}. {}: €{:.2} ({:+.2}, {:+.2}%)",
+            rank + 1,
+            name,
+            worth,
+            gain,
+            return_percent(*gain, *baseline)
+        ));
+    }
+    builder.push_italic_line(format!("Page {}/{}", page, pages));
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = match period {
+        Period::All => String::from("Leaderboard"),
+        Period::Last(duration) => format!("Leaderboard (last {} days)", duration.num_days()),
+    };
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+#[command]
+#[description("Places a limit or stop order, filled automatically once the price reaches the trigger. Use \"cancel\" with an order number to cancel it.")]
+#[example("!order buy AAPL 5 limit 180")]
+#[usage("!order [buy|sell] [ticker] [quantity|all] [limit|stop] [price], or !order cancel [number]")]
+#[min_args(2)]
+#[max_args(5)]
+#[help_available]
+pub async fn order(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if refuse_if_frozen(ctx, msg).await {
+        return Ok(());
+    }
+
+    let arguments: Vec<&str> = args.raw().collect();
+    let user_id: String = msg.author.id.to_string();
+
+    if arguments[0].eq_ignore_ascii_case("cancel") {
+        let number = match arguments[1].trim_start_matches('#').parse::<i64>() {
+            Ok(number) => number,
+            Err(_) => {
+                let _ = msg.reply(&ctx.http, "Usage: !order cancel [number]").await;
+                return Ok(());
+            }
+        };
+
+        match cancel_pending_order(user_id, number).await {
+            Ok(true) => {
+                let _ = msg.reply(&ctx.http, format!("Order #{} cancelled.", number)).await;
+            },
+            Ok(false) => {
+                let _ = msg.reply(&ctx.http, format!("You have no pending order #{}.", number)).await;
+            },
+            Err(err) => {
+                let _ = log_error(format!("(order) Could not cancel order #{}: {}.", number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        }
+        return Ok(());
+    }
+
+    let request: OrderRequest = match parse_order_request(&arguments) {
+        Ok(request) => request,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+
+    // Validate the ticker now rather than when the job evaluates it
+    if let Err(error) = get_stock_price(request.ticker.clone()).await {
+        let _ = msg.reply(&ctx.http, error).await;
+        return Ok(());
+    }
+
+    match create_pending_order(
+        user_id,
+        msg.channel_id.to_string(),
+        request.side.to_string(),
+        request.ticker.clone(),
+        request.quantity,
+        request.order_type.to_string(),
+        Some(request.trigger_price),
+    ).await {
+        Ok(number) => {
+            let _ = msg.reply(&ctx.http, format!(
+                "Order #{} placed: {} {} ${} {} ${:.2}. You will be notified once it is filled.",
+                number,
+                request.side,
+                format_order_quantity(request.quantity),
+                request.ticker,
+                request.order_type,
+                request.trigger_price
+            )).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(order) Could not place the order: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not place the order.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Lists your pending limit, stop and market-on-open orders.")]
+#[example("!orders")]
+#[num_args(0)]
+#[help_available]
+pub async fn orders(ctx: &Context, msg: &Message) -> CommandResult {
+    match get_pending_orders(Some(msg.author.id.to_string())).await {
+        Ok(orders) => {
+            let mut builder = MessageBuilder::new();
+            if orders.is_empty() {
+                builder.push_line("You have no pending order.");
+            }
+            for order in orders.iter() {
+                builder.push_line(format!(
+                    "#{}: {} {} ${} {}",
+                    order.number,
+                    order.side,
+                    format_order_quantity(order.quantity),
+                    order.ticker,
+                    format_order_trigger(&order.order_type, order.trigger_price)
+                ));
+            }
+
+            let mut embed_data = CEmbedData::default();
+            embed_data.title = format!("{}'s pending orders", msg.author.name);
+            embed_data.description = builder.build();
+            send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(orders) Could not query the pending orders: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+fn format_order_quantity(quantity: Option<f64>) -> String {
+    match quantity {
+        Some(quantity) => quantity.to_string(),
+        None => String::from("all"),
+    }
+}
+
+fn format_order_trigger(order_type: &str, trigger_price: Option<f64>) -> String {
+    match trigger_price {
+        Some(trigger_price) => format!("{} ${:.2}", order_type, trigger_price),
+        None => String::from("at the market open"),
+    }
+}
+
+#[command]
+#[description("Alerts you once when a ticker reaches a price, moves by a percentage today or makes a new 52-week high. Add \"here\" to be alerted in this channel instead of by private message, and \"rearm\" to be alerted again on every new crossing.")]
+#[example("!alert NVDA change -5% here rearm")]
+#[usage("!alert [ticker] [above|below|change|high52] [threshold] [here] [rearm], or !alert remove [number]")]
+#[min_args(2)]
+#[max_args(5)]
+#[help_available]
+pub async fn alert(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut arguments: Vec<&str> = args.raw().collect();
+    let user_id: String = msg.author.id.to_string();
+
+    if arguments[0].eq_ignore_ascii_case("remove") || arguments[0].eq_ignore_ascii_case("delete") {
+        let number = match arguments[1].trim_start_matches('#').parse::<i64>() {
+            Ok(number) => number,
+            Err(_) => {
+                let _ = msg.reply(&ctx.http, "Usage: !alert remove [number]").await;
+                return Ok(());
+            }
+        };
+
+        match delete_price_alert(user_id, number).await {
+            Ok(true) => {
+                let _ = msg.reply(&ctx.http, format!("Alert #{} removed.", number)).await;
+            },
+            Ok(false) => {
+                let _ = msg.reply(&ctx.http, format!("You have no alert #{}.", number)).await;
+            },
+            Err(err) => {
+                let _ = log_error(format!("(alert) Could not remove alert #{}: {}.", number, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        }
+        return Ok(());
+    }
+
+    let rearm: bool = arguments.iter().any(|arg| arg.eq_ignore_ascii_case("rearm"));
+    let here: bool = arguments.iter().any(|arg| arg.eq_ignore_ascii_case("here"));
+    arguments.retain(|arg| !arg.eq_ignore_ascii_case("rearm") && !arg.eq_ignore_ascii_case("here"));
+
+    if arguments.len() < 2 {
+        let _ = msg.reply(&ctx.http, "Usage: !alert AAPL above 200, or !alert remove [number]").await;
+        return Ok(());
+    }
+
+    let ticker: String = normalize_ticker(arguments[0]);
+    let condition: AlertCondition = match AlertCondition::parse(&arguments[1..]) {
+        Ok(condition) => condition,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+
+    if let Err(error) = get_stock_price(ticker.clone()).await {
+        let _ = msg.reply(&ctx.http, error).await;
+        return Ok(());
+    }
+
+    let channel_id: Option<String> = if here { Some(msg.channel_id.to_string()) } else { None };
+    let (condition_name, threshold) = condition.to_stored();
+
+    match create_price_alert(user_id, channel_id, ticker.clone(), condition_name, threshold, rearm).await {
+        Ok(number) => {
+            let _ = msg.reply(&ctx.http, format!("Alert #{} set: ${} {}.", number, ticker, condition)).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(alert) Could not create the alert: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            let _ = msg.reply(&ctx.http, "Could not create the alert.").await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Lists your price alerts.")]
+#[example("!alerts")]
+#[num_args(0)]
+#[help_available]
+pub async fn alerts(ctx: &Context, msg: &Message) -> CommandResult {
+    match get_price_alerts(Some(msg.author.id.to_string())).await {
+        Ok(alerts) => {
+            let mut builder = MessageBuilder::new();
+            if alerts.is_empty() {
+                builder.push_line("You have no price alert.");
+            }
+            for alert in alerts.iter() {
+                let condition = match AlertCondition::from_stored(&alert.condition, alert.threshold) {
+                    Some(condition) => condition.to_string(),
+                    None => alert.condition.clone(),
+                };
+                let mut flags: Vec<&str> = vec![];
+                if alert.channel_id.is_some() {
+                    flags.push("in channel");
+                }
+                if alert.rearm {
+                    flags.push(if alert.armed { "re-arming" } else { "re-arming, waiting to reset" });
+                }
+                builder.push_line(format!("#{}: ${} {} {}", alert.number, alert.ticker, condition, if flags.is_empty() { String::new() } else { format!("({})", flags.join(", ")) }));
+            }
+
+            let mut embed_data = CEmbedData::default();
+            embed_data.title = format!("{}'s price alerts", msg.author.name);
+            embed_data.description = builder.build();
+            send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+        },
+        Err(err) => {
+            let _ = log_error(format!("(alerts) Could not query the alerts: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Manages your watchlist: add or remove tickers, or list them. Use !watchlist to see their prices.")]
+#[example("!watch add AAPL MSFT")]
+#[usage("!watch [add|remove|list] [tickers...]")]
+#[min_args(1)]
+#[help_available]
+pub async fn watch(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let arguments: Vec<&str> = args.raw().collect();
+    let user_id: String = msg.author.id.to_string();
+    let tickers: Vec<String> = arguments[1..].iter().map(|ticker| normalize_ticker(ticker)).filter(|ticker| !ticker.is_empty()).collect();
+    let mut builder = MessageBuilder::new();
+
+    match arguments[0].to_lowercase().as_str() {
+        "add" if !tickers.is_empty() => {
+            // Only keep tickers that actually exist
+            let known: Vec<String> = match get_stock_quotes(tickers.clone()).await {
+                Ok(quotes) => quotes.into_iter().map(|quote| quote.ticker).collect(),
+                Err(error) => {
+                    let _ = msg.reply(&ctx.http, error).await;
+                    return Ok(());
+                }
+            };
+
+            for ticker in tickers.iter() {
+                if !known.contains(ticker) {
+                    builder.push_line(format!("${}: unknown ticker.", ticker));
+                    continue;
+                }
+                match create_watchlist_entry(user_id.clone(), ticker.clone()).await {
+                    Ok(true) => builder.push_line(format!("${}: added.", ticker)),
+                    Ok(false) => builder.push_line(format!("${}: already watched.", ticker)),
+                    Err(err) => {
+                        let _ = log_error(format!("(watch) Could not add ${} to the watchlist: {}.", ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                        builder.push_line(format!("${}: could not be added.", ticker))
+                    }
+                };
+            }
+        },
+        "remove" | "delete" if !tickers.is_empty() => {
+            for ticker in tickers.iter() {
+                match delete_watchlist_entry(user_id.clone(), ticker.clone()).await {
+                    Ok(true) => builder.push_line(format!("${}: removed.", ticker)),
+                    Ok(false) => builder.push_line(format!("${}: was not watched.", ticker)),
+                    Err(err) => {
+                        let _ = log_error(format!("(watch) Could not remove ${} from the watchlist: {}.", ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                        builder.push_line(format!("${}: could not be removed.", ticker))
+                    }
+                };
+            }
+        },
+        "list" => {
+            match get_watchlist(user_id).await {
+                Ok(watched) if watched.is_empty() => builder.push_line("Your watchlist is empty."),
+                Ok(watched) => builder.push_line(format!("Watching: {}", watched.iter().map(|ticker| format!("${}", ticker)).collect::<Vec<String>>().join(", "))),
+                Err(err) => {
+                    let _ = log_error(format!("(watch) Could not query the watchlist: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                    return Ok(());
+                }
+            };
+        },
+        _ => {
+            builder.push_line("Usage: !watch add AAPL MSFT, !watch remove AAPL or !watch list");
+        }
+    }
+
+    let _ = msg.reply(&ctx.http, builder.build()).await;
+
+    Ok(())
+}
+
+#[command]
+#[description("Shows the price, day change, day range and 52-week range of every ticker in your watchlist.")]
+#[example("!watchlist")]
+#[num_args(0)]
+#[help_available]
+pub async fn watchlist(ctx: &Context, msg: &Message) -> CommandResult {
+    let watched: Vec<String> = match get_watchlist(msg.author.id.to_string()).await {
+        Ok(watched) => watched,
+        Err(err) => {
+            let _ = log_error(format!("(watchlist) Could not query the watchlist: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    if watched.is_empty() {
+        let _ = msg.reply(&ctx.http, "Your watchlist is empty, use !watch add [tickers] first.").await;
+        return Ok(());
+    }
+
+    match get_stock_quotes(watched).await {
+        Ok(quotes) => {
+            let mut embed_data = CEmbedData::default();
+            embed_data.title = format!("{}'s watchlist", msg.author.name);
+            embed_data.description = format_quotes_table(&quotes);
+            send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+        },
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Lists the earnings calls of the coming week for the tickers held or watched by the members of this guild. Holders and watchers are also notified by private message a few days before each call.")]
+#[example("!earnings week")]
+#[usage("!earnings [week]")]
+#[max_args(1)]
+#[only_in(guilds)]
+#[help_available]
+pub async fn earnings(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if let Some(period) = args.current() {
+        if !period.eq_ignore_ascii_case("week") {
+            let _ = msg.reply(&ctx.http, "Usage: !earnings [week]").await;
+            return Ok(());
+        }
+    }
+
+    let members: HashSet<String> = match msg.guild_id.and_then(|guild_id| guild_id.to_guild_cached(&ctx.cache)) {
+        Some(guild) => guild.members.keys().map(|user_id| user_id.to_string()).collect(),
+        None => return Ok(()),
+    };
+    let (users, watchlist) = match (get_all_portfolios().await, get_all_watchlist_entries().await) {
+        (Ok(users), Ok(watchlist)) => (users, watchlist),
+        (Err(err), _) | (_, Err(err)) => {
+            let _ = log_error(format!("(earnings) Could not query the tracked tickers: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let users: Vec<PortfolioUser> = users.into_iter().filter(|user| members.contains(&user.unique_id)).collect();
+    let watchlist: Vec<WatchlistEntry> = watchlist.into_iter().filter(|entry| members.contains(&entry.user_id)).collect();
+    let followers = followers_by_ticker(&users, &watchlist);
+    if followers.is_empty() {
+        let _ = msg.reply(&ctx.http, "Nobody in this guild holds or watches a ticker yet.").await;
+        return Ok(());
+    }
+
+    let quotes = get_stock_prices(followers.keys().cloned().collect()).await;
+    let calls: Vec<EarningsCall> = upcoming_calls(&quotes, Utc::now(), EARNINGS_WEEK_DAYS);
+    if calls.is_empty() {
+        let _ = msg.reply(&ctx.http, format!("No earnings call in the next {} days for the {} tracked tickers.", EARNINGS_WEEK_DAYS, followers.len())).await;
+        return Ok(());
+    }
+
+    let timezone: Tz = get_user_timezone(msg.author.id.to_string()).await;
+    let mut builder = MessageBuilder::new();
+    for call in calls.iter() {
+        let tracked_by: usize = followers.get(&call.ticker).map(|users| users.len()).unwrap_or(0);
+        builder
+            .push_bold(format!("${}", call.ticker))
+            .push_line(format!(
+                " {}: {} ({} {})",
+                call.name,
+                call.describe(&timezone),
+                tracked_by,
+                if tracked_by == 1 { "member" } else { "members" }
+            ));
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = format!("Earnings calls of the next {} days", EARNINGS_WEEK_DAYS);
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+#[command]
+#[aliases(graph)]
+#[description("Draws the price history of a ticker as a line or candlestick chart (1d, 5d, 1mo, 3mo, 6mo, 1y, 2y, 5y, ytd, max). Your purchases are marked on it when you hold the ticker.")]
+#[example("!chart AAPL 3mo candle")]
+#[usage("!chart [ticker] [range] [line|candle]")]
+#[min_args(1)]
+#[max_args(3)]
+#[help_available]
+pub async fn chart(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut ticker: Option<String> = None;
+    let mut range: HistoryRange = HistoryRange::OneMonth;
+    let mut style: ChartStyle = ChartStyle::Line;
+
+    for arg in args.raw() {
+        match arg.to_lowercase().as_str() {
+            "line" => style = ChartStyle::Line,
+            "candle" | "candles" | "candlestick" => style = ChartStyle::Candlestick,
+            _ => match HistoryRange::parse(arg) {
+                Some(parsed) => range = parsed,
+                None => ticker = Some(normalize_ticker(arg)),
+            },
+        }
+    }
+
+    let ticker: String = match ticker {
+        Some(ticker) => ticker,
+        None => {
+            let _ = msg.reply(&ctx.http, "Please give a ticker, for example: !chart AAPL 1mo").await;
+            return Ok(());
+        }
+    };
+
+    let candles: Vec<Candle> = match get_stock_history(ticker.clone(), range).await {
+        Ok(candles) => candles,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+
+    // Purchases are only worth showing for a position that is still open
+    let mut buy_points: Vec<BuyPoint> = vec![];
+    let mut average_cost: Option<f64> = None;
+    if let Ok(Some(user)) = get_user_portfolio(msg.author.id.to_string()).await {
+        let lines: Vec<PortfolioLines> = user.portfolio.map(|portfolio| portfolio.lines).unwrap_or_default();
+        if let Some(position) = aggregate_positions(&lines).into_iter().find(|position| position.ticker == ticker) {
+            average_cost = Some(position.average_cost());
+            match get_purchase_points(msg.author.id.to_string(), ticker.clone()).await {
+                Ok(points) => {
+                    buy_points = points
+                        .iter()
+                        .map(|point| BuyPoint { timestamp: point.epoch as i64, price: point.price })
+                        .collect();
+                },
+                Err(err) => {
+                    let _ = log_error(format!("(chart) Could not query the purchases: {}.", err), LogErrorLevel::WARN, msg.channel_id.to_string(), false).await;
+                }
+            }
+        }
+    }
+
+    // Drawing takes a while, it must not hold up the other commands
+    let title: String = format!("{} ({})", ticker, range);
+    let rendered = {
+        let candles: Vec<Candle> = candles.clone();
+        tokio::task::spawn_blocking(move || render_price_chart(&title, &candles, &buy_points, average_cost, style)).await
+    };
+    let png: Vec<u8> = match rendered.unwrap_or_else(|err| Err(err.to_string())) {
+        Ok(png) => png,
+        Err(err) => {
+            let _ = log_error(format!("(chart) Could not render the chart of {}: {}.", ticker, err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    let first_close: f64 = candles.first().map(|candle| candle.close).unwrap_or(0.0);
+    let last_close: f64 = candles.last().map(|candle| candle.close).unwrap_or(0.0);
+    let change: f64 = if first_close > 0.0 { (last_close - first_close) / first_close * 100.0 } else { 0.0 };
+
+    let attachment = AttachmentType::Bytes {
+        data: Cow::from(png),
+        filename: format!("{}_{}.png", ticker, range),
+    };
+    if let Err(err) = msg.channel_id.send_files(&ctx.http, vec![attachment], |m| {
+        m.reference_message(msg).content(format!("**{}** over {}: {:.2} → {:.2} ({:+.2}%)", ticker, range, first_close, last_close, change))
+    }).await {
+        let _ = log_error(format!("(chart) Could not send the chart: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases(technicals)]
+#[description("Computes technical indicators from the last year of daily prices: SMA and EMA at several windows, RSI, MACD and Bollinger bands, with a plain reading of the recent crossovers.")]
+#[example("!ta AAPL")]
+#[usage("!ta [ticker]")]
+#[num_args(1)]
+#[help_available]
+pub async fn ta(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let ticker: String = normalize_ticker(args.rest());
+
+    let candles: Vec<Candle> = match get_stock_history(ticker.clone(), HistoryRange::OneYear).await {
+        Ok(candles) => candles,
+        Err(error) => {
+            let _ = msg.reply(&ctx.http, error).await;
+            return Ok(());
+        }
+    };
+    let closes: Vec<f64> = candles.iter().map(|candle| candle.close).collect();
+    let summary: TechnicalSummary = match summarize(&closes) {
+        Some(summary) => summary,
+        None => {
+            let _ = msg.reply(&ctx.http, format!("No price history for ${}.", ticker)).await;
+            return Ok(());
+        }
+    };
+    // The averages reported with the quote, next to the ones computed here
+    let quote: Option<StockInfo> = get_stock_price(ticker.clone()).await.ok();
+
+    let format_value = |value: Option<f64>| -> String {
+        value.map(|value| format!("{:.2}", value)).unwrap_or_else(|| String::from("-"))
+    };
+
+    let mut builder = MessageBuilder::new();
+    builder.push_line(format!("Price: {:.2} ({} sessions)", summary.price, summary.sessions));
+    for (window, value) in summary.smas.iter() {
+        let reported: Option<f64> = match (window, quote.as_ref()) {
+            (50, Some(quote)) => Some(quote.fifty_day_average),
+            (200, Some(quote)) => Some(quote.two_hundred_day_average),
+            _ => None,
+        };
+        match reported.filter(|reported| *reported > 0.0) {
+            Some(reported) => builder.push_line(format!("SMA {}: {} (quote: {:.2})", window, format_value(*value), reported)),
+            None => builder.push_line(format!("SMA {}: {}", window, format_value(*value))),
+        };
+    }
+    for (window, value) in summary.emas.iter() {
+        builder.push_line(format!("EMA {}: {}", window, format_value(*value)));
+    }
+    builder.push_line(format!("RSI {}: {}", RSI_PERIOD, format_value(summary.rsi)));
+    match summary.macd {
+        Some(point) => builder.push_line(format!(
+            "MACD ({}, {}, {}): {:.2}, signal {:.2}, histogram {:+.2}",
+            MACD_FAST, MACD_SLOW, MACD_SIGNAL, point.macd, point.signal, point.histogram
+        )),
+        None => builder.push_line(format!("MACD ({}, {}, {}): -", MACD_FAST, MACD_SLOW, MACD_SIGNAL)),
+    };
+    match summary.bollinger {
+        Some(band) => builder.push_line(format!(
+            "Bollinger ({}, {}): {:.2} / {:.2} / {:.2}",
+            BOLLINGER_WINDOW, BOLLINGER_WIDTH, band.lower, band.middle, band.upper
+        )),
+        None => builder.push_line(format!("Bollinger ({}, {}): -", BOLLINGER_WINDOW, BOLLINGER_WIDTH)),
+    };
+
+    builder.push_line("").push_bold_line("Reading");
+    if summary.signals.is_empty() {
+        builder.push_line("No recent crossover, nothing stands out.");
+    }
+    for signal in summary.signals.iter() {
+        builder.push_line(format!("- {}", signal));
+    }
+    builder.push_italic_line("Not investment advice.");
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = match quote.as_ref() {
+        Some(quote) => format!("Technical analysis of ${} ({})", ticker, quote.name),
+        None => format!("Technical analysis of ${}", ticker),
+    };
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    Ok(())
+}
+
+#[command]
+#[aliases(perf)]
+#[description("Shows how your portfolio did over a period (7d, 2w, 1m, 1y, all): time-weighted return, maximum drawdown, and best and worst positions, based on the end of day snapshots. Add \"chart\" to draw your net worth.")]
+#[example("!performance 1m chart")]
+#[usage("!performance [period] [chart]")]
+#[max_args(2)]
+#[help_available]
+pub async fn performance(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut period: Period = Period::All;
+    let mut with_chart: bool = false;
+
+    for arg in args.raw() {
+        if arg.eq_ignore_ascii_case("chart") {
+            with_chart = true;
+        } else if let Some(parsed) = parse_period(arg) {
+            period = parsed;
+        }
+    }
+
+    let user_id: String = msg.author.id.to_string();
+    let since: Option<f64> = period.since_epoch();
+    let (snapshots, positions, transactions) = match (
+        get_user_snapshots(user_id.clone(), since).await,
+        get_user_position_snapshots(user_id.clone(), since).await,
+        get_user_transactions(user_id.clone(), None, since, None).await,
+    ) {
+        (Ok(snapshots), Ok(positions), Ok(transactions)) => (snapshots, positions, transactions),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            let _ = log_error(format!("(performance) Could not query the history: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            return Ok(());
+        }
+    };
+
+    if snapshots.len() < 2 {
+        let _ = msg.reply(&ctx.http, "Not enough history yet: your portfolio is snapshotted every evening, come back in a few days.").await;
+        return Ok(());
+    }
+
+    // Money brought in or taken out from outside the market, per day
+    let mut flows: HashMap<String, f64> = HashMap::new();
+    for transaction in transactions.iter().filter(|transaction| EXTERNAL_FLOW_KINDS.contains(&transaction.kind.as_str())) {
+        let day: String = transaction.created_local.to_string().chars().take(10).collect();
+        *flows.entry(day).or_insert(0.0) += transaction.amount;
+    }
+
+    let values: Vec<DailyValue> = snapshots
+        .iter()
+        .map(|snapshot| DailyValue {
+            day: snapshot.day.clone(),
+            net_worth: snapshot.net_worth,
+            flow: flows.get(&snapshot.day).copied().unwrap_or(0.0),
+        })
+        .collect();
+    let index: Vec<f64> = return_index(&values);
+    let returns: Vec<PositionReturn> = position_returns(&positions);
+
+    let (first, last) = (&values[0], &values[values.len() - 1]);
+    let mut builder = MessageBuilder::new();
+    builder.push_line(format!("From {} to {} ({} snapshots)", first.day, last.day, values.len()));
+    builder.push_line(format!("Net worth: €{:.2} → €{:.2}", first.net_worth, last.net_worth));
+    builder.push_line(format!("Time-weighted return: {:+.2}%", time_weighted_return(&index)));
+    builder.push_line(format!("Maximum drawdown: {:.2}%", max_drawdown(&index)));
+
+    if !returns.is_empty() {
+        // With few positions, each one is listed once: the better half as best, the rest as worst
+        let best: usize = 3.min(returns.len().div_ceil(2));
+        let worst: usize = 3.min(returns.len() - best);
+        builder.push_line("");
+        builder.push_bold_line("Best positions");
+        for position in returns.iter().take(best) {
+            builder.push_line(format!("${}: {:+.2}%", position.ticker, position.change_percent));
+        }
+        if worst > 0 {
+            builder.push_bold_line("Worst positions");
+            for position in returns.iter().rev().take(worst) {
+                builder.push_line(format!("${}: {:+.2}%", position.ticker, position.change_percent));
+            }
+        }
+    }
+
+    let mut embed_data = CEmbedData::default();
+    embed_data.title = match period {
+        Period::All => format!("{}'s performance", msg.author.name),
+        Period::Last(duration) => format!("{}'s performance (last {} days)", msg.author.name, duration.num_days()),
+    };
+    embed_data.description = builder.build();
+    send_embed_or_discord_error(&ctx, msg.channel_id, ERRORS.into(), embed_data).await;
+
+    if with_chart {
+        let points: Vec<(i64, f64)> = values
+            .iter()
+            .filter_map(|value| {
+                let midnight = NaiveDate::parse_from_str(&value.day, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0)?;
+                Some((Utc.from_utc_datetime(&midnight).timestamp(), value.net_worth))
+            })
+            .collect();
+
+        let title: String = format!("{}'s net worth", msg.author.name);
+        let rendered = tokio::task::spawn_blocking(move || render_value_chart(&title, &points)).await;
+        match rendered.unwrap_or_else(|err| Err(err.to_string())) {
+            Ok(png) => {
+                let attachment = AttachmentType::Bytes {
+                    data: Cow::from(png),
+                    filename: format!("performance_{}.png", msg.author.name),
+                };
+                if let Err(err) = msg.channel_id.send_files(&ctx.http, vec![attachment], |m| m).await {
+                    let _ = log_error(format!("(performance) Could not send the chart: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+                }
+            },
+            Err(err) => {
+                let _ = log_error(format!("(performance) Could not render the chart: {}.", err), LogErrorLevel::ERROR, msg.channel_id.to_string(), true).await;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -27,3 +27,55 @@ pub mod channels {
     pub static INFRARED: u64 = 905600831984918589;
     pub static EVERYONE: u64 = 76097907983392768;
 }
+
+pub mod trading {
+    // Currency of the users' money, trades are converted into it
+    pub static ACCOUNT_CURRENCY: &str = "EUR";
+    // Money every user starts with (see Discord::User.money default)
+    pub static STARTING_MONEY: f64 = 1000.0;
+    // Hour (Europe/Brussels) after which the end of day snapshots are taken, once the US markets are closed
+    pub static SNAPSHOT_HOUR: u32 = 23;
+    pub static SNAPSHOT_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 10);
+    pub static LEADERBOARD_PAGE_SIZE: usize = 10;
+    // Tickers looked up at once by !stocks, keeps the comparison within an embed
+    pub static MAX_COMPARED_TICKERS: usize = 10;
+    pub static ALERTS_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 5);
+    pub static ORDERS_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 5);
+    // Hour (Europe/Brussels) after which the day's dividends and splits are applied
+    pub static CORPORATE_ACTIONS_HOUR: u32 = 22;
+    pub static CORPORATE_ACTIONS_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 10);
+    // Dividends and splits older than this are not applied anymore, covers the bot being offline for a few days
+    pub static CORPORATE_ACTIONS_LOOKBACK_DAYS: i64 = 7;
+    pub static MARGIN_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 15);
+    // Users are warned once their equity falls below the maintenance requirement times this buffer
+    pub static MARGIN_WARNING_BUFFER: f64 = 1.1;
+    // Hour (Europe/Brussels) after which the daily interest on borrowed money and shares is charged
+    pub static MARGIN_INTEREST_HOUR: u32 = 21;
+    pub static SEASON_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 5);
+    // Participants listed in the season standings and announcements
+    pub static SEASON_STANDINGS_SIZE: usize = 10;
+    // How often upcoming earnings calls are checked for notifications
+    pub static EARNINGS_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+    // Days covered by !earnings week
+    pub static EARNINGS_WEEK_DAYS: i64 = 7;
+}
+
+pub mod economy {
+    // Money of a !daily claim, before the streak bonus
+    pub static DAILY_AMOUNT: f64 = 100.0;
+    // Extra money per consecutive day claimed, capped at DAILY_MAX_STREAK_BONUS days
+    pub static DAILY_STREAK_BONUS: f64 = 10.0;
+    pub static DAILY_MAX_STREAK_BONUS: i64 = 10;
+    // Hours between two claims, and hours after which a missed claim breaks the streak
+    pub static DAILY_COOLDOWN_HOURS: i64 = 20;
+    pub static DAILY_STREAK_HOURS: i64 = 48;
+    // How long !pay waits for the sender to confirm the transfer
+    pub static PAY_CONFIRMATION_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(30);
+    // Entries shown by !eco audit, and mismatched accounts listed by !eco reconcile
+    pub static AUDIT_PAGE_SIZE: i64 = 15;
+    pub static RECONCILIATION_REPORT_SIZE: usize = 20;
+    // How often the expired temporary roles bought in the shops are taken back
+    pub static SHOP_EXPIRY_CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 5);
+    // How long !unregister waits for the user to confirm the deletion of their account
+    pub static UNREGISTER_CONFIRMATION_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(30);
+}
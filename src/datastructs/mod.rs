@@ -2,6 +2,7 @@ pub mod bot_info;
 mod embed_data;
 pub mod owa_data;
 mod sanitized_message;
+pub mod stocks_config;
 
 pub use embed_data::CEmbedData;
 pub use sanitized_message::SanitizedMessage;
@@ -0,0 +1,73 @@
+use ron::de::from_reader;
+use serde::Deserialize;
+
+/// Where the stock quotes come from.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum QuoteSource {
+    /// Live quotes from the yahoo finance API
+    Yahoo,
+    /// Recorded yahoo finance responses, read from `fixture_path`. Used to run the stocks feature offline.
+    Fixture,
+}
+
+/// What happens to a trade placed while the exchange of the ticker is closed.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum OffHoursTrades {
+    /// The trade is refused, the user is told when the market opens
+    Reject,
+    /// The trade becomes a market-on-open order, filled at the first price once the market opens
+    QueueOnOpen,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StocksConfig {
+    pub quote_source: QuoteSource,
+    pub fixture_path: String,
+    /// Recorded yahoo chart responses, one <TICKER>.json file per ticker
+    pub history_fixture_dir: String,
+    /// Fallback exchange rates, used when a live rate cannot be fetched
+    pub fx_fixture_path: String,
+    /// How long a quote is reused before asking the provider again
+    pub quote_cache_ttl_secs: u64,
+    /// How long a symbol search (company name to ticker) is reused
+    pub search_cache_ttl_secs: u64,
+    /// TrueType font used for the chart labels. Charts are drawn without text when it cannot be read.
+    pub chart_font_path: String,
+    pub off_hours_trades: OffHoursTrades,
+    /// How many days before an earnings call the holders and watchers of the ticker are notified. 0 disables the notifications.
+    pub earnings_notice_days: i64,
+}
+
+impl Default for StocksConfig {
+    fn default() -> Self {
+        StocksConfig {
+            quote_source: QuoteSource::Yahoo,
+            fixture_path: "data/fixtures/quotes.json".into(),
+            history_fixture_dir: "data/fixtures/history".into(),
+            fx_fixture_path: "data/fixtures/fx_rates.json".into(),
+            quote_cache_ttl_secs: 60,
+            search_cache_ttl_secs: 60 * 60 * 24,
+            chart_font_path: "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".into(),
+            off_hours_trades: OffHoursTrades::Reject,
+            earnings_notice_days: 2,
+        }
+    }
+}
+
+/// Reads data/stocks.ron. The file is optional: the defaults are used when it does not exist.
+pub fn read_stocks_config() -> StocksConfig {
+    let file_path = "data/stocks.ron";
+    let file = match std::fs::File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return StocksConfig::default(),
+    };
+
+    match from_reader(file) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Failed to read stocks.ron, using the default stocks configuration: {}", err);
+            StocksConfig::default()
+        }
+    }
+}
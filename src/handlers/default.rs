@@ -2,11 +2,13 @@ use serenity::{
     async_trait,
     client::{Context, EventHandler},
     http::CacheHttp,
-    model::{channel::Message, guild::Member, id::GuildId},
+    model::{application::interaction::Interaction, channel::Message, guild::Member, id::GuildId},
 };
 use std::sync::Arc;
 
+use crate::persistence::edge::requests::update::sync_discord_names;
 use crate::utils::bot_reply::reply_question;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
 use crate::{datastructs::SanitizedMessage, plugins::*};
 
 pub struct DefaultHandler;
@@ -23,14 +25,42 @@ impl EventHandler for DefaultHandler {
         // Tea time announcer
         tea_time::tea_time_announcer(Arc::new(ctx.clone())).await;
         weather::task_thunderstorm_sentry(Arc::new(ctx.clone())).await;
+        portfolio_snapshots::task_portfolio_snapshots().await;
+        pending_orders::task_pending_orders(Arc::new(ctx.clone())).await;
+        price_alerts::task_price_alerts(Arc::new(ctx.clone())).await;
+        corporate_actions::task_corporate_actions(Arc::new(ctx.clone())).await;
+        margin_calls::task_margin_calls(Arc::new(ctx.clone())).await;
+        seasons::task_season_ends(Arc::new(ctx.clone())).await;
+        earnings::task_earnings_notifications(Arc::new(ctx.clone())).await;
+        shop_roles::task_temp_role_expiry(Arc::new(ctx.clone())).await;
+        slash_commands::register_slash_commands(&ctx).await;
         release_announcer::task_game_release_announcement_sentry(Arc::new(ctx.clone())).await;
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        slash_commands::handle_interaction(&ctx, interaction).await;
+    }
+
     async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
         println!("A new client connects the server, sending instructions...");
         join_message::send_join_message(Arc::new(ctx.clone()), new_member).await;
     }
 
+    async fn guild_member_update(&self, _ctx: Context, old_if_available: Option<Member>, new: Member) {
+        // Keep the names shown by the bot in sync, registered or not is checked by the update
+        let unchanged: bool = matches!(
+            old_if_available,
+            Some(old) if old.display_name() == new.display_name() && old.user.name == new.user.name
+        );
+        if new.user.bot || unchanged {
+            return;
+        }
+
+        if let Err(err) = sync_discord_names(new.user.id.to_string(), new.user.name.clone(), new.display_name().into_owned()).await {
+            log_error(format!("(guild_member_update) Could not sync the names of a user: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+        }
+    }
+
     #[allow(unused_variables)]
     async fn message(&self, ctx: Context, msg: Message) {
         // Check if the message mentions the bot
@@ -1,7 +1,8 @@
-use serenity::framework::standard::StandardFramework;
+use serenity::framework::standard::{CommandGroup, StandardFramework};
+use serenity::model::channel::Message;
 use serenity::model::id::UserId;
-use serenity::prelude::GatewayIntents;
-use serenity::{client::Client, framework::standard::macros::group};
+use serenity::prelude::{Context, GatewayIntents};
+use serenity::{client::Client, framework::standard::macros::{group, hook}};
 use std::collections::{hash_map::RandomState, HashSet};
 use dotenv::dotenv;
 
@@ -40,16 +41,65 @@ pub struct Fun;
 pub struct Utilities;
 
 #[group]
-#[commands(stocks, buy_stock, sell_stock, consult_portfolio, get_financial_infos)]
+#[commands(
+    stocks,
+    search_ticker,
+    buy_stock,
+    sell_stock,
+    short,
+    cover,
+    margin,
+    consult_portfolio,
+    get_financial_infos,
+    history,
+    statement,
+    leaderboard,
+    order,
+    orders,
+    alert,
+    alerts,
+    watch,
+    watchlist,
+    earnings,
+    chart,
+    ta,
+    performance,
+    season
+)]
 pub struct Stocks;
 
 #[group]
+#[commands(trading_rules, start_season, end_season, eco, shop_item)]
 pub struct Admin;
 
 #[group]
-#[commands(register)]
+#[commands(register, timezone, profile, unregister)]
 pub struct Account;
 
+#[group]
+#[commands(daily, pay, balance, shop, buy, inventory, use_item)]
+pub struct Economy;
+
+/// Commands of these groups need an account, which is opened on the first one used
+static ACCOUNT_GROUPS: [&CommandGroup; 3] = [&STOCKS_GROUP, &ECONOMY_GROUP, &ACCOUNT_GROUP];
+/// register tells whether it opened the account, unregister must not open one
+static NO_AUTO_REGISTER: [&str; 2] = ["register", "unregister"];
+
+#[hook]
+async fn before_command(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    let needs_account: bool = ACCOUNT_GROUPS.iter().any(|group| {
+        group.options.commands.iter().any(|command| command.options.names.contains(&command_name))
+    });
+
+    if needs_account && !NO_AUTO_REGISTER.contains(&command_name) {
+        if let Some(Some(money)) = buckets::ensure_registered(ctx, msg).await {
+            let _ = msg.reply(&ctx.http, format!("Welcome! Your account was opened with €{:.2}, see !profile.", money)).await;
+        }
+    }
+
+    true
+}
+
 #[tokio::main]
 async fn main() {
     let infos: BotInfo = read_bot_infos();
@@ -74,12 +124,14 @@ async fn main() {
             c.no_dm_prefix(false);
             c
         })
+        .before(before_command)
         .group(&HELPERS_GROUP)
         .group(&FUN_GROUP)
         .group(&UTILITIES_GROUP)
         .group(&ADMIN_GROUP)
         .group(&STOCKS_GROUP)
-        .group(&ACCOUNT_GROUP);
+        .group(&ACCOUNT_GROUP)
+        .group(&ECONOMY_GROUP);
 
     let handler: DefaultHandler = DefaultHandler::new();
 
@@ -1,10 +1,246 @@
 pub mod requests {
     use super::super::edge_models::*;
+    use edgedb_protocol::model::{Json, Uuid};
 
     async fn get_conn() -> anyhow::Result<edgedb_tokio::Client, edgedb_tokio::Error> {
         edgedb_tokio::create_client().await
     }
 
+    /// Kinds of money movements recorded in the ledger (Discord::Transaction.kind).
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum LedgerKind {
+        Buy,
+        Sell,
+        Grant,
+        Transfer,
+        Dividend,
+        Split,
+        Short,
+        Cover,
+        Interest,
+        MarginCall,
+        Daily,
+        Purchase,
+        /// Money an account starts with, the reconciliation counts from the latest one
+        Opening,
+    }
+
+    impl std::fmt::Display for LedgerKind {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                LedgerKind::Buy => write!(f, "buy"),
+                LedgerKind::Sell => write!(f, "sell"),
+                LedgerKind::Grant => write!(f, "grant"),
+                LedgerKind::Transfer => write!(f, "transfer"),
+                LedgerKind::Dividend => write!(f, "dividend"),
+                LedgerKind::Split => write!(f, "split"),
+                LedgerKind::Short => write!(f, "short"),
+                LedgerKind::Cover => write!(f, "cover"),
+                LedgerKind::Interest => write!(f, "interest"),
+                LedgerKind::MarginCall => write!(f, "margin_call"),
+                LedgerKind::Daily => write!(f, "daily"),
+                LedgerKind::Purchase => write!(f, "purchase"),
+                LedgerKind::Opening => write!(f, "opening"),
+            }
+        }
+    }
+
+    /// A ledger entry to append. `amount` is the signed cash movement in the account currency, `balance_after` the resulting money.
+    pub struct LedgerEntry {
+        pub user_id: String,
+        pub kind: LedgerKind,
+        pub ticker: Option<String>,
+        pub price: Option<f64>,
+        pub currency: Option<String>,
+        pub fx_rate: Option<f64>,
+        pub quantity: Option<f64>,
+        pub amount: f64,
+        pub fees: f64,
+        pub balance_after: f64,
+        pub counterparty_id: Option<String>,
+        pub note: Option<String>,
+    }
+
+    /// Appends an entry to the ledger, as part of the caller's transaction.
+    async fn record_transaction(tx: &mut edgedb_tokio::Transaction, entry: LedgerEntry) -> anyhow::Result<(), edgedb_tokio::Error> {
+        tx.execute("insert Discord::Transaction {
+            user_id := <str>$0,
+            kind := <str>$1,
+            ticker := <optional str>$2,
+            price := <optional float64>$3,
+            quantity := <optional float64>$4,
+            amount := <float64>$5,
+            fees := <float64>$6,
+            balance_after := <float64>$7,
+            counterparty_id := <optional str>$8,
+            note := <optional str>$9,
+            currency := <optional str>$10,
+            fx_rate := <optional float64>$11
+        }", &(
+            entry.user_id,
+            entry.kind.to_string(),
+            entry.ticker,
+            entry.price,
+            entry.quantity,
+            entry.amount,
+            entry.fees,
+            entry.balance_after,
+            entry.counterparty_id,
+            entry.note,
+            entry.currency,
+            entry.fx_rate,
+        )).await
+    }
+
+    /// Economy administration actions recorded in the audit log (Discord::EconomyAudit.action).
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum EconomyAction {
+        Grant,
+        Revoke,
+        Set,
+        Freeze,
+        Unfreeze,
+        Reconcile,
+    }
+
+    impl std::fmt::Display for EconomyAction {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                EconomyAction::Grant => write!(f, "grant"),
+                EconomyAction::Revoke => write!(f, "revoke"),
+                EconomyAction::Set => write!(f, "set"),
+                EconomyAction::Freeze => write!(f, "freeze"),
+                EconomyAction::Unfreeze => write!(f, "unfreeze"),
+                EconomyAction::Reconcile => write!(f, "reconcile"),
+            }
+        }
+    }
+
+    /// An audit log entry to append, see LedgerEntry for the amounts.
+    pub struct AuditEntry {
+        pub owner_id: String,
+        pub user_id: Option<String>,
+        pub action: EconomyAction,
+        pub amount: Option<f64>,
+        pub balance_before: Option<f64>,
+        pub balance_after: Option<f64>,
+        pub reason: String,
+    }
+
+    /// Appends an entry to the economy audit log, as part of the caller's transaction.
+    async fn record_audit(tx: &mut edgedb_tokio::Transaction, entry: AuditEntry) -> anyhow::Result<(), edgedb_tokio::Error> {
+        tx.execute("insert Discord::EconomyAudit {
+            owner_id := <str>$0,
+            user_id := <optional str>$1,
+            action := <str>$2,
+            amount := <optional float64>$3,
+            balance_before := <optional float64>$4,
+            balance_after := <optional float64>$5,
+            reason := <str>$6
+        }", &(
+            entry.owner_id,
+            entry.user_id,
+            entry.action.to_string(),
+            entry.amount,
+            entry.balance_before,
+            entry.balance_after,
+            entry.reason,
+        )).await
+    }
+
+    /// Usernames are unique but can be given up and taken by someone else on Discord: an account
+    /// still holding `username` gets its unique id as username until its own names are synced.
+    async fn free_username(tx: &mut edgedb_tokio::Transaction, unique_id: &str, username: &str) -> anyhow::Result<(), edgedb_tokio::Error> {
+        tx.execute("
+            update Discord::User
+            filter .username = <str>$1 and .unique_id != <str>$0
+            set { username := .unique_id }", &(unique_id.to_string(), username.to_string())).await
+    }
+
+    /// Whether the account is frozen, as part of the caller's transaction so that a freeze cannot slip in between.
+    async fn is_frozen(tx: &mut edgedb_tokio::Transaction, unique_id: &str) -> anyhow::Result<bool, edgedb_tokio::Error> {
+        tx.query_required_single("
+            select exists (
+                select Discord::User
+                filter .unique_id = <str>$0 and .frozen
+            )", &(unique_id.to_string(),)).await
+    }
+
+    /// Money, positions and margin guild of a user, as part of the caller's transaction so that margin checks
+    /// are made on the account the trade applies to.
+    async fn read_portfolio_user(tx: &mut edgedb_tokio::Transaction, unique_id: &str) -> anyhow::Result<Option<PortfolioUser>, edgedb_tokio::Error> {
+        tx.query_single("
+            select Discord::User {
+                unique_id,
+                display_name,
+                money,
+                portfolio: {
+                    lines: {
+                        ticker,
+                        quantity,
+                        created_at,
+                        bought_at,
+                        currency,
+                        fx_rate
+                    } order by .created_at asc
+                },
+                shorts := (
+                    select Discord::ShortPosition {
+                        id,
+                        ticker,
+                        quantity,
+                        sold_at,
+                        currency,
+                        fx_rate
+                    }
+                    filter .user_id = Discord::User.unique_id
+                    order by .created asc
+                ),
+                margin_guild_id
+            }
+            filter .unique_id = <str>$0", &(unique_id.to_string(),)).await
+    }
+
+    /// Makes the margin rules of `guild_id` apply to a user who borrows after trading there. A user who was already
+    /// borrowing (`user` is the account before the trade) keeps the guild they started borrowing in.
+    async fn bind_margin_guild(tx: &mut edgedb_tokio::Transaction, user: &PortfolioUser, guild_id: Option<String>) -> anyhow::Result<(), edgedb_tokio::Error> {
+        let already_borrowing: bool = user.money < 0.0 || !user.shorts.is_empty();
+        let guild_id: String = match guild_id {
+            Some(_) if already_borrowing && user.margin_guild_id.is_some() => return Ok(()),
+            Some(guild_id) if user.margin_guild_id.as_ref() != Some(&guild_id) => guild_id,
+            _ => return Ok(()),
+        };
+
+        tx.execute("
+            update Discord::User
+            filter .unique_id = <str>$0
+            set { margin_guild_id := <str>$1 }", &(user.unique_id.clone(), guild_id)).await
+    }
+
+    /// Whether the pending order being filled is still pending, as part of the fill's transaction so that a
+    /// cancellation either lands before the fill or waits for it. Trades which are not order fills pass.
+    async fn is_order_pending(tx: &mut edgedb_tokio::Transaction, order_id: Option<Uuid>) -> anyhow::Result<bool, edgedb_tokio::Error> {
+        match order_id {
+            Some(order_id) => tx.query_required_single("
+                select exists (
+                    select Discord::PendingOrder
+                    filter .id = <uuid>$0 and .status = 'pending'
+                )", &(order_id,)).await,
+            None => Ok(true),
+        }
+    }
+
+    /// Marks the pending order being filled as filled at `price`, in the same transaction as the trade.
+    async fn mark_order_filled(tx: &mut edgedb_tokio::Transaction, order_id: Option<Uuid>, price: f64) -> anyhow::Result<(), edgedb_tokio::Error> {
+        match order_id {
+            Some(order_id) => tx.execute("
+                update Discord::PendingOrder
+                filter .id = <uuid>$0
+                set { status := 'filled', fill_price := <float64>$1, updated := datetime_of_statement() }", &(order_id, price)).await,
+            None => Ok(()),
+        }
+    }
+
     pub mod read 
     {
         use super::*;
@@ -19,7 +255,8 @@ pub mod requests {
                             username,
                             unique_id,
                             display_name,
-                            money
+                            money,
+                            timezone
                         }
                         filter .unique_id = <str>$0", &(unique_id,)).await;
                     match result {
@@ -30,7 +267,212 @@ pub mod requests {
                 Err(error) => Err(error),
             }
         }
-    
+
+        /// Items of a guild shop, cheapest first. Disabled items are only listed for the administrators.
+        pub async fn get_shop_items(guild_id: String, include_disabled: bool) -> anyhow::Result<Vec<ShopItem>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::ShopItem {
+                            name,
+                            kind,
+                            price,
+                            description,
+                            role_id,
+                            duration_hours,
+                            colour,
+                            stock,
+                            enabled
+                        }
+                        filter .guild_id = <str>$0 and (<bool>$1 or .enabled)
+                        order by .price asc then .name asc", &(guild_id, include_disabled)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        pub async fn get_inventory(user_id: String, guild_id: String) -> anyhow::Result<Vec<InventoryEntry>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::InventoryItem {
+                            item_name,
+                            kind,
+                            role_id,
+                            quantity,
+                            expires_epoch := <float64>datetime_get(.expires, 'epochseconds')
+                        }
+                        filter .user_id = <str>$0 and .guild_id = <str>$1
+                        order by .kind asc then .item_name asc", &(user_id, guild_id)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Temporary roles bought in a shop whose time is up.
+        pub async fn get_expired_temp_roles() -> anyhow::Result<Vec<ExpiredRole>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::InventoryItem {
+                            user_id,
+                            guild_id,
+                            item_name,
+                            role_id
+                        }
+                        filter .kind = 'temp_role' and .expires <= datetime_of_statement()", &()).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Roles given by the items a user bought, in every guild, to take back before the account goes.
+        pub async fn get_owned_roles(user_id: String) -> anyhow::Result<Vec<OwnedRole>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::InventoryItem {
+                            guild_id,
+                            item_name,
+                            role_id
+                        }
+                        filter .user_id = <str>$0 and exists .role_id", &(user_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        pub async fn is_account_frozen(unique_id: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_required_single("
+                        select exists (
+                            select Discord::User
+                            filter .unique_id = <str>$0 and .frozen
+                        )", &(unique_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Money of every user (or of one) next to the sum of their ledger entries, for the reconciliation.
+        pub async fn get_ledger_balances(unique_id: Option<String>) -> anyhow::Result<Vec<LedgerBalance>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::User {
+                            unique_id,
+                            display_name,
+                            money,
+                            ledger_sum := (
+                                with
+                                    entries := (select Discord::Transaction filter .user_id = Discord::User.unique_id),
+                                    # A re-created account starts over from its own opening entry
+                                    opened := max((select entries filter .kind = 'opening').created),
+                                    counted := (select entries filter .created >= (opened ?? .created))
+                                select sum(counted.amount - counted.fees)
+                            ),
+                            entries := count((select Discord::Transaction filter .user_id = Discord::User.unique_id)),
+                            last_balance := (
+                                select Discord::Transaction
+                                filter .user_id = Discord::User.unique_id
+                                order by .created desc
+                                limit 1
+                            ).balance_after
+                        }
+                        filter (not exists <optional str>$0 or .unique_id ?= <optional str>$0)
+                        order by .display_name asc", &(unique_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Latest audit log entries, of one user or of everyone.
+        pub async fn get_economy_audit(user_id: Option<String>, limit: i64) -> anyhow::Result<Vec<EconomyAuditEntry>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::EconomyAudit {
+                            owner_id,
+                            user_id,
+                            action,
+                            amount,
+                            balance_before,
+                            balance_after,
+                            reason,
+                            created_local
+                        }
+                        filter (not exists <optional str>$0 or .user_id ?= <optional str>$0)
+                        order by .created desc
+                        limit <int64>$1", &(user_id, limit)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        pub async fn get_daily_state(unique_id: String) -> anyhow::Result<Option<DailyState>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_single("
+                        select Discord::User {
+                            money,
+                            daily_streak,
+                            last_daily_epoch := <float64>datetime_get(.last_daily, 'epochseconds')
+                        }
+                        filter .unique_id = <str>$0", &(unique_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Unique ids of the frozen accounts, which the scheduled jobs leave untouched.
+        pub async fn get_frozen_account_ids() -> anyhow::Result<Vec<String>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::User.unique_id
+                        filter Discord::User.frozen", &()).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Account details shown by !profile. None if the user is not registered.
+        pub async fn get_profile(unique_id: String) -> anyhow::Result<Option<Profile>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_single("
+                        with user := (select Discord::User filter .unique_id = <str>$0)
+                        select user {
+                            display_name,
+                            money,
+                            timezone,
+                            daily_streak,
+                            frozen,
+                            frozen_reason,
+                            registered_epoch := <float64>datetime_get(.registered, 'epochseconds'),
+                            pending_orders := count((
+                                select Discord::PendingOrder
+                                filter .user_id = user.unique_id and .status = 'pending'
+                            )),
+                            price_alerts := count((select Discord::PriceAlert filter .user_id = user.unique_id)),
+                            watched_tickers := count((select Discord::WatchlistEntry filter .user_id = user.unique_id))
+                        }", &(unique_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
         pub async fn get_latest_error_logs(limit: i32) -> anyhow::Result<Option<Vec<ErrorLog>>, edgedb_tokio::Error> 
         {
             match get_conn().await {
@@ -54,52 +496,2452 @@ pub mod requests {
             }
         }
         
+        /// Returns the ledger of a user, newest first, optionally restricted to a ticker and to entries after `since_epoch` (seconds).
+        pub async fn get_user_transactions(unique_id: String, ticker: Option<String>, since_epoch: Option<f64>, limit: Option<i64>) -> anyhow::Result<Vec<LedgerTransaction>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::Transaction {
+                            kind,
+                            ticker,
+                            price,
+                            currency,
+                            fx_rate,
+                            quantity,
+                            amount,
+                            fees,
+                            balance_after,
+                            counterparty_id,
+                            note,
+                            created_local
+                        }
+                        filter .user_id = <str>$0
+                            and (not exists <optional str>$1 or .ticker ?= <optional str>$1)
+                            and ((.created >= to_datetime(<optional float64>$2)) ?? true)
+                        order by .created desc
+                        limit <optional int64>$3", &(unique_id, ticker, since_epoch, limit)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Every purchase of a ticker by a user, oldest first, with the quote price paid.
+        pub async fn get_purchase_points(unique_id: String, ticker: String) -> anyhow::Result<Vec<PurchasePoint>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::Transaction {
+                            epoch := <float64>datetime_get(.created, 'epochseconds'),
+                            price := assert_exists(.price)
+                        }
+                        filter .user_id = <str>$0 and .ticker = <str>$1 and .kind = 'buy' and exists .price
+                        order by .created asc", &(unique_id, ticker)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
         pub async fn get_user_portfolio(unique_id: String) -> anyhow::Result<Option<PortfolioUser>, edgedb_tokio::Error>
         {
-            unimplemented!()
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_single("
+                        select Discord::User {
+                            unique_id,
+                            display_name,
+                            money,
+                            portfolio: {
+                                lines: {
+                                    ticker,
+                                    quantity,
+                                    created_at,
+                                    bought_at,
+                                    currency,
+                                    fx_rate
+                                } order by .created_at asc
+                            },
+                            shorts := (
+                                select Discord::ShortPosition {
+                                    id,
+                                    ticker,
+                                    quantity,
+                                    sold_at,
+                                    currency,
+                                    fx_rate
+                                }
+                                filter .user_id = Discord::User.unique_id
+                                order by .created asc
+                            ),
+                            margin_guild_id
+                        }
+                        filter .unique_id = <str>$0", &(unique_id,)).await
+                },
+                Err(error) => Err(error),
+            }
         }
-    }
 
-    pub mod create {
-        use super::*;
+        /// Every registered user along with their portfolio.
+        pub async fn get_all_portfolios() -> anyhow::Result<Vec<PortfolioUser>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::User {
+                            unique_id,
+                            display_name,
+                            money,
+                            portfolio: {
+                                lines: {
+                                    ticker,
+                                    quantity,
+                                    created_at,
+                                    bought_at,
+                                    currency,
+                                    fx_rate
+                                } order by .created_at asc
+                            },
+                            shorts := (
+                                select Discord::ShortPosition {
+                                    id,
+                                    ticker,
+                                    quantity,
+                                    sold_at,
+                                    currency,
+                                    fx_rate
+                                }
+                                filter .user_id = Discord::User.unique_id
+                                order by .created asc
+                            ),
+                            margin_guild_id
+                        }", &()).await
+                },
+                Err(error) => Err(error),
+            }
+        }
 
-        pub async fn create_discord_user(username: String, display_name: String, unique_id: String) -> Option<edgedb_tokio::Error> {
+        /// Snapshots taken after `since_epoch` (seconds), oldest first.
+        pub async fn get_snapshots_since(since_epoch: f64) -> anyhow::Result<Vec<NetWorthSnapshot>, edgedb_tokio::Error>
+        {
             match get_conn().await {
                 Ok(conn) => {
-                    match conn.execute::<(String, String, String)>("INSERT Discord::User {
-                        unique_id := <str>$0, username := <str>$1, display_name := <str>$2
-                    }", &(unique_id, username, display_name)).await {
-                        Ok(_) => {
-                            return None;
-                        },
-                        Err(error) => {
-                            return Some(error);
-                        },
-                    }
+                    conn.query("
+                        select Discord::NetWorthSnapshot {
+                            user_id,
+                            day,
+                            cash,
+                            net_worth
+                        }
+                        filter .created >= to_datetime(<float64>$0)
+                        order by .created asc", &(since_epoch,)).await
                 },
-                Err(error) => {
-                    return Some(error);
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Ledger entries of the given kinds recorded after `since_epoch` (seconds), oldest first, with their Europe/Brussels day.
+        pub async fn get_ledger_flows_since(since_epoch: f64, kinds: &[&str]) -> anyhow::Result<Vec<LedgerFlow>, edgedb_tokio::Error>
+        {
+            let kinds: Json = Json::new_unchecked(serde_json::json!(kinds).to_string());
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::Transaction {
+                            user_id,
+                            day := <str>cal::to_local_date(.created, 'Europe/Brussels'),
+                            amount
+                        }
+                        filter .created >= to_datetime(<float64>$0)
+                            and .kind in <str>json_array_unpack(<json>$1)
+                        order by .created asc", &(since_epoch, kinds)).await
                 },
+                Err(error) => Err(error),
             }
         }
-    
-        pub async fn create_error_log(log: String, level: String, channel_name: String) -> anyhow::Result<(), edgedb_tokio::Error> {
+
+        /// End of day valuations of a user, oldest first, optionally only those taken after `since_epoch` (seconds).
+        pub async fn get_user_snapshots(user_id: String, since_epoch: Option<f64>) -> anyhow::Result<Vec<NetWorthSnapshot>, edgedb_tokio::Error>
+        {
             match get_conn().await {
                 Ok(conn) => {
-                    let result = conn.execute("insert Dev::ErrorLog {
-                        log := <str>$0,
-                        level := <str>$1,
-                        channel_name := <str>$2
-                    }", &(log, level, channel_name)).await;
-                    if result.is_err() {
-                        return Err(result.unwrap_err());
-                    }
-                    Ok(())
-                }
-                Err(err) => Err(err)
+                    conn.query("
+                        select Discord::NetWorthSnapshot {
+                            user_id,
+                            day,
+                            cash,
+                            net_worth
+                        }
+                        filter .user_id = <str>$0
+                            and ((.created >= to_datetime(<optional float64>$1)) ?? true)
+                        order by .day asc", &(user_id, since_epoch)).await
+                },
+                Err(error) => Err(error),
             }
         }
-        
+
+        /// End of day valuations of every position of a user, oldest first, optionally only those taken after `since_epoch` (seconds).
+        pub async fn get_user_position_snapshots(user_id: String, since_epoch: Option<f64>) -> anyhow::Result<Vec<PositionSnapshot>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::PositionSnapshot {
+                            day,
+                            ticker,
+                            quantity,
+                            market_value,
+                            cost_basis
+                        }
+                        filter .user_id = <str>$0
+                            and ((.created >= to_datetime(<optional float64>$1)) ?? true)
+                        order by .day asc then .ticker asc", &(user_id, since_epoch)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Short selling and margin rules of a guild, `None` when the guild never changed them.
+        pub async fn get_guild_trading_settings(guild_id: String) -> anyhow::Result<Option<GuildTradingSettings>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_single("
+                        select Discord::GuildTradingSettings {
+                            guild_id,
+                            short_selling,
+                            margin,
+                            interest_rate,
+                            initial_margin,
+                            maintenance_margin
+                        }
+                        filter .guild_id = <str>$0", &(guild_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// The season currently running in a guild, if any.
+        pub async fn get_active_season(guild_id: String) -> anyhow::Result<Option<Season>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_single("
+                        select Discord::Season {
+                            number,
+                            guild_id,
+                            channel_id,
+                            starting_balance,
+                            started_epoch := <float64>datetime_get(.started, 'epochseconds'),
+                            ends_epoch := <float64>datetime_get(.ends, 'epochseconds'),
+                            ended
+                        }
+                        filter .guild_id = <str>$0 and not .ended and .ends > datetime_of_statement()
+                        limit 1", &(guild_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Seasons of a guild, newest first. Restricted to the finished ones when `ended_only` is set.
+        pub async fn get_guild_seasons(guild_id: String, ended_only: bool) -> anyhow::Result<Vec<Season>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::Season {
+                            number,
+                            guild_id,
+                            channel_id,
+                            starting_balance,
+                            started_epoch := <float64>datetime_get(.started, 'epochseconds'),
+                            ends_epoch := <float64>datetime_get(.ends, 'epochseconds'),
+                            ended
+                        }
+                        filter .guild_id = <str>$0 and (.ended or not <bool>$1)
+                        order by .number desc", &(guild_id, ended_only)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Running seasons whose end date has passed, in every guild.
+        pub async fn get_due_seasons() -> anyhow::Result<Vec<Season>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::Season {
+                            number,
+                            guild_id,
+                            channel_id,
+                            starting_balance,
+                            started_epoch := <float64>datetime_get(.started, 'epochseconds'),
+                            ends_epoch := <float64>datetime_get(.ends, 'epochseconds'),
+                            ended
+                        }
+                        filter not .ended and .ends <= datetime_of_statement()", &()).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Participants of a season, by final rank once it ended. Restricted to a user when `user_id` is given.
+        pub async fn get_season_participants(season: i64, user_id: Option<String>) -> anyhow::Result<Vec<SeasonParticipant>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::SeasonParticipant {
+                            user_id,
+                            display_name,
+                            money,
+                            lines: { ticker, quantity, created_at, bought_at, currency, fx_rate },
+                            final_net_worth,
+                            final_rank
+                        }
+                        filter .season.number = <int64>$0 and (not exists <optional str>$1 or .user_id ?= <optional str>$1)
+                        order by .final_rank asc empty last then .joined asc", &(season, user_id)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Orders still waiting for their trigger price. Restricted to a user when `user_id` is given.
+        pub async fn get_pending_orders(user_id: Option<String>) -> anyhow::Result<Vec<PendingOrder>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::PendingOrder {
+                            id,
+                            number,
+                            user_id,
+                            channel_id,
+                            side,
+                            ticker,
+                            quantity,
+                            order_type,
+                            trigger_price,
+                            status
+                        }
+                        filter .status = 'pending' and (not exists <optional str>$0 or .user_id ?= <optional str>$0)
+                        order by .number asc", &(user_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Price alerts, restricted to a user when `user_id` is given.
+        pub async fn get_price_alerts(user_id: Option<String>) -> anyhow::Result<Vec<PriceAlert>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::PriceAlert {
+                            id,
+                            number,
+                            user_id,
+                            channel_id,
+                            ticker,
+                            condition,
+                            threshold,
+                            rearm,
+                            armed
+                        }
+                        filter not exists <optional str>$0 or .user_id ?= <optional str>$0
+                        order by .number asc", &(user_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Tickers watched by a user, alphabetically.
+        pub async fn get_watchlist(user_id: String) -> anyhow::Result<Vec<String>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::WatchlistEntry.ticker
+                        filter Discord::WatchlistEntry.user_id = <str>$0
+                        order by Discord::WatchlistEntry.ticker asc", &(user_id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Every watched ticker of every user.
+        pub async fn get_all_watchlist_entries() -> anyhow::Result<Vec<WatchlistEntry>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query("
+                        select Discord::WatchlistEntry {
+                            user_id,
+                            ticker
+                        }
+                        order by .ticker asc", &()).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    pub mod update {
+        use super::*;
+
+        /// Outcome of a paper trading purchase.
+        pub enum BuyOutcome {
+            /// The line was added to the portfolio, and the user has `remaining_money` left.
+            Bought { remaining_money: f64 },
+            /// The user cannot afford the purchase, even with the `credit_limit` of their margin.
+            InsufficientFunds { money: f64, credit_limit: f64 },
+            UserNotFound,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+            /// The pending order being filled was cancelled or filled meanwhile, nothing was bought.
+            OrderClosed,
+        }
+
+        /// Debits the user of `quantity * price`, converted with `fx_rate`, and adds a new line to their portfolio.
+        ///
+        /// `price` is in the quote `currency`, `fx_rate` converts it into the account currency.
+        /// `credit_limit` tells how far below zero the money of the account may go when buying on margin, 0 for cash
+        /// accounts. A purchase on margin makes the rules of `guild_id` apply to the user, unless they already borrow.
+        /// `order_id` is the pending order this purchase fills, it is marked as filled along with the purchase.
+        /// Everything happens in a single transaction, so the balance and margin checks and the debit cannot race.
+        #[allow(clippy::too_many_arguments)]
+        pub async fn buy_stock_line(unique_id: String, guild_id: Option<String>, ticker: String, quantity: f64, price: f64, currency: String, fx_rate: f64, order_id: Option<Uuid>, credit_limit: impl Fn(&PortfolioUser) -> f64) -> anyhow::Result<BuyOutcome, edgedb_tokio::Error>
+        {
+            let cost: f64 = quantity * price * fx_rate;
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let guild_id = guild_id.clone();
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        let credit_limit = &credit_limit;
+                        async move {
+                            let user: PortfolioUser = match read_portfolio_user(&mut tx, &unique_id).await? {
+                                Some(user) => user,
+                                None => return Ok(BuyOutcome::UserNotFound),
+                            };
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(BuyOutcome::AccountFrozen);
+                            }
+                            if !is_order_pending(&mut tx, order_id).await? {
+                                return Ok(BuyOutcome::OrderClosed);
+                            }
+
+                            let money: f64 = user.money;
+                            let credit_limit: f64 = credit_limit(&user);
+                            if money + credit_limit < cost {
+                                return Ok(BuyOutcome::InsufficientFunds { money, credit_limit });
+                            }
+
+                            // Users registered before the portfolios existed do not have one yet
+                            tx.execute("
+                                update Discord::User
+                                filter .unique_id = <str>$0 and not exists .portfolio
+                                set { portfolio := (insert Discord::Portfolio) }", &(unique_id.clone(),)).await?;
+
+                            tx.execute("
+                                with
+                                    user := (select Discord::User filter .unique_id = <str>$0),
+                                    line := (insert Discord::PortfolioLine {
+                                        ticker := <str>$1,
+                                        quantity := <float64>$2,
+                                        bought_at := <float64>$3,
+                                        currency := <str>$4,
+                                        fx_rate := <float64>$5
+                                    })
+                                update Discord::Portfolio
+                                filter Discord::Portfolio = user.portfolio
+                                set { lines += line }", &(unique_id.clone(), ticker.clone(), quantity, price, currency.clone(), fx_rate)).await?;
+
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money - <float64>$1 }
+                                ).money
+                                limit 1", &(unique_id.clone(), cost)).await?;
+
+                            if remaining_money < 0.0 {
+                                bind_margin_guild(&mut tx, &user, guild_id).await?;
+                            }
+                            mark_order_filled(&mut tx, order_id, price).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Buy,
+                                ticker: Some(ticker),
+                                price: Some(price),
+                                currency: Some(currency),
+                                fx_rate: Some(fx_rate),
+                                quantity: Some(quantity),
+                                amount: -cost,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: None,
+                            }).await?;
+
+                            Ok(BuyOutcome::Bought { remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Realized result of selling (part of) a single lot, in the account currency.
+        pub struct RealizedLot {
+            pub quantity: f64,
+            pub bought_at: f64,
+            pub profit: f64,
+            /// Part of the profit due to the stock price moving
+            pub price_profit: f64,
+            /// Part of the profit due to the exchange rate moving since the purchase
+            pub fx_profit: f64,
+        }
+
+        /// Outcome of a paper trading sale.
+        pub enum SellOutcome {
+            /// The lots were consumed oldest first, `proceeds` were credited and the user has `remaining_money` left.
+            Sold { lots: Vec<RealizedLot>, proceeds: f64, remaining_money: f64 },
+            /// The user tried to sell more than they `owned`.
+            Oversold { owned: f64 },
+            UserNotFound,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+            /// The pending order being filled was cancelled or filled meanwhile, nothing was sold.
+            OrderClosed,
+        }
+
+        /// Quantities below this are considered to be zero, to absorb floating point noise on fractional shares.
+        const QUANTITY_EPSILON: f64 = 1e-9;
+
+        /// Sells `quantity` shares out of `lots`, oldest first: emptied lines are deleted, the last one is reduced.
+        /// Works for the lines of main portfolios and of season accounts alike.
+        async fn consume_lots(tx: &mut edgedb_tokio::Transaction, lots: &[StockLot], quantity: f64, price: f64, fx_rate: f64) -> anyhow::Result<Vec<RealizedLot>, edgedb_tokio::Error>
+        {
+            let mut left_to_sell: f64 = quantity;
+            let mut realized: Vec<RealizedLot> = vec![];
+
+            for lot in lots.iter() {
+                if left_to_sell <= QUANTITY_EPSILON {
+                    break;
+                }
+
+                let sold: f64 = left_to_sell.min(lot.quantity);
+                let lot_left: f64 = lot.quantity - sold;
+
+                if lot_left <= QUANTITY_EPSILON {
+                    // Links are restricted on target deletion, detach the line first
+                    tx.execute("
+                        with line := (select Discord::PortfolioLine filter .id = <uuid>$0)
+                        update Discord::Portfolio
+                        filter line in .lines
+                        set { lines -= line }", &(lot.id,)).await?;
+                    tx.execute("
+                        with line := (select Discord::PortfolioLine filter .id = <uuid>$0)
+                        update Discord::SeasonParticipant
+                        filter line in .lines
+                        set { lines -= line }", &(lot.id,)).await?;
+                    tx.execute("
+                        delete Discord::PortfolioLine
+                        filter .id = <uuid>$0", &(lot.id,)).await?;
+                } else {
+                    tx.execute("
+                        update Discord::PortfolioLine
+                        filter .id = <uuid>$0
+                        set { quantity := <float64>$1 }", &(lot.id, lot_left)).await?;
+                }
+
+                let price_profit: f64 = (price - lot.bought_at) * lot.fx_rate * sold;
+                let fx_profit: f64 = price * (fx_rate - lot.fx_rate) * sold;
+                realized.push(RealizedLot {
+                    quantity: sold,
+                    bought_at: lot.bought_at,
+                    profit: price_profit + fx_profit,
+                    price_profit,
+                    fx_profit,
+                });
+                left_to_sell -= sold;
+            }
+
+            Ok(realized)
+        }
+
+        /// Sells `quantity` shares of `ticker` at `price` (or every share owned when `quantity` is `None`).
+        ///
+        /// `price` is in the quote `currency`, `fx_rate` converts it into the account currency.
+        /// The portfolio lines are consumed first-in-first-out: emptied lines are deleted, the last one is reduced.
+        /// The proceeds are credited in the same transaction, which also marks `order_id`, the pending order this sale fills, as filled.
+        pub async fn sell_stock_lines(unique_id: String, ticker: String, quantity: Option<f64>, price: f64, currency: String, fx_rate: f64, order_id: Option<Uuid>) -> anyhow::Result<SellOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0", &(unique_id.clone(),)).await?;
+
+                            if money.is_none() {
+                                return Ok(SellOutcome::UserNotFound);
+                            }
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(SellOutcome::AccountFrozen);
+                            }
+                            if !is_order_pending(&mut tx, order_id).await? {
+                                return Ok(SellOutcome::OrderClosed);
+                            }
+
+                            let lots: Vec<StockLot> = tx.query("
+                                with user := (select Discord::User filter .unique_id = <str>$0)
+                                select user.portfolio.lines { id, quantity, bought_at, fx_rate }
+                                filter .ticker = <str>$1
+                                order by .created_at asc then .id", &(unique_id.clone(), ticker.clone())).await?;
+
+                            let owned: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                            let quantity: f64 = quantity.unwrap_or(owned);
+
+                            if owned <= QUANTITY_EPSILON || quantity > owned + QUANTITY_EPSILON {
+                                return Ok(SellOutcome::Oversold { owned });
+                            }
+
+                            let realized: Vec<RealizedLot> = consume_lots(&mut tx, &lots, quantity, price, fx_rate).await?;
+
+                            let proceeds: f64 = quantity * price * fx_rate;
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money + <float64>$1 }
+                                ).money
+                                limit 1", &(unique_id.clone(), proceeds)).await?;
+                            mark_order_filled(&mut tx, order_id, price).await?;
+
+                            let realized_profit: f64 = realized.iter().map(|lot| lot.profit).sum();
+                            let realized_fx_profit: f64 = realized.iter().map(|lot| lot.fx_profit).sum();
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Sell,
+                                ticker: Some(ticker),
+                                price: Some(price),
+                                currency: Some(currency),
+                                fx_rate: Some(fx_rate),
+                                quantity: Some(quantity),
+                                amount: proceeds,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: Some(format!("Realized profit/loss: {:+.2} (FX {:+.2})", realized_profit, realized_fx_profit)),
+                            }).await?;
+
+                            Ok(SellOutcome::Sold { lots: realized, proceeds, remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Buys in a season account: same as `buy_stock_line`, without margin and outside of the ledger.
+        /// `UserNotFound` means the user does not take part in the season, or that it is over.
+        pub async fn season_buy(season: i64, user_id: String, ticker: String, quantity: f64, price: f64, currency: String, fx_rate: f64) -> anyhow::Result<BuyOutcome, edgedb_tokio::Error>
+        {
+            let cost: f64 = quantity * price * fx_rate;
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let user_id = user_id.clone();
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::SeasonParticipant.money
+                                filter Discord::SeasonParticipant.season.number = <int64>$0
+                                    and Discord::SeasonParticipant.user_id = <str>$1
+                                    and Discord::SeasonParticipant.season.ends > datetime_of_statement()
+                                    and not Discord::SeasonParticipant.season.ended", &(season, user_id.clone())).await?;
+
+                            let money = match money {
+                                Some(money) => money,
+                                None => return Ok(BuyOutcome::UserNotFound),
+                            };
+                            if is_frozen(&mut tx, &user_id).await? {
+                                return Ok(BuyOutcome::AccountFrozen);
+                            }
+
+                            if money < cost {
+                                return Ok(BuyOutcome::InsufficientFunds { money, credit_limit: 0.0 });
+                            }
+
+                            let remaining_money: f64 = tx.query_required_single("
+                                with line := (insert Discord::PortfolioLine {
+                                    ticker := <str>$2,
+                                    quantity := <float64>$3,
+                                    bought_at := <float64>$4,
+                                    currency := <str>$5,
+                                    fx_rate := <float64>$6
+                                })
+                                select (
+                                    update Discord::SeasonParticipant
+                                    filter .season.number = <int64>$0 and .user_id = <str>$1
+                                    set { lines += line, money := .money - <float64>$7 }
+                                ).money
+                                limit 1", &(season, user_id, ticker, quantity, price, currency, fx_rate, cost)).await?;
+
+                            Ok(BuyOutcome::Bought { remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Sells from a season account, first-in-first-out like `sell_stock_lines`, outside of the ledger.
+        /// `UserNotFound` means the user does not take part in the season, or that it is over.
+        pub async fn season_sell(season: i64, user_id: String, ticker: String, quantity: Option<f64>, price: f64, fx_rate: f64) -> anyhow::Result<SellOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let user_id = user_id.clone();
+                        let ticker = ticker.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::SeasonParticipant.money
+                                filter Discord::SeasonParticipant.season.number = <int64>$0
+                                    and Discord::SeasonParticipant.user_id = <str>$1
+                                    and Discord::SeasonParticipant.season.ends > datetime_of_statement()
+                                    and not Discord::SeasonParticipant.season.ended", &(season, user_id.clone())).await?;
+
+                            if money.is_none() {
+                                return Ok(SellOutcome::UserNotFound);
+                            }
+                            if is_frozen(&mut tx, &user_id).await? {
+                                return Ok(SellOutcome::AccountFrozen);
+                            }
+
+                            let lots: Vec<StockLot> = tx.query("
+                                with participant := (
+                                    select Discord::SeasonParticipant
+                                    filter .season.number = <int64>$0 and .user_id = <str>$1
+                                )
+                                select participant.lines { id, quantity, bought_at, fx_rate }
+                                filter .ticker = <str>$2
+                                order by .created_at asc then .id", &(season, user_id.clone(), ticker.clone())).await?;
+
+                            let owned: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                            let quantity: f64 = quantity.unwrap_or(owned);
+
+                            if owned <= QUANTITY_EPSILON || quantity > owned + QUANTITY_EPSILON {
+                                return Ok(SellOutcome::Oversold { owned });
+                            }
+
+                            let realized: Vec<RealizedLot> = consume_lots(&mut tx, &lots, quantity, price, fx_rate).await?;
+
+                            let proceeds: f64 = quantity * price * fx_rate;
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::SeasonParticipant
+                                    filter .season.number = <int64>$0 and .user_id = <str>$1
+                                    set { money := .money + <float64>$2 }
+                                ).money
+                                limit 1", &(season, user_id, proceeds)).await?;
+
+                            Ok(SellOutcome::Sold { lots: realized, proceeds, remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Final net worth and rank of a participant, in the account currency.
+        pub struct SeasonStanding {
+            pub user_id: String,
+            pub net_worth: f64,
+            pub rank: i64,
+        }
+
+        /// Archives the final standings and closes the season. Returns false when it was already closed.
+        pub async fn finish_season(season: i64, standings: Vec<SeasonStanding>) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            let standings: Vec<serde_json::Value> = standings
+                .iter()
+                .map(|standing| serde_json::json!({
+                    "user_id": standing.user_id,
+                    "net_worth": standing.net_worth,
+                    "rank": standing.rank,
+                }))
+                .collect();
+            let standings: Json = Json::new_unchecked(serde_json::Value::Array(standings).to_string());
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let standings = standings.clone();
+                        async move {
+                            let closed: Option<i64> = tx.query_single("
+                                select (
+                                    update Discord::Season
+                                    filter .number = <int64>$0 and not .ended
+                                    set { ended := true }
+                                ).number", &(season,)).await?;
+
+                            if closed.is_none() {
+                                return Ok(false);
+                            }
+
+                            tx.execute("
+                                for standing in json_array_unpack(<json>$1) union (
+                                    update Discord::SeasonParticipant
+                                    filter .season.number = <int64>$0 and .user_id = <str>standing['user_id']
+                                    set {
+                                        final_net_worth := <float64>standing['net_worth'],
+                                        final_rank := <int64>standing['rank']
+                                    }
+                                )", &(season, standings)).await?;
+
+                            Ok(true)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of a short sale.
+        pub enum ShortOutcome {
+            /// The proceeds were credited and the user has `remaining_money`.
+            Shorted { remaining_money: f64 },
+            /// The sale would break the initial margin, only `available` can still be shorted.
+            InsufficientMargin { available: f64 },
+            UserNotFound,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+        }
+
+        /// Sells `quantity` borrowed shares of `ticker` at `price` and credits the proceeds.
+        /// The guild the short is opened in becomes the one whose margin rules apply to the user, unless they already borrow.
+        ///
+        /// `available_exposure` tells how much the account may still short. It is checked in the same transaction as the sale.
+        #[allow(clippy::too_many_arguments)]
+        pub async fn open_short_position(unique_id: String, guild_id: String, ticker: String, quantity: f64, price: f64, currency: String, fx_rate: f64, available_exposure: impl Fn(&PortfolioUser) -> f64) -> anyhow::Result<ShortOutcome, edgedb_tokio::Error>
+        {
+            let proceeds: f64 = quantity * price * fx_rate;
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let guild_id = guild_id.clone();
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        let available_exposure = &available_exposure;
+                        async move {
+                            let user: PortfolioUser = match read_portfolio_user(&mut tx, &unique_id).await? {
+                                Some(user) => user,
+                                None => return Ok(ShortOutcome::UserNotFound),
+                            };
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(ShortOutcome::AccountFrozen);
+                            }
+                            let available: f64 = available_exposure(&user);
+                            if proceeds > available {
+                                return Ok(ShortOutcome::InsufficientMargin { available });
+                            }
+
+                            bind_margin_guild(&mut tx, &user, Some(guild_id)).await?;
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money + <float64>$1 }
+                                ).money
+                                limit 1", &(unique_id.clone(), proceeds)).await?;
+
+                            tx.execute("
+                                insert Discord::ShortPosition {
+                                    user_id := <str>$0,
+                                    ticker := <str>$1,
+                                    quantity := <float64>$2,
+                                    sold_at := <float64>$3,
+                                    currency := <str>$4,
+                                    fx_rate := <float64>$5
+                                }", &(unique_id.clone(), ticker.clone(), quantity, price, currency.clone(), fx_rate)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Short,
+                                ticker: Some(ticker),
+                                price: Some(price),
+                                currency: Some(currency),
+                                fx_rate: Some(fx_rate),
+                                quantity: Some(quantity),
+                                amount: proceeds,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: None,
+                            }).await?;
+
+                            Ok(ShortOutcome::Shorted { remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of a !daily claim.
+        pub enum DailyOutcome {
+            /// The allowance was credited, the user now has `money`.
+            Claimed { money: f64 },
+            /// The user does not exist or already claimed in the last `cooldown_hours` hours, which keeps a claim from going through twice.
+            NotClaimable,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+        }
+
+        /// Credits a !daily claim.
+        pub async fn claim_daily(unique_id: String, amount: f64, streak: i64, cooldown_hours: i64) -> anyhow::Result<DailyOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        async move {
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(DailyOutcome::AccountFrozen);
+                            }
+
+                            let money: Option<f64> = tx.query_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                        and (not exists .last_daily or .last_daily <= datetime_of_statement() - to_duration(hours := <int64>$3))
+                                    set {
+                                        money := .money + <float64>$1,
+                                        daily_streak := <int64>$2,
+                                        last_daily := datetime_of_statement()
+                                    }
+                                ).money
+                                limit 1", &(unique_id.clone(), amount, streak, cooldown_hours)).await?;
+
+                            let money: f64 = match money {
+                                Some(money) => money,
+                                None => return Ok(DailyOutcome::NotClaimable),
+                            };
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Daily,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount,
+                                fees: 0.0,
+                                balance_after: money,
+                                counterparty_id: None,
+                                note: Some(format!("Day {} of the streak", streak)),
+                            }).await?;
+
+                            Ok(DailyOutcome::Claimed { money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of a transfer between two users.
+        pub enum TransferOutcome {
+            Transferred { sender_money: f64, recipient_money: f64 },
+            /// The sender only has `money`, transfers cannot be paid with borrowed money.
+            InsufficientFunds { money: f64 },
+            SenderNotFound,
+            RecipientNotFound,
+            /// The sender or the recipient account is frozen.
+            AccountFrozen,
+        }
+
+        /// Moves `amount` from the sender to the recipient, both sides recorded in the ledger.
+        pub async fn transfer_money(sender_id: String, recipient_id: String, amount: f64, note: Option<String>) -> anyhow::Result<TransferOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let sender_id = sender_id.clone();
+                        let recipient_id = recipient_id.clone();
+                        let note = note.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0
+                                limit 1", &(sender_id.clone(),)).await?;
+                            let money: f64 = match money {
+                                Some(money) => money,
+                                None => return Ok(TransferOutcome::SenderNotFound),
+                            };
+                            let recipient_exists: bool = tx.query_required_single("
+                                select exists (select Discord::User filter .unique_id = <str>$0)", &(recipient_id.clone(),)).await?;
+                            if !recipient_exists {
+                                return Ok(TransferOutcome::RecipientNotFound);
+                            }
+                            let frozen: bool = tx.query_required_single("
+                                select exists (
+                                    select Discord::User
+                                    filter .unique_id in {<str>$0, <str>$1} and .frozen
+                                )", &(sender_id.clone(), recipient_id.clone())).await?;
+                            if frozen {
+                                return Ok(TransferOutcome::AccountFrozen);
+                            }
+                            if money < amount {
+                                return Ok(TransferOutcome::InsufficientFunds { money });
+                            }
+
+                            let sender_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money - <float64>$1 }
+                                ).money
+                                limit 1", &(sender_id.clone(), amount)).await?;
+                            let recipient_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money + <float64>$1 }
+                                ).money
+                                limit 1", &(recipient_id.clone(), amount)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: sender_id.clone(),
+                                kind: LedgerKind::Transfer,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount: -amount,
+                                fees: 0.0,
+                                balance_after: sender_money,
+                                counterparty_id: Some(recipient_id.clone()),
+                                note: note.clone(),
+                            }).await?;
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: recipient_id,
+                                kind: LedgerKind::Transfer,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount,
+                                fees: 0.0,
+                                balance_after: recipient_money,
+                                counterparty_id: Some(sender_id),
+                                note,
+                            }).await?;
+
+                            Ok(TransferOutcome::Transferred { sender_money, recipient_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Changes the money of a user on behalf of an owner: adds `amount` (Grant), removes it (Revoke) or replaces
+        /// the money with it (Set). The difference goes to the ledger as a grant and the action to the audit log.
+        /// Returns the money before and after, or None when the user does not exist.
+        pub async fn admin_adjust_money(owner_id: String, unique_id: String, action: EconomyAction, amount: f64, reason: String) -> anyhow::Result<Option<(f64, f64)>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let owner_id = owner_id.clone();
+                        let unique_id = unique_id.clone();
+                        let reason = reason.clone();
+                        async move {
+                            let before: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0
+                                limit 1", &(unique_id.clone(),)).await?;
+                            let before: f64 = match before {
+                                Some(before) => before,
+                                None => return Ok(None),
+                            };
+                            let after: f64 = match action {
+                                EconomyAction::Grant => before + amount,
+                                EconomyAction::Revoke => before - amount,
+                                _ => amount,
+                            };
+
+                            tx.execute("
+                                update Discord::User
+                                filter .unique_id = <str>$0
+                                set { money := <float64>$1 }", &(unique_id.clone(), after)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id.clone(),
+                                kind: LedgerKind::Grant,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount: after - before,
+                                fees: 0.0,
+                                balance_after: after,
+                                counterparty_id: Some(owner_id.clone()),
+                                note: Some(format!("{}: {}", action, reason)),
+                            }).await?;
+                            record_audit(&mut tx, AuditEntry {
+                                owner_id,
+                                user_id: Some(unique_id),
+                                action,
+                                amount: Some(amount),
+                                balance_before: Some(before),
+                                balance_after: Some(after),
+                                reason,
+                            }).await?;
+
+                            Ok(Some((before, after)))
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Freezes or unfreezes an account on behalf of an owner, recorded in the audit log. Returns false when the user does not exist.
+        pub async fn set_account_frozen(owner_id: String, unique_id: String, frozen: bool, reason: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let owner_id = owner_id.clone();
+                        let unique_id = unique_id.clone();
+                        let reason = reason.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set {
+                                        frozen := <bool>$1,
+                                        frozen_reason := <optional str>$2
+                                    }
+                                ).money
+                                limit 1", &(unique_id.clone(), frozen, Some(reason.clone()).filter(|_| frozen))).await?;
+                            if money.is_none() {
+                                return Ok(false);
+                            }
+
+                            record_audit(&mut tx, AuditEntry {
+                                owner_id,
+                                user_id: Some(unique_id),
+                                action: if frozen { EconomyAction::Freeze } else { EconomyAction::Unfreeze },
+                                amount: None,
+                                balance_before: money,
+                                balance_after: money,
+                                reason,
+                            }).await?;
+
+                            Ok(true)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of a shop purchase.
+        pub enum ShopPurchaseOutcome {
+            Bought { item: ShopItem, remaining_money: f64 },
+            /// No enabled item of that name in the guild shop.
+            ItemNotFound,
+            SoldOut,
+            /// Roles and colours are only bought once.
+            AlreadyOwned,
+            InsufficientFunds { money: f64 },
+            UserNotFound,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+        }
+
+        /// Buys an item of a guild shop: debits the price, takes one from the stock and adds the item to the inventory.
+        /// Buying a temporary role again extends it, buying a consumable again adds one more.
+        pub async fn buy_shop_item(guild_id: String, user_id: String, name: String) -> anyhow::Result<ShopPurchaseOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let guild_id = guild_id.clone();
+                        let user_id = user_id.clone();
+                        let name = name.clone();
+                        async move {
+                            let item: Option<ShopItem> = tx.query_single("
+                                select Discord::ShopItem {
+                                    name,
+                                    kind,
+                                    price,
+                                    description,
+                                    role_id,
+                                    duration_hours,
+                                    colour,
+                                    stock,
+                                    enabled
+                                }
+                                filter .guild_id = <str>$0 and .name = <str>$1 and .enabled
+                                limit 1", &(guild_id.clone(), name.clone())).await?;
+                            let item: ShopItem = match item {
+                                Some(item) => item,
+                                None => return Ok(ShopPurchaseOutcome::ItemNotFound),
+                            };
+                            if matches!(item.stock, Some(stock) if stock <= 0) {
+                                return Ok(ShopPurchaseOutcome::SoldOut);
+                            }
+
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0
+                                limit 1", &(user_id.clone(),)).await?;
+                            let money: f64 = match money {
+                                Some(money) => money,
+                                None => return Ok(ShopPurchaseOutcome::UserNotFound),
+                            };
+                            if is_frozen(&mut tx, &user_id).await? {
+                                return Ok(ShopPurchaseOutcome::AccountFrozen);
+                            }
+                            let owned: bool = tx.query_required_single("
+                                select exists (
+                                    select Discord::InventoryItem
+                                    filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2
+                                )", &(user_id.clone(), guild_id.clone(), item.name.clone())).await?;
+                            if owned && (item.kind == "role" || item.kind == "colour") {
+                                return Ok(ShopPurchaseOutcome::AlreadyOwned);
+                            }
+                            if money < item.price {
+                                return Ok(ShopPurchaseOutcome::InsufficientFunds { money });
+                            }
+
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money - <float64>$1 }
+                                ).money
+                                limit 1", &(user_id.clone(), item.price)).await?;
+                            tx.execute("
+                                update Discord::ShopItem
+                                filter .guild_id = <str>$0 and .name = <str>$1 and exists .stock
+                                set { stock := .stock - 1 }", &(guild_id.clone(), item.name.clone())).await?;
+                            tx.execute("
+                                insert Discord::InventoryItem {
+                                    user_id := <str>$0,
+                                    guild_id := <str>$1,
+                                    item_name := <str>$2,
+                                    kind := <str>$3,
+                                    role_id := <optional str>$4,
+                                    expires := datetime_of_statement() + to_duration(hours := <optional int64>$5)
+                                }
+                                unless conflict on ((.user_id, .guild_id, .item_name))
+                                else (
+                                    update Discord::InventoryItem
+                                    set {
+                                        quantity := .quantity + 1,
+                                        expires := max({.expires, datetime_of_statement()}) + to_duration(hours := <optional int64>$5)
+                                    }
+                                )", &(
+                                    user_id.clone(),
+                                    guild_id.clone(),
+                                    item.name.clone(),
+                                    item.kind.clone(),
+                                    item.role_id.clone(),
+                                    item.duration_hours,
+                                )).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id,
+                                kind: LedgerKind::Purchase,
+                                ticker: None,
+                                price: Some(item.price),
+                                currency: None,
+                                fx_rate: None,
+                                quantity: Some(1.0),
+                                amount: -item.price,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: Some(format!("Shop item {} of guild {}", item.name, guild_id)),
+                            }).await?;
+
+                            Ok(ShopPurchaseOutcome::Bought { item, remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Undoes a purchase whose role could not be given: the price is credited back, the stock and the inventory restored.
+        pub async fn refund_shop_purchase(guild_id: String, user_id: String, item: ShopItem) -> anyhow::Result<f64, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let guild_id = guild_id.clone();
+                        let user_id = user_id.clone();
+                        let item = item.clone();
+                        async move {
+                            let money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money + <float64>$1 }
+                                ).money
+                                limit 1", &(user_id.clone(), item.price)).await?;
+                            tx.execute("
+                                update Discord::ShopItem
+                                filter .guild_id = <str>$0 and .name = <str>$1 and exists .stock
+                                set { stock := .stock + 1 }", &(guild_id.clone(), item.name.clone())).await?;
+                            // A consumable bought several times loses one, anything else leaves the inventory
+                            tx.execute("
+                                delete Discord::InventoryItem
+                                filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2 and .quantity <= 1", &(user_id.clone(), guild_id.clone(), item.name.clone())).await?;
+                            tx.execute("
+                                update Discord::InventoryItem
+                                filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2 and .quantity > 1
+                                set { quantity := .quantity - 1 }", &(user_id.clone(), guild_id.clone(), item.name.clone())).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id,
+                                kind: LedgerKind::Purchase,
+                                ticker: None,
+                                price: Some(item.price),
+                                currency: None,
+                                fx_rate: None,
+                                quantity: Some(-1.0),
+                                amount: item.price,
+                                fees: 0.0,
+                                balance_after: money,
+                                counterparty_id: None,
+                                note: Some(format!("Refund of shop item {} of guild {}", item.name, guild_id)),
+                            }).await?;
+
+                            Ok(money)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Uses one consumable of the inventory and returns how many are left, None when the user has none.
+        pub async fn use_consumable(user_id: String, guild_id: String, name: String) -> anyhow::Result<Option<i64>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let user_id = user_id.clone();
+                        let guild_id = guild_id.clone();
+                        let name = name.clone();
+                        async move {
+                            let left: Option<i64> = tx.query_single("
+                                select (
+                                    update Discord::InventoryItem
+                                    filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2
+                                        and .kind = 'consumable' and .quantity > 0
+                                    set { quantity := .quantity - 1 }
+                                ).quantity
+                                limit 1", &(user_id.clone(), guild_id.clone(), name.clone())).await?;
+                            if left == Some(0) {
+                                tx.execute("
+                                    delete Discord::InventoryItem
+                                    filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2", &(user_id, guild_id, name)).await?;
+                            }
+
+                            Ok(left)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// A change to a shop item, see !shop_item edit.
+        pub enum ShopItemEdit {
+            Price(f64),
+            Description(String),
+            /// None for an unlimited stock
+            Stock(Option<i64>),
+            Enabled(bool),
+        }
+
+        /// Returns false when the guild shop has no such item.
+        pub async fn edit_shop_item(guild_id: String, name: String, edit: ShopItemEdit) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let filter: &str = "filter .guild_id = <str>$0 and .name = <str>$1";
+                    let updated: Vec<Uuid> = match edit {
+                        ShopItemEdit::Price(price) => conn.query(&format!("
+                            select (update Discord::ShopItem {} set {{ price := <float64>$2 }}).id", filter), &(guild_id, name, price)).await?,
+                        ShopItemEdit::Description(description) => conn.query(&format!("
+                            select (update Discord::ShopItem {} set {{ description := <str>$2 }}).id", filter), &(guild_id, name, description)).await?,
+                        ShopItemEdit::Stock(stock) => conn.query(&format!("
+                            select (update Discord::ShopItem {} set {{ stock := <optional int64>$2 }}).id", filter), &(guild_id, name, stock)).await?,
+                        ShopItemEdit::Enabled(enabled) => conn.query(&format!("
+                            select (update Discord::ShopItem {} set {{ enabled := <bool>$2 }}).id", filter), &(guild_id, name, enabled)).await?,
+                    };
+
+                    Ok(!updated.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of buying back shorted shares.
+        pub enum CoverOutcome {
+            /// `cost` was debited, `profit` is the realized result in the account currency.
+            Covered { quantity: f64, cost: f64, profit: f64, remaining_money: f64 },
+            /// The user tried to cover more than the `owed` shares.
+            Overcovered { owed: f64 },
+            UserNotFound,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+        }
+
+        /// Buys back `quantity` shorted shares of `ticker` at `price` (or every owed share when `quantity` is `None`).
+        ///
+        /// The short lots are consumed first-in-first-out. Covering always goes through, even if the money ends up negative,
+        /// since it only lowers the risk of the account.
+        pub async fn cover_short_position(unique_id: String, ticker: String, quantity: Option<f64>, price: f64, currency: String, fx_rate: f64) -> anyhow::Result<CoverOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0", &(unique_id.clone(),)).await?;
+
+                            if money.is_none() {
+                                return Ok(CoverOutcome::UserNotFound);
+                            }
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(CoverOutcome::AccountFrozen);
+                            }
+
+                            let lots: Vec<ShortLot> = tx.query("
+                                select Discord::ShortPosition { id, ticker, quantity, sold_at, currency, fx_rate }
+                                filter .user_id = <str>$0 and .ticker = <str>$1
+                                order by .created asc then .id", &(unique_id.clone(), ticker.clone())).await?;
+
+                            let owed: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                            let quantity: f64 = quantity.unwrap_or(owed);
+
+                            if owed <= QUANTITY_EPSILON || quantity > owed + QUANTITY_EPSILON {
+                                return Ok(CoverOutcome::Overcovered { owed });
+                            }
+
+                            let mut left_to_cover: f64 = quantity;
+                            let mut profit: f64 = 0.0;
+
+                            for lot in lots.iter() {
+                                if left_to_cover <= QUANTITY_EPSILON {
+                                    break;
+                                }
+
+                                let covered: f64 = left_to_cover.min(lot.quantity);
+                                let lot_left: f64 = lot.quantity - covered;
+
+                                if lot_left <= QUANTITY_EPSILON {
+                                    tx.execute("
+                                        delete Discord::ShortPosition
+                                        filter .id = <uuid>$0", &(lot.id,)).await?;
+                                } else {
+                                    tx.execute("
+                                        update Discord::ShortPosition
+                                        filter .id = <uuid>$0
+                                        set { quantity := <float64>$1 }", &(lot.id, lot_left)).await?;
+                                }
+
+                                profit += (lot.sold_at * lot.fx_rate - price * fx_rate) * covered;
+                                left_to_cover -= covered;
+                            }
+
+                            let cost: f64 = quantity * price * fx_rate;
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money - <float64>$1 }
+                                ).money
+                                limit 1", &(unique_id.clone(), cost)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Cover,
+                                ticker: Some(ticker),
+                                price: Some(price),
+                                currency: Some(currency),
+                                fx_rate: Some(fx_rate),
+                                quantity: Some(quantity),
+                                amount: -cost,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: Some(format!("Realized profit/loss: {:+.2}", profit)),
+                            }).await?;
+
+                            Ok(CoverOutcome::Covered { quantity, cost, profit, remaining_money })
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Debits the interest on borrowed money and shares and returns the remaining money.
+        pub async fn charge_margin_interest(unique_id: String, interest: f64, note: String) -> anyhow::Result<f64, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let note = note.clone();
+                        async move {
+                            let remaining_money: f64 = tx.query_required_single("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set { money := .money - <float64>$1 }
+                                ).money
+                                limit 1", &(unique_id.clone(), interest)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Interest,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount: -interest,
+                                fees: 0.0,
+                                balance_after: remaining_money,
+                                counterparty_id: None,
+                                note: Some(note),
+                            }).await?;
+
+                            Ok(remaining_money)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Records a margin call warning or liquidation in the ledger, without moving money.
+        pub async fn record_margin_call(unique_id: String, note: String) -> anyhow::Result<(), edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let note = note.clone();
+                        async move {
+                            let money: f64 = tx.query_required_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0
+                                limit 1", &(unique_id.clone(),)).await?;
+
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::MarginCall,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount: 0.0,
+                                fees: 0.0,
+                                balance_after: money,
+                                counterparty_id: None,
+                                note: Some(note),
+                            }).await
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// How a dividend or a split affected one holder. `credited` is in the account currency, negative when a
+        /// short seller pays the dividend back.
+        pub struct CorporateActionResult {
+            pub user_id: String,
+            /// The shares were sold short
+            pub short: bool,
+            pub quantity_before: f64,
+            pub quantity_after: f64,
+            pub credited: f64,
+        }
+
+        /// Users holding shares of `ticker` bought before `ex_date` (epoch seconds), as part of the caller's transaction.
+        async fn get_holders_at(tx: &mut edgedb_tokio::Transaction, ticker: String, ex_date: f64) -> anyhow::Result<Vec<Holding>, edgedb_tokio::Error> {
+            tx.query("
+                with holders := (
+                    select Discord::User {
+                        unique_id,
+                        money,
+                        held := sum((
+                            select .portfolio.lines
+                            filter .ticker = <str>$0
+                                and cal::to_datetime(.created_at, 'Europe/Brussels') < to_datetime(<float64>$1)
+                        ).quantity)
+                    }
+                )
+                select holders { unique_id, money, held }
+                filter .held > 0", &(ticker, ex_date)).await
+        }
+
+        /// Users short on `ticker` with shares sold before `ex_date` (epoch seconds), as part of the caller's transaction.
+        async fn get_short_sellers_at(tx: &mut edgedb_tokio::Transaction, ticker: String, ex_date: f64) -> anyhow::Result<Vec<Holding>, edgedb_tokio::Error> {
+            tx.query("
+                with sellers := (
+                    select Discord::User {
+                        unique_id,
+                        money,
+                        held := sum((
+                            select Discord::ShortPosition
+                            filter .user_id = Discord::User.unique_id
+                                and .ticker = <str>$0
+                                and .created < to_datetime(<float64>$1)
+                        ).quantity)
+                    }
+                )
+                select sellers { unique_id, money, held }
+                filter .held > 0", &(ticker, ex_date)).await
+        }
+
+        /// Credits a dividend of `amount` per share (quote `currency`, converted with `fx_rate`) to everyone holding `ticker` on its ex-date.
+        ///
+        /// Returns `None` when this dividend was already applied.
+        pub async fn apply_dividend(ticker: String, ex_date: f64, amount: f64, currency: String, fx_rate: f64) -> anyhow::Result<Option<Vec<CorporateActionResult>>, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let ticker = ticker.clone();
+                        let currency = currency.clone();
+                        async move {
+                            let recorded: Option<Uuid> = tx.query_single("
+                                select (
+                                    insert Discord::CorporateAction {
+                                        ticker := <str>$0,
+                                        kind := 'dividend',
+                                        ex_date := to_datetime(<float64>$1),
+                                        value := <float64>$2
+                                    } unless conflict
+                                ).id", &(ticker.clone(), ex_date, amount)).await?;
+
+                            if recorded.is_none() {
+                                return Ok(None);
+                            }
+
+                            // Short sellers owe the dividend to the lender of the shares
+                            let mut holdings: Vec<(Holding, bool)> = vec![];
+                            for holder in get_holders_at(&mut tx, ticker.clone(), ex_date).await? {
+                                holdings.push((holder, false));
+                            }
+                            for seller in get_short_sellers_at(&mut tx, ticker.clone(), ex_date).await? {
+                                holdings.push((seller, true));
+                            }
+
+                            let mut results: Vec<CorporateActionResult> = vec![];
+                            for (holder, short) in holdings {
+                                let credited: f64 = if short { -holder.held * amount * fx_rate } else { holder.held * amount * fx_rate };
+                                let remaining_money: f64 = tx.query_required_single("
+                                    select (
+                                        update Discord::User
+                                        filter .unique_id = <str>$0
+                                        set { money := .money + <float64>$1 }
+                                    ).money
+                                    limit 1", &(holder.unique_id.clone(), credited)).await?;
+
+                                record_transaction(&mut tx, LedgerEntry {
+                                    user_id: holder.unique_id.clone(),
+                                    kind: LedgerKind::Dividend,
+                                    ticker: Some(ticker.clone()),
+                                    price: Some(amount),
+                                    currency: Some(currency.clone()),
+                                    fx_rate: Some(fx_rate),
+                                    quantity: Some(holder.held),
+                                    amount: credited,
+                                    fees: 0.0,
+                                    balance_after: remaining_money,
+                                    counterparty_id: None,
+                                    note: if short { Some(String::from("Dividend owed on a short position")) } else { None },
+                                }).await?;
+
+                                results.push(CorporateActionResult {
+                                    user_id: holder.unique_id,
+                                    short,
+                                    quantity_before: holder.held,
+                                    quantity_after: holder.held,
+                                    credited,
+                                });
+                            }
+
+                            Ok(Some(results))
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Applies a split of `numerator` for `denominator` to the lots of `ticker` bought or sold short before its ex-date,
+        /// along with the pending orders on it. The purchase and sale prices are divided accordingly, so the cost basis does not move.
+        ///
+        /// Returns `None` when this split was already applied.
+        pub async fn apply_split(ticker: String, ex_date: f64, numerator: f64, denominator: f64) -> anyhow::Result<Option<Vec<CorporateActionResult>>, edgedb_tokio::Error>
+        {
+            let ratio: f64 = numerator / denominator;
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let ticker = ticker.clone();
+                        async move {
+                            let recorded: Option<Uuid> = tx.query_single("
+                                select (
+                                    insert Discord::CorporateAction {
+                                        ticker := <str>$0,
+                                        kind := 'split',
+                                        ex_date := to_datetime(<float64>$1),
+                                        value := <float64>$2
+                                    } unless conflict
+                                ).id", &(ticker.clone(), ex_date, ratio)).await?;
+
+                            if recorded.is_none() {
+                                return Ok(None);
+                            }
+
+                            let holders: Vec<Holding> = get_holders_at(&mut tx, ticker.clone(), ex_date).await?;
+                            let sellers: Vec<Holding> = get_short_sellers_at(&mut tx, ticker.clone(), ex_date).await?;
+
+                            tx.execute("
+                                update Discord::PortfolioLine
+                                filter .ticker = <str>$0
+                                    and cal::to_datetime(.created_at, 'Europe/Brussels') < to_datetime(<float64>$1)
+                                set {
+                                    quantity := .quantity * <float64>$2,
+                                    bought_at := .bought_at / <float64>$2
+                                }", &(ticker.clone(), ex_date, ratio)).await?;
+
+                            tx.execute("
+                                update Discord::ShortPosition
+                                filter .ticker = <str>$0 and .created < to_datetime(<float64>$1)
+                                set {
+                                    quantity := .quantity * <float64>$2,
+                                    sold_at := .sold_at / <float64>$2
+                                }", &(ticker.clone(), ex_date, ratio)).await?;
+
+                            tx.execute("
+                                update Discord::PendingOrder
+                                filter .ticker = <str>$0 and .status = 'pending' and .created < to_datetime(<float64>$2)
+                                set {
+                                    quantity := .quantity * <float64>$1,
+                                    trigger_price := .trigger_price / <float64>$1,
+                                    updated := datetime_of_statement()
+                                }", &(ticker.clone(), ratio, ex_date)).await?;
+
+                            let mut results: Vec<CorporateActionResult> = vec![];
+                            let holdings = holders.into_iter().map(|holder| (holder, false)).chain(sellers.into_iter().map(|seller| (seller, true)));
+                            for (holder, short) in holdings {
+                                let quantity_after: f64 = holder.held * ratio;
+
+                                record_transaction(&mut tx, LedgerEntry {
+                                    user_id: holder.unique_id.clone(),
+                                    kind: LedgerKind::Split,
+                                    ticker: Some(ticker.clone()),
+                                    price: None,
+                                    currency: None,
+                                    fx_rate: None,
+                                    quantity: Some(quantity_after - holder.held),
+                                    amount: 0.0,
+                                    fees: 0.0,
+                                    balance_after: holder.money,
+                                    counterparty_id: None,
+                                    note: Some(format!(
+                                        "{}:{} split, {} {}shares became {}",
+                                        numerator, denominator, holder.held, if short { "shorted " } else { "" }, quantity_after
+                                    )),
+                                }).await?;
+
+                                results.push(CorporateActionResult {
+                                    user_id: holder.unique_id,
+                                    short,
+                                    quantity_before: holder.held,
+                                    quantity_after,
+                                    credited: 0.0,
+                                });
+                            }
+
+                            Ok(Some(results))
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Changes the timezone the user reads dates in. Returns false if the user is not registered.
+        pub async fn set_user_timezone(unique_id: String, timezone: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let updated: Vec<Uuid> = conn.query("
+                        select (
+                            update Discord::User
+                            filter .unique_id = <str>$0
+                            set { timezone := <str>$1 }
+                        ).id", &(unique_id, timezone)).await?;
+
+                    Ok(!updated.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Keeps the Discord names of a user up to date. Returns false if the user is not registered.
+        pub async fn sync_discord_names(unique_id: String, username: String, display_name: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let username = username.clone();
+                        let display_name = display_name.clone();
+                        async move {
+                            free_username(&mut tx, &unique_id, &username).await?;
+
+                            let updated: Vec<Uuid> = tx.query("
+                                select (
+                                    update Discord::User
+                                    filter .unique_id = <str>$0
+                                    set {
+                                        username := <str>$1,
+                                        display_name := <str>$2
+                                    }
+                                ).id", &(unique_id, username, display_name)).await?;
+
+                            Ok(!updated.is_empty())
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Cancels a pending order of the user. Returns false if there is no such pending order.
+        pub async fn cancel_pending_order(user_id: String, number: i64) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let cancelled: Vec<Uuid> = conn.query("
+                        select (
+                            update Discord::PendingOrder
+                            filter .user_id = <str>$0 and .number = <int64>$1 and .status = 'pending'
+                            set { status := 'cancelled', updated := datetime_of_statement() }
+                        ).id", &(user_id, number)).await?;
+                    Ok(!cancelled.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Records why a pending order could not be filled. An order cancelled meanwhile stays cancelled.
+        pub async fn fail_order(id: Uuid, note: String) -> anyhow::Result<(), edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.execute("
+                        update Discord::PendingOrder
+                        filter .id = <uuid>$0 and .status = 'pending'
+                        set { status := 'failed', fill_price := {}, note := <str>$1, updated := datetime_of_statement() }", &(id, note)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Arms or disarms a re-arming price alert. Disarming means it just triggered.
+        pub async fn set_price_alert_armed(id: Uuid, armed: bool) -> anyhow::Result<(), edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.execute("
+                        update Discord::PriceAlert
+                        filter .id = <uuid>$0
+                        set {
+                            armed := <bool>$1,
+                            last_triggered := (datetime_of_statement() if not <bool>$1 else .last_triggered)
+                        }", &(id, armed)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    pub mod delete {
+        use super::*;
+
+        /// Deletes a price alert of the user by number. Returns false if the user has no such alert.
+        pub async fn delete_price_alert(user_id: String, number: i64) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let deleted: Vec<Uuid> = conn.query("
+                        select (
+                            delete Discord::PriceAlert
+                            filter .user_id = <str>$0 and .number = <int64>$1
+                        ).id", &(user_id, number)).await?;
+                    Ok(!deleted.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Deletes a one-shot price alert once it triggered.
+        pub async fn delete_price_alert_by_id(id: Uuid) -> anyhow::Result<(), edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.execute("
+                        delete Discord::PriceAlert
+                        filter .id = <uuid>$0", &(id,)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Removes an item from a guild shop. What users already bought stays in their inventory,
+        /// except colours: their role is deleted along with the item.
+        pub async fn delete_shop_item(guild_id: String, name: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let deleted: Vec<Uuid> = conn.query("
+                        with
+                            item := (
+                                delete Discord::ShopItem
+                                filter .guild_id = <str>$0 and .name = <str>$1
+                            ),
+                            colours := (
+                                delete Discord::InventoryItem
+                                filter .guild_id = <str>$0 and .item_name = item.name and .kind = 'colour'
+                            )
+                        select item.id", &(guild_id, name)).await?;
+                    Ok(!deleted.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Takes an item out of an inventory, once the role of a temporary item expired.
+        pub async fn delete_inventory_item(user_id: String, guild_id: String, item_name: String) -> anyhow::Result<(), edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.execute("
+                        delete Discord::InventoryItem
+                        filter .user_id = <str>$0 and .guild_id = <str>$1 and .item_name = <str>$2", &(user_id, guild_id, item_name)).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Outcome of an account deletion.
+        pub enum DeleteAccountOutcome {
+            Deleted,
+            UserNotFound,
+            /// The money is negative or shares are still shorted, a new account would wipe that debt out.
+            InDebt,
+            /// The account was frozen by the owners.
+            AccountFrozen,
+        }
+
+        /// Deletes an account along with its portfolio, orders, alerts, watchlist, inventory and snapshots.
+        /// The ledger, the audit log and season accounts are kept. Accounts in debt or frozen are refused.
+        pub async fn delete_discord_user(unique_id: String) -> anyhow::Result<DeleteAccountOutcome, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        async move {
+                            let money: Option<f64> = tx.query_single("
+                                select Discord::User.money
+                                filter Discord::User.unique_id = <str>$0", &(unique_id.clone(),)).await?;
+                            let shorted: bool = tx.query_required_single("
+                                select exists (select Discord::ShortPosition filter .user_id = <str>$0)", &(unique_id.clone(),)).await?;
+                            match money {
+                                None => return Ok(DeleteAccountOutcome::UserNotFound),
+                                Some(money) if money < 0.0 || shorted => return Ok(DeleteAccountOutcome::InDebt),
+                                Some(_) => {}
+                            }
+                            // The owners may have frozen the account while the user was confirming
+                            if is_frozen(&mut tx, &unique_id).await? {
+                                return Ok(DeleteAccountOutcome::AccountFrozen);
+                            }
+
+                            let portfolio_ids: Json = tx.query_required_single("
+                                select <json>array_agg((select Discord::User filter .unique_id = <str>$0).portfolio.id)", &(unique_id.clone(),)).await?;
+                            let line_ids: Json = tx.query_required_single("
+                                select <json>array_agg((select Discord::User filter .unique_id = <str>$0).portfolio.lines.id)", &(unique_id.clone(),)).await?;
+
+                            let deleted: Vec<Uuid> = tx.query("
+                                select (
+                                    delete Discord::User
+                                    filter .unique_id = <str>$0
+                                ).id", &(unique_id.clone(),)).await?;
+                            if deleted.is_empty() {
+                                return Ok(DeleteAccountOutcome::UserNotFound);
+                            }
+
+                            // The portfolio goes before its lines, which it links to
+                            tx.execute("
+                                delete Discord::Portfolio
+                                filter .id in <uuid>json_array_unpack(<json>$0)", &(portfolio_ids,)).await?;
+                            tx.execute("
+                                delete Discord::PortfolioLine
+                                filter .id in <uuid>json_array_unpack(<json>$0)", &(line_ids,)).await?;
+
+                            tx.execute("delete Discord::PendingOrder filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::PriceAlert filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::WatchlistEntry filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::EarningsNotice filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::InventoryItem filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::NetWorthSnapshot filter .user_id = <str>$0", &(unique_id.clone(),)).await?;
+                            tx.execute("delete Discord::PositionSnapshot filter .user_id = <str>$0", &(unique_id,)).await?;
+
+                            Ok(DeleteAccountOutcome::Deleted)
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+
+        /// Removes a ticker from a user's watchlist. Returns false if it was not watched.
+        pub async fn delete_watchlist_entry(user_id: String, ticker: String) -> anyhow::Result<bool, edgedb_tokio::Error>
+        {
+            match get_conn().await {
+                Ok(conn) => {
+                    let deleted: Vec<Uuid> = conn.query("
+                        select (
+                            delete Discord::WatchlistEntry
+                            filter .user_id = <str>$0 and .ticker = <str>$1
+                        ).id", &(user_id, ticker)).await?;
+                    Ok(!deleted.is_empty())
+                },
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    pub mod create {
+        use super::*;
+
+        /// Registers a user unless they already are. Returns the money of the account if it was just created.
+        ///
+        /// Only a first account gets the starting money. An account opened again after `delete_discord_user`
+        /// starts at 0, or deleting and re-opening accounts would create money.
+        pub async fn ensure_discord_user(unique_id: String, username: String, display_name: String) -> anyhow::Result<Option<f64>, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let unique_id = unique_id.clone();
+                        let username = username.clone();
+                        let display_name = display_name.clone();
+                        async move {
+                            free_username(&mut tx, &unique_id, &username).await?;
+
+                            // The ledger of a deleted account is kept, it tells whether the user had one before
+                            let reopened: bool = tx.query_required_single("
+                                select exists (
+                                    select Discord::Transaction
+                                    filter .user_id = <str>$0
+                                )", &(unique_id.clone(),)).await?;
+
+                            let money: Option<f64> = tx.query_single("
+                                select (
+                                    insert Discord::User {
+                                        unique_id := <str>$0,
+                                        username := <str>$1,
+                                        display_name := <str>$2
+                                    }
+                                    unless conflict on .unique_id
+                                ).money", &(unique_id.clone(), username, display_name)).await?;
+                            let mut money: f64 = match money {
+                                Some(money) => money,
+                                None => return Ok(None),
+                            };
+                            if reopened {
+                                money = tx.query_required_single("
+                                    select (
+                                        update Discord::User
+                                        filter .unique_id = <str>$0
+                                        set { money := 0.0 }
+                                    ).money
+                                    limit 1", &(unique_id.clone(),)).await?;
+                            }
+
+                            // The reconciliation starts over from here
+                            record_transaction(&mut tx, LedgerEntry {
+                                user_id: unique_id,
+                                kind: LedgerKind::Opening,
+                                ticker: None,
+                                price: None,
+                                currency: None,
+                                fx_rate: None,
+                                quantity: None,
+                                amount: money,
+                                fees: 0.0,
+                                balance_after: money,
+                                counterparty_id: None,
+                                note: Some(String::from("Opening balance")),
+                            }).await?;
+
+                            Ok(Some(money))
+                        }
+                    }).await
+                },
+                Err(error) => Err(error),
+            }
+        }
+    
+        pub async fn create_error_log(log: String, level: String, channel_name: String) -> anyhow::Result<(), edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    let result = conn.execute("insert Dev::ErrorLog {
+                        log := <str>$0,
+                        level := <str>$1,
+                        channel_name := <str>$2
+                    }", &(log, level, channel_name)).await;
+                    if result.is_err() {
+                        return Err(result.unwrap_err());
+                    }
+                    Ok(())
+                }
+                Err(err) => Err(err)
+            }
+        }
+        
+        /// Value of one position at snapshot time, in the account currency.
+        pub struct SnapshotPosition {
+            pub ticker: String,
+            pub quantity: f64,
+            pub market_value: f64,
+            pub cost_basis: f64,
+        }
+
+        /// Stores the end of day valuation of a user and of each of their positions.
+        /// Taking the snapshot of a day again replaces it.
+        pub async fn create_net_worth_snapshot(user_id: String, day: String, cash: f64, net_worth: f64, positions: Vec<SnapshotPosition>) -> anyhow::Result<(), edgedb_tokio::Error> {
+            let positions: Vec<serde_json::Value> = positions
+                .iter()
+                .map(|position| serde_json::json!({
+                    "ticker": position.ticker,
+                    "quantity": position.quantity,
+                    "market_value": position.market_value,
+                    "cost_basis": position.cost_basis,
+                }))
+                .collect();
+            let positions: Json = Json::new_unchecked(serde_json::Value::Array(positions).to_string());
+
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let user_id = user_id.clone();
+                        let day = day.clone();
+                        let positions = positions.clone();
+                        async move {
+                            tx.execute("insert Discord::NetWorthSnapshot {
+                                user_id := <str>$0,
+                                day := <str>$1,
+                                cash := <float64>$2,
+                                net_worth := <float64>$3
+                            }
+                            unless conflict on ((.user_id, .day))
+                            else (
+                                update Discord::NetWorthSnapshot
+                                set { cash := <float64>$2, net_worth := <float64>$3, created := datetime_of_statement() }
+                            )", &(user_id.clone(), day.clone(), cash, net_worth)).await?;
+
+                            tx.execute("
+                                delete Discord::PositionSnapshot
+                                filter .user_id = <str>$0 and .day = <str>$1", &(user_id.clone(), day.clone())).await?;
+
+                            tx.execute("
+                                for position in json_array_unpack(<json>$2) union (
+                                    insert Discord::PositionSnapshot {
+                                        user_id := <str>$0,
+                                        day := <str>$1,
+                                        ticker := <str>position['ticker'],
+                                        quantity := <float64>position['quantity'],
+                                        market_value := <float64>position['market_value'],
+                                        cost_basis := <float64>position['cost_basis']
+                                    }
+                                )", &(user_id, day, positions)).await
+                        }
+                    }).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Stores the short selling and margin rules of a guild, replacing the previous ones.
+        pub async fn save_guild_trading_settings(settings: GuildTradingSettings) -> anyhow::Result<(), edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.execute("insert Discord::GuildTradingSettings {
+                        guild_id := <str>$0,
+                        short_selling := <bool>$1,
+                        margin := <bool>$2,
+                        interest_rate := <float64>$3,
+                        initial_margin := <float64>$4,
+                        maintenance_margin := <float64>$5
+                    }
+                    unless conflict on .guild_id
+                    else (
+                        update Discord::GuildTradingSettings
+                        set {
+                            short_selling := <bool>$1,
+                            margin := <bool>$2,
+                            interest_rate := <float64>$3,
+                            initial_margin := <float64>$4,
+                            maintenance_margin := <float64>$5
+                        }
+                    )", &(
+                        settings.guild_id,
+                        settings.short_selling,
+                        settings.margin,
+                        settings.interest_rate,
+                        settings.initial_margin,
+                        settings.maintenance_margin,
+                    )).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Starts a season in a guild and returns its number, `None` when one is already running there.
+        pub async fn create_season(guild_id: String, channel_id: String, starting_balance: f64, ends_epoch: f64) -> anyhow::Result<Option<i64>, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let guild_id = guild_id.clone();
+                        let channel_id = channel_id.clone();
+                        async move {
+                            let running: Option<i64> = tx.query_single("
+                                select Discord::Season.number
+                                filter Discord::Season.guild_id = <str>$0 and not Discord::Season.ended
+                                limit 1", &(guild_id.clone(),)).await?;
+
+                            if running.is_some() {
+                                return Ok(None);
+                            }
+
+                            let number: i64 = tx.query_required_single("
+                                select (
+                                    insert Discord::Season {
+                                        guild_id := <str>$0,
+                                        channel_id := <str>$1,
+                                        starting_balance := <float64>$2,
+                                        ends := to_datetime(<float64>$3)
+                                    }
+                                ).number", &(guild_id, channel_id, starting_balance, ends_epoch)).await?;
+
+                            Ok(Some(number))
+                        }
+                    }).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Gives the user a fresh season account with the starting balance. Returns false when they already joined.
+        pub async fn join_season(season: i64, user_id: String, display_name: String) -> anyhow::Result<bool, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    let joined: Option<String> = conn.query_single("
+                        with season := (select Discord::Season filter .number = <int64>$0)
+                        select (
+                            insert Discord::SeasonParticipant {
+                                season := season,
+                                user_id := <str>$1,
+                                display_name := <str>$2,
+                                money := season.starting_balance
+                            }
+                            unless conflict on ((.season, .user_id))
+                        ).user_id", &(season, user_id, display_name)).await?;
+
+                    Ok(joined.is_some())
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Remembers that the user was told about an earnings call. Returns false when they already were.
+        pub async fn create_earnings_notice(user_id: String, ticker: String, earnings_epoch: i64) -> anyhow::Result<bool, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    let created: Option<String> = conn.query_single("
+                        select (
+                            insert Discord::EarningsNotice {
+                                user_id := <str>$0,
+                                ticker := <str>$1,
+                                earnings := to_datetime(<float64>$2)
+                            }
+                            unless conflict on ((.user_id, .ticker, .earnings))
+                        ).user_id", &(user_id, ticker, earnings_epoch as f64)).await?;
+
+                    Ok(created.is_some())
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Records an owner action which does not change any account, such as a reconciliation.
+        pub async fn create_economy_audit(owner_id: String, user_id: Option<String>, action: EconomyAction, reason: String) -> anyhow::Result<(), edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.transaction(|mut tx| {
+                        let owner_id = owner_id.clone();
+                        let user_id = user_id.clone();
+                        let reason = reason.clone();
+                        async move {
+                            record_audit(&mut tx, AuditEntry {
+                                owner_id,
+                                user_id,
+                                action,
+                                amount: None,
+                                balance_before: None,
+                                balance_after: None,
+                                reason,
+                            }).await
+                        }
+                    }).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Places a limit, stop or market-on-open order and returns its number.
+        pub async fn create_pending_order(user_id: String, channel_id: String, side: String, ticker: String, quantity: Option<f64>, order_type: String, trigger_price: Option<f64>) -> anyhow::Result<i64, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_required_single("select (insert Discord::PendingOrder {
+                        user_id := <str>$0,
+                        channel_id := <str>$1,
+                        side := <str>$2,
+                        ticker := <str>$3,
+                        quantity := <optional float64>$4,
+                        order_type := <str>$5,
+                        trigger_price := <optional float64>$6
+                    }).number", &(user_id, channel_id, side, ticker, quantity, order_type, trigger_price)).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Stores a price alert and returns its number.
+        pub async fn create_price_alert(user_id: String, channel_id: Option<String>, ticker: String, condition: String, threshold: Option<f64>, rearm: bool) -> anyhow::Result<i64, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    conn.query_required_single("select (insert Discord::PriceAlert {
+                        user_id := <str>$0,
+                        channel_id := <optional str>$1,
+                        ticker := <str>$2,
+                        condition := <str>$3,
+                        threshold := <optional float64>$4,
+                        rearm := <bool>$5
+                    }).number", &(user_id, channel_id, ticker, condition, threshold, rearm)).await
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Adds a ticker to a user's watchlist. Returns false if it was already watched.
+        pub async fn create_watchlist_entry(user_id: String, ticker: String) -> anyhow::Result<bool, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    let inserted: Vec<Uuid> = conn.query("select (insert Discord::WatchlistEntry {
+                        user_id := <str>$0,
+                        ticker := <str>$1
+                    } unless conflict).id", &(user_id, ticker)).await?;
+                    Ok(!inserted.is_empty())
+                }
+                Err(err) => Err(err)
+            }
+        }
+
+        /// Adds an item to a guild shop. Returns false when the shop already has an item of that name.
+        pub async fn create_shop_item(guild_id: String, item: ShopItem) -> anyhow::Result<bool, edgedb_tokio::Error> {
+            match get_conn().await {
+                Ok(conn) => {
+                    let inserted: Vec<Uuid> = conn.query("select (insert Discord::ShopItem {
+                        guild_id := <str>$0,
+                        name := <str>$1,
+                        kind := <str>$2,
+                        price := <float64>$3,
+                        description := <optional str>$4,
+                        role_id := <optional str>$5,
+                        duration_hours := <optional int64>$6,
+                        colour := <optional int64>$7,
+                        stock := <optional int64>$8,
+                        enabled := <bool>$9
+                    } unless conflict).id", &(
+                        guild_id,
+                        item.name,
+                        item.kind,
+                        item.price,
+                        item.description,
+                        item.role_id,
+                        item.duration_hours,
+                        item.colour,
+                        item.stock,
+                        item.enabled,
+                    )).await?;
+                    Ok(!inserted.is_empty())
+                }
+                Err(err) => Err(err)
+            }
+        }
+
         // pub async fn add_message(message: String, author_discord_id: String, channel_id: String, is_bot: bool) -> anyhow::Result<(), edgedb_tokio::Error> {
         //     let mut author_uuid: Option<Uuid> = None;
         //     let author_account = super::read::get_discord_user_info(author_discord_id).await;
@@ -7,7 +7,8 @@ pub struct User {
     pub username: String,
     pub unique_id: String,
     pub display_name: String,
-    pub money: f64
+    pub money: f64,
+    pub timezone: String,
 }
 
 #[derive(Queryable, Debug)]
@@ -32,18 +33,270 @@ pub struct ChannelMessage {
 // Portfolio
 #[derive(Queryable, Debug)]
 pub struct PortfolioUser {
-    portfolio: Portfolio,
+    pub unique_id: String,
+    pub display_name: String,
+    pub money: f64,
+    pub portfolio: Option<Portfolio>,
+    pub shorts: Vec<ShortLot>,
+    pub margin_guild_id: Option<String>,
 }
 
 #[derive(Queryable, Debug)]
 pub struct Portfolio {
-    lines: Vec<PortfolioLines>,
+    pub lines: Vec<PortfolioLines>,
 }
 
-#[derive(Queryable, Debug)]
+#[derive(Queryable, Debug, Clone)]
 pub struct PortfolioLines {
-    ticker: String,
-    quantity: f64,
-    created_at: LocalDatetime,
-    bought_at: f64,
+    pub ticker: String,
+    pub quantity: f64,
+    pub created_at: LocalDatetime,
+    pub bought_at: f64,
+    pub currency: Option<String>,
+    pub fx_rate: f64,
+}
+
+/// Shares sold short in one go, as consumed when covering
+#[derive(Queryable, Debug, Clone)]
+pub struct ShortLot {
+    pub id: Uuid,
+    pub ticker: String,
+    pub quantity: f64,
+    pub sold_at: f64,
+    pub currency: Option<String>,
+    pub fx_rate: f64,
+}
+
+/// A single purchase (lot) of a stock, as consumed when selling
+#[derive(Queryable, Debug, Clone)]
+pub struct StockLot {
+    pub id: Uuid,
+    pub quantity: f64,
+    pub bought_at: f64,
+    pub fx_rate: f64,
+}
+
+/// Money of a user and the state of their !daily claims
+#[derive(Queryable, Debug, Clone)]
+pub struct DailyState {
+    pub money: f64,
+    pub daily_streak: i64,
+    pub last_daily_epoch: Option<f64>,
+}
+
+/// What !profile shows about an account, besides its net worth
+#[derive(Queryable, Debug, Clone)]
+pub struct Profile {
+    pub display_name: String,
+    pub money: f64,
+    pub timezone: String,
+    pub daily_streak: i64,
+    pub frozen: bool,
+    pub frozen_reason: Option<String>,
+    pub registered_epoch: f64,
+    pub pending_orders: i64,
+    pub price_alerts: i64,
+    pub watched_tickers: i64,
+}
+
+/// An entry of the append-only money ledger
+#[derive(Queryable, Debug, Clone)]
+pub struct LedgerTransaction {
+    pub kind: String,
+    pub ticker: Option<String>,
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub fx_rate: Option<f64>,
+    pub quantity: Option<f64>,
+    pub amount: f64,
+    pub fees: f64,
+    pub balance_after: f64,
+    pub counterparty_id: Option<String>,
+    pub note: Option<String>,
+    pub created_local: LocalDatetime,
+}
+
+/// Money of a user next to what their ledger entries add up to
+#[derive(Queryable, Debug, Clone)]
+pub struct LedgerBalance {
+    pub unique_id: String,
+    pub display_name: String,
+    pub money: f64,
+    /// Sum of the cash movements minus the fees, from the latest opening entry on
+    pub ledger_sum: f64,
+    pub entries: i64,
+    /// Balance after the latest entry
+    pub last_balance: Option<f64>,
+}
+
+/// An economy administration action of an owner
+#[derive(Queryable, Debug, Clone)]
+pub struct EconomyAuditEntry {
+    pub owner_id: String,
+    pub user_id: Option<String>,
+    pub action: String,
+    pub amount: Option<f64>,
+    pub balance_before: Option<f64>,
+    pub balance_after: Option<f64>,
+    pub reason: String,
+    pub created_local: LocalDatetime,
+}
+
+/// How many shares of a ticker a user held at some point
+#[derive(Queryable, Debug, Clone)]
+pub struct Holding {
+    pub unique_id: String,
+    pub money: f64,
+    pub held: f64,
+}
+
+/// When a purchase happened (epoch seconds) and the quote price paid
+#[derive(Queryable, Debug, Clone)]
+pub struct PurchasePoint {
+    pub epoch: f64,
+    pub price: f64,
+}
+
+/// Money a ledger entry brought into (positive) or took out of (negative) an account, on a Europe/Brussels day
+#[derive(Queryable, Debug, Clone)]
+pub struct LedgerFlow {
+    pub user_id: String,
+    /// YYYY-MM-DD
+    pub day: String,
+    pub amount: f64,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct NetWorthSnapshot {
+    pub user_id: String,
+    pub day: String,
+    pub cash: f64,
+    pub net_worth: f64,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct PositionSnapshot {
+    pub day: String,
+    pub ticker: String,
+    pub quantity: f64,
+    pub market_value: f64,
+    pub cost_basis: f64,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct GuildTradingSettings {
+    pub guild_id: String,
+    pub short_selling: bool,
+    pub margin: bool,
+    pub interest_rate: f64,
+    pub initial_margin: f64,
+    pub maintenance_margin: f64,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct PendingOrder {
+    pub id: Uuid,
+    pub number: i64,
+    pub user_id: String,
+    pub channel_id: Option<String>,
+    pub side: String,
+    pub ticker: String,
+    pub quantity: Option<f64>,
+    pub order_type: String,
+    pub trigger_price: Option<f64>,
+    pub status: String,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct PriceAlert {
+    pub id: Uuid,
+    pub number: i64,
+    pub user_id: String,
+    pub channel_id: Option<String>,
+    pub ticker: String,
+    pub condition: String,
+    pub threshold: Option<f64>,
+    pub rearm: bool,
+    pub armed: bool,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct WatchlistEntry {
+    pub user_id: String,
+    pub ticker: String,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct ShopItem {
+    pub name: String,
+    pub kind: String,
+    pub price: f64,
+    pub description: Option<String>,
+    pub role_id: Option<String>,
+    pub duration_hours: Option<i64>,
+    pub colour: Option<i64>,
+    pub stock: Option<i64>,
+    pub enabled: bool,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct InventoryEntry {
+    pub item_name: String,
+    pub kind: String,
+    pub role_id: Option<String>,
+    pub quantity: i64,
+    pub expires_epoch: Option<f64>,
+}
+
+/// Role of a temp_role item whose time is up
+#[derive(Queryable, Debug, Clone)]
+pub struct ExpiredRole {
+    pub user_id: String,
+    pub guild_id: String,
+    pub item_name: String,
+    pub role_id: Option<String>,
+}
+
+/// Role given by an item of a user's inventory, in any guild
+#[derive(Queryable, Debug, Clone)]
+pub struct OwnedRole {
+    pub guild_id: String,
+    pub item_name: String,
+    pub role_id: Option<String>,
+}
+
+/// Trading contest of a guild
+#[derive(Queryable, Debug, Clone)]
+pub struct Season {
+    pub number: i64,
+    pub guild_id: String,
+    pub channel_id: String,
+    pub starting_balance: f64,
+    pub started_epoch: f64,
+    pub ends_epoch: f64,
+    pub ended: bool,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct SeasonParticipant {
+    pub user_id: String,
+    pub display_name: String,
+    pub money: f64,
+    pub lines: Vec<PortfolioLines>,
+    pub final_net_worth: Option<f64>,
+    pub final_rank: Option<i64>,
+}
+
+impl SeasonParticipant {
+    /// The season account in the shape of a main account, so that it can be valued the same way.
+    pub fn as_portfolio_user(&self) -> PortfolioUser {
+        PortfolioUser {
+            unique_id: self.user_id.clone(),
+            display_name: self.display_name.clone(),
+            money: self.money,
+            portfolio: Some(Portfolio { lines: self.lines.clone() }),
+            shorts: vec![],
+            margin_guild_id: None,
+        }
+    }
 }
@@ -1,3 +1,3 @@
 pub mod mem;
 pub mod edge;
-mod edge_models; // No need to expose
+pub mod edge_models;
@@ -1,5 +1,6 @@
 pub mod join_message;
 pub mod message_announcer;
 pub mod sticky_plugin;
+pub mod slash_commands;
 pub mod scheduled;
 pub use scheduled::*;
\ No newline at end of file
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::{Europe::Brussels, Tz};
+use serenity::{client::Context, model::id::UserId, utils::MessageBuilder};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::constants::trading::{CORPORATE_ACTIONS_CHECK_INTERVAL, CORPORATE_ACTIONS_HOUR, CORPORATE_ACTIONS_LOOKBACK_DAYS};
+use crate::persistence::edge::requests::read::get_all_portfolios;
+use crate::persistence::edge::requests::update::{apply_dividend, apply_split, CorporateActionResult};
+use crate::utils::fx::get_fx_rate;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::quotes::{quote_provider, AssetClass, CorporateAction, HistoryRange};
+use crate::utils::shortcuts::send_private_message_or_console_error;
+use crate::utils::stock_utils::{epoch_to_date, get_stock_price};
+
+/// Async loop which credits the dividends and applies the splits of the held and shorted tickers, once a day.
+///
+/// Install directly on a handler.
+pub async fn task_corporate_actions(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        let mut last_run_day: Option<String> = None;
+
+        loop {
+            let now: DateTime<Tz> = Utc::now().with_timezone(&Brussels);
+            let today: String = now.format("%Y-%m-%d").to_string();
+
+            if now.hour() >= CORPORATE_ACTIONS_HOUR && last_run_day.as_ref() != Some(&today) {
+                process_corporate_actions(&ctx).await;
+                last_run_day = Some(today);
+            }
+
+            tokio::time::sleep(CORPORATE_ACTIONS_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn process_corporate_actions(ctx: &Context) -> () {
+    let users = match get_all_portfolios().await {
+        Ok(users) => users,
+        Err(err) => {
+            log_error(format!("(corporate actions) Could not query the portfolios: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    let tickers: BTreeSet<String> = users
+        .iter()
+        .filter_map(|user| user.portfolio.as_ref())
+        .flat_map(|portfolio| portfolio.lines.iter().map(|line| line.ticker.clone()))
+        // Short sellers pay the dividends and have their lots split too
+        .chain(users.iter().flat_map(|user| user.shorts.iter().map(|lot| lot.ticker.clone())))
+        // Crypto pays no dividend and does not split
+        .filter(|ticker| AssetClass::of_ticker(ticker) == AssetClass::Equity)
+        .collect();
+
+    let now: i64 = Utc::now().timestamp();
+    let oldest: i64 = now - CORPORATE_ACTIONS_LOOKBACK_DAYS * 24 * 60 * 60;
+
+    for ticker in tickers.iter() {
+        let actions: Vec<CorporateAction> = match quote_provider().get_corporate_actions(ticker, HistoryRange::OneMonth).await {
+            Ok(actions) => actions,
+            Err(err) => {
+                log_error(format!("(corporate actions) Could not fetch the events of {}: {}.", ticker, err), LogErrorLevel::WARN, String::from(""), true).await;
+                continue;
+            }
+        };
+
+        for action in actions.iter().filter(|action| action.ex_date() >= oldest && action.ex_date() <= now) {
+            apply_corporate_action(ctx, ticker, action).await;
+        }
+    }
+}
+
+/// Applies a single dividend or split (at most once, the persistence layer remembers them) and notifies the holders.
+async fn apply_corporate_action(ctx: &Context, ticker: &String, action: &CorporateAction) -> () {
+    let mut currency: String = String::new();
+
+    let applied = match action {
+        CorporateAction::Dividend { ex_date, amount } => {
+            // The dividend is paid in the quote currency of the ticker
+            currency = match get_stock_price(ticker.clone()).await {
+                Ok(info) => info.currency,
+                Err(err) => {
+                    log_error(format!("(corporate actions) Could not get the currency of {}: {}.", ticker, err), LogErrorLevel::WARN, String::from(""), true).await;
+                    return;
+                }
+            };
+            let fx_rate: f64 = match get_fx_rate(&currency).await {
+                Ok(fx_rate) => fx_rate,
+                Err(err) => {
+                    log_error(format!("(corporate actions) Could not convert {}: {}.", currency, err), LogErrorLevel::WARN, String::from(""), true).await;
+                    return;
+                }
+            };
+
+            apply_dividend(ticker.clone(), *ex_date as f64, *amount, currency.clone(), fx_rate).await
+        }
+        CorporateAction::Split { ex_date, numerator, denominator } => {
+            apply_split(ticker.clone(), *ex_date as f64, *numerator, *denominator).await
+        }
+    };
+
+    let results: Vec<CorporateActionResult> = match applied {
+        Ok(Some(results)) => results,
+        // Already applied on a previous run
+        Ok(None) => return,
+        Err(err) => {
+            log_error(format!("(corporate actions) Could not apply {:?} on {}: {}.", action, ticker, err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    let ex_date: String = epoch_to_date(action.ex_date())[..10].to_string();
+    for result in results.iter() {
+        let message: String = match action {
+            CorporateAction::Dividend { amount, .. } if result.short => format!(
+                "${} paid a dividend of {} {} per share (ex-date {}): €{:.2} debited for your {} shorted shares.",
+                ticker, amount, currency, ex_date, -result.credited, result.quantity_before
+            ),
+            CorporateAction::Split { numerator, denominator, .. } if result.short => format!(
+                "${} split {}:{} (ex-date {}): your {} shorted shares became {}, their sale prices were adjusted accordingly.",
+                ticker, numerator, denominator, ex_date, result.quantity_before, result.quantity_after
+            ),
+            CorporateAction::Dividend { amount, .. } => format!(
+                "${} paid a dividend of {} {} per share (ex-date {}): €{:.2} credited for your {} shares.",
+                ticker, amount, currency, ex_date, result.credited, result.quantity_before
+            ),
+            CorporateAction::Split { numerator, denominator, .. } => format!(
+                "${} split {}:{} (ex-date {}): your {} shares became {}, their purchase prices were adjusted accordingly.",
+                ticker, numerator, denominator, ex_date, result.quantity_before, result.quantity_after
+            ),
+        };
+
+        if let Ok(user_id) = result.user_id.parse::<u64>() {
+            let mut builder = MessageBuilder::new();
+            builder.push_line(message);
+            send_private_message_or_console_error(ctx, UserId(user_id), &mut builder).await;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::Utc;
+use chrono_tz::{Europe::Brussels, Tz};
+use serenity::{client::Context, model::id::UserId, utils::MessageBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::constants::trading::EARNINGS_CHECK_INTERVAL;
+use crate::datastructs::stocks_config::read_stocks_config;
+use crate::persistence::edge::requests::create::create_earnings_notice;
+use crate::persistence::edge::requests::read::{get_all_portfolios, get_all_watchlist_entries, get_discord_user_info};
+use crate::utils::earnings::{followers_by_ticker, upcoming_calls, EarningsCall};
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::shortcuts::send_private_message_or_console_error;
+use crate::utils::stock_utils::get_stock_prices;
+
+/// Async loop which tells the users about the upcoming earnings calls of the tickers they hold or watch.
+///
+/// Install directly on a handler.
+pub async fn task_earnings_notifications(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        loop {
+            notify_upcoming_earnings(&ctx).await;
+            tokio::time::sleep(EARNINGS_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn notify_upcoming_earnings(ctx: &Context) -> () {
+    let notice_days: i64 = read_stocks_config().earnings_notice_days;
+    if notice_days <= 0 {
+        return;
+    }
+
+    let users = match get_all_portfolios().await {
+        Ok(users) => users,
+        Err(err) => {
+            log_error(format!("(earnings) Could not query the portfolios: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+    let watchlist = match get_all_watchlist_entries().await {
+        Ok(watchlist) => watchlist,
+        Err(err) => {
+            log_error(format!("(earnings) Could not query the watchlists: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    let followers: BTreeMap<String, BTreeSet<String>> = followers_by_ticker(&users, &watchlist);
+    if followers.is_empty() {
+        return;
+    }
+
+    let quotes = get_stock_prices(followers.keys().cloned().collect()).await;
+    let calls: Vec<EarningsCall> = upcoming_calls(&quotes, Utc::now(), notice_days);
+
+    // One message per user, listing every call they were not told about yet
+    let mut to_notify: BTreeMap<String, Vec<&EarningsCall>> = BTreeMap::new();
+    for call in calls.iter() {
+        for user_id in followers.get(&call.ticker).into_iter().flatten() {
+            match create_earnings_notice(user_id.clone(), call.ticker.clone(), call.start).await {
+                Ok(true) => to_notify.entry(user_id.clone()).or_default().push(call),
+                Ok(false) => {},
+                Err(err) => {
+                    log_error(format!("(earnings) Could not record the notice of ${} for {}: {}.", call.ticker, user_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+            }
+        }
+    }
+
+    for (user_id, calls) in to_notify.iter() {
+        let user_discord_id: u64 = match user_id.parse::<u64>() {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        let timezone: Tz = match get_discord_user_info(user_id.clone()).await {
+            Ok(Some(user)) => user.timezone.parse().unwrap_or(Brussels),
+            _ => Brussels,
+        };
+
+        let mut builder = MessageBuilder::new();
+        builder.push_bold_line("Upcoming earnings calls for tickers you hold or watch:");
+        for call in calls.iter() {
+            builder.push_line(format!("${} ({}): {}", call.ticker, call.name, call.describe(&timezone)));
+        }
+
+        send_private_message_or_console_error(ctx, UserId(user_discord_id), &mut builder).await;
+    }
+}
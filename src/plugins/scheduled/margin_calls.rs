@@ -0,0 +1,251 @@
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::{Europe::Brussels, Tz};
+use serenity::{client::Context, model::id::UserId, utils::MessageBuilder};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::constants::trading::{MARGIN_CHECK_INTERVAL, MARGIN_INTEREST_HOUR};
+use crate::persistence::edge::requests::read::{get_all_portfolios, get_frozen_account_ids};
+use crate::persistence::edge::requests::update::{
+    charge_margin_interest, cover_short_position, record_margin_call, sell_stock_lines, CoverOutcome, SellOutcome,
+};
+use crate::persistence::edge_models::PortfolioUser;
+use crate::utils::fx::get_fx_rate;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::margin::{guild_margin_rules, margin_account, MarginAccount, MarginRules, MarginState};
+use crate::utils::market_hours::market_status;
+use crate::utils::portfolio::{fetch_held_prices, value_positions, value_shorts, HeldPrice};
+use crate::utils::shortcuts::send_private_message_or_console_error;
+use crate::utils::stock_utils::get_stock_prices;
+
+/// A position the liquidation may close, valued in the account currency.
+struct LiquidationCandidate {
+    ticker: String,
+    quantity: f64,
+    market_value: f64,
+    short: bool,
+}
+
+/// Async loop which charges the daily interest on borrowed money and shares, warns the users close to a margin call
+/// and liquidates the positions of those below the maintenance margin.
+///
+/// Install directly on a handler.
+pub async fn task_margin_calls(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        let mut last_interest_day: Option<String> = None;
+        // Users already warned, so that they are not messaged on every check
+        let mut warned: HashSet<String> = HashSet::new();
+
+        loop {
+            tokio::time::sleep(MARGIN_CHECK_INTERVAL).await;
+
+            let now: DateTime<Tz> = Utc::now().with_timezone(&Brussels);
+            let today: String = now.format("%Y-%m-%d").to_string();
+            let charge_interest: bool = now.hour() >= MARGIN_INTEREST_HOUR && last_interest_day.as_ref() != Some(&today);
+
+            check_margin_accounts(&ctx, charge_interest, &mut warned).await;
+            if charge_interest {
+                last_interest_day = Some(today);
+            }
+        }
+    });
+}
+
+async fn check_margin_accounts(ctx: &Context, charge_interest: bool, warned: &mut HashSet<String>) -> () {
+    // Frozen accounts are neither charged nor liquidated until the owners unfreeze them
+    let frozen: HashSet<String> = match get_frozen_account_ids().await {
+        Ok(frozen) => frozen.into_iter().collect(),
+        Err(err) => {
+            log_error(format!("(margin calls) Could not query the frozen accounts: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+    let users: Vec<PortfolioUser> = match get_all_portfolios().await {
+        Ok(users) => users
+            .into_iter()
+            .filter(|user| user.money < 0.0 || !user.shorts.is_empty())
+            .filter(|user| !frozen.contains(&user.unique_id))
+            .collect(),
+        Err(err) => {
+            log_error(format!("(margin calls) Could not query the portfolios: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    // Users who paid their debts back are not borrowing anymore
+    warned.retain(|unique_id| users.iter().any(|user| &user.unique_id == unique_id));
+    if users.is_empty() {
+        return;
+    }
+
+    let prices = fetch_held_prices(&users).await;
+
+    for user in users.iter() {
+        let rules: MarginRules = guild_margin_rules(user.margin_guild_id.clone()).await;
+        let account: MarginAccount = margin_account(user, &prices);
+
+        if charge_interest {
+            let interest: f64 = account.borrowed() * rules.interest_rate / 365.0;
+            if interest > 0.0 {
+                let note: String = format!(
+                    "Daily interest at {:.2}% a year on €{:.2} borrowed",
+                    rules.interest_rate * 100.0,
+                    account.borrowed()
+                );
+                if let Err(err) = charge_margin_interest(user.unique_id.clone(), interest, note).await {
+                    log_error(format!("(margin calls) Could not charge the interest of {}: {}.", user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+            }
+        }
+
+        match account.state(&rules) {
+            MarginState::Healthy => {
+                warned.remove(&user.unique_id);
+            }
+            MarginState::Warning => {
+                if warned.insert(user.unique_id.clone()) {
+                    warn_user(ctx, user, &account, &rules).await;
+                }
+            }
+            MarginState::Liquidation => {
+                liquidate(ctx, user, &account, &rules, &prices).await;
+                warned.remove(&user.unique_id);
+            }
+        }
+    }
+}
+
+async fn warn_user(ctx: &Context, user: &PortfolioUser, account: &MarginAccount, rules: &MarginRules) -> () {
+    let note: String = format!(
+        "Warning: equity €{:.2} close to the maintenance requirement of €{:.2}",
+        account.equity(),
+        account.maintenance_requirement(rules)
+    );
+    if let Err(err) = record_margin_call(user.unique_id.clone(), note).await {
+        log_error(format!("(margin calls) Could not record the warning of {}: {}.", user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+    }
+
+    if let Ok(user_id) = user.unique_id.parse::<u64>() {
+        let mut builder = MessageBuilder::new();
+        builder
+            .push_bold_line("Margin call warning")
+            .push_line(format!(
+                "Your equity (€{:.2}) is close to the maintenance requirement of €{:.2} ({:.0}% of €{:.2} of positions).",
+                account.equity(),
+                account.maintenance_requirement(rules),
+                rules.maintenance_margin * 100.0,
+                account.exposure()
+            ))
+            .push_line("Sell, cover or wait for better prices: below the requirement your largest positions are liquidated. See !margin.");
+        send_private_message_or_console_error(ctx, UserId(user_id), &mut builder).await;
+    }
+}
+
+/// Closes the largest positions first, at the current prices, until the initial requirement is met again.
+/// Positions whose market is closed are left alone, the next check takes care of them.
+async fn liquidate(ctx: &Context, user: &PortfolioUser, account: &MarginAccount, rules: &MarginRules, prices: &HashMap<String, HeldPrice>) -> () {
+    let mut candidates: Vec<LiquidationCandidate> = vec![];
+
+    if let Some(portfolio) = user.portfolio.as_ref() {
+        for valuation in value_positions(&portfolio.lines, prices).iter().filter(|valuation| valuation.price.is_some()) {
+            candidates.push(LiquidationCandidate {
+                ticker: valuation.position.ticker.clone(),
+                quantity: valuation.position.quantity,
+                market_value: valuation.market_value(),
+                short: false,
+            });
+        }
+    }
+    for short in value_shorts(&user.shorts, prices).iter().filter(|short| short.price.is_some()) {
+        candidates.push(LiquidationCandidate {
+            ticker: short.ticker.clone(),
+            quantity: short.quantity,
+            market_value: short.market_value(),
+            short: true,
+        });
+    }
+    candidates.sort_by(|a, b| b.market_value.total_cmp(&a.market_value));
+
+    let tickers: BTreeSet<String> = candidates.iter().map(|candidate| candidate.ticker.clone()).collect();
+    let quotes = get_stock_prices(tickers.into_iter().collect()).await;
+
+    let mut left_to_close: f64 = account.excess_exposure(rules);
+    let mut closed: Vec<String> = vec![];
+
+    for candidate in candidates.iter() {
+        if left_to_close <= 0.0 {
+            break;
+        }
+
+        let info = match quotes.get(&candidate.ticker) {
+            Some(Ok(info)) if market_status(info, Utc::now()).is_open() => info,
+            _ => continue,
+        };
+        let fx_rate: f64 = match get_fx_rate(&info.currency).await {
+            Ok(fx_rate) => fx_rate,
+            Err(_) => continue,
+        };
+
+        // The whole position when it is not enough, the needed part otherwise
+        let quantity: Option<f64> = if candidate.market_value <= left_to_close || candidate.market_value <= 0.0 {
+            None
+        } else {
+            Some(candidate.quantity * left_to_close / candidate.market_value)
+        };
+
+        if candidate.short {
+            match cover_short_position(user.unique_id.clone(), candidate.ticker.clone(), quantity, info.price, info.currency.clone(), fx_rate).await {
+                Ok(CoverOutcome::Covered { quantity, cost, profit, .. }) => {
+                    left_to_close -= cost;
+                    closed.push(format!("Covered {} ${} at {:.2} {} (€{:.2}, profit/loss €{:+.2})", quantity, candidate.ticker, info.price, info.currency, cost, profit));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log_error(format!("(margin calls) Could not cover ${} of {}: {}.", candidate.ticker, user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+            }
+        } else {
+            match sell_stock_lines(user.unique_id.clone(), candidate.ticker.clone(), quantity, info.price, info.currency.clone(), fx_rate, None).await {
+                Ok(SellOutcome::Sold { lots, proceeds, .. }) => {
+                    let sold: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                    let profit: f64 = lots.iter().map(|lot| lot.profit).sum();
+                    left_to_close -= proceeds;
+                    closed.push(format!("Sold {} ${} at {:.2} {} (€{:.2}, profit/loss €{:+.2})", sold, candidate.ticker, info.price, info.currency, proceeds, profit));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log_error(format!("(margin calls) Could not sell ${} of {}: {}.", candidate.ticker, user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+            }
+        }
+    }
+
+    if closed.is_empty() {
+        return;
+    }
+
+    let note: String = format!(
+        "Liquidation: equity €{:.2} below the maintenance requirement of €{:.2}. {}",
+        account.equity(),
+        account.maintenance_requirement(rules),
+        closed.join("; ")
+    );
+    if let Err(err) = record_margin_call(user.unique_id.clone(), note).await {
+        log_error(format!("(margin calls) Could not record the liquidation of {}: {}.", user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+    }
+
+    if let Ok(user_id) = user.unique_id.parse::<u64>() {
+        let mut builder = MessageBuilder::new();
+        builder
+            .push_bold_line("Margin call: positions liquidated")
+            .push_line(format!(
+                "Your equity (€{:.2}) fell below the maintenance requirement of €{:.2}, so these positions were closed:",
+                account.equity(),
+                account.maintenance_requirement(rules)
+            ));
+        for line in closed.iter() {
+            builder.push_line(format!("- {}", line));
+        }
+        send_private_message_or_console_error(ctx, UserId(user_id), &mut builder).await;
+    }
+}
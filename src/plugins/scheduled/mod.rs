@@ -1,3 +1,11 @@
 pub mod tea_time;
 pub mod weather;
-pub mod release_announcer;
\ No newline at end of file
+pub mod release_announcer;
+pub mod portfolio_snapshots;
+pub mod pending_orders;
+pub mod price_alerts;
+pub mod corporate_actions;
+pub mod margin_calls;
+pub mod seasons;
+pub mod earnings;
+pub mod shop_roles;
\ No newline at end of file
@@ -0,0 +1,120 @@
+use chrono::Utc;
+use serenity::{client::Context, model::id::UserId, utils::MessageBuilder};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::constants::trading::ORDERS_CHECK_INTERVAL;
+use crate::persistence::edge::requests::read::get_pending_orders;
+use crate::persistence::edge::requests::update::{
+    buy_stock_line, fail_order, sell_stock_lines, BuyOutcome, SellOutcome,
+};
+use crate::persistence::edge_models::{PendingOrder, PortfolioUser};
+use crate::utils::fx::get_fx_rate;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::market_hours::market_status;
+use crate::utils::orders::{should_fill, OrderSide, OrderType};
+use crate::utils::shortcuts::send_private_message_or_console_error;
+use crate::utils::stock_utils::get_stock_prices;
+
+/// Async loop which checks the pending orders against the current prices, and fills them while their market is open.
+///
+/// Install directly on a handler.
+pub async fn task_pending_orders(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(ORDERS_CHECK_INTERVAL).await;
+            process_pending_orders(&ctx).await;
+        }
+    });
+}
+
+async fn process_pending_orders(ctx: &Context) -> () {
+    let orders: Vec<PendingOrder> = match get_pending_orders(None).await {
+        Ok(orders) => orders,
+        Err(err) => {
+            log_error(format!("(pending orders) Could not query the pending orders: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    if orders.is_empty() {
+        return;
+    }
+
+    let tickers: BTreeSet<String> = orders.iter().map(|order| order.ticker.clone()).collect();
+    let prices = get_stock_prices(tickers.into_iter().collect()).await;
+
+    for order in orders.iter() {
+        let (side, order_type): (OrderSide, OrderType) = match (order.side.parse(), order.order_type.parse()) {
+            (Ok(side), Ok(order_type)) => (side, order_type),
+            _ => continue,
+        };
+        // Prices quoted while the exchange is closed are stale
+        let (price, currency): (f64, String) = match prices.get(&order.ticker) {
+            Some(Ok(info)) if market_status(info, Utc::now()).is_open() => (info.price, info.currency.clone()),
+            _ => continue,
+        };
+
+        if !should_fill(side, order_type, order.trigger_price, price) {
+            continue;
+        }
+
+        // The trade marks the order as filled in its own transaction, a cancellation in the meantime wins over the fill
+        let notification: String = match fill_order(order, side, price, currency).await {
+            Ok(Some(message)) => message,
+            Ok(None) => continue,
+            Err(reason) => {
+                if let Err(err) = fail_order(order.id, reason.clone()).await {
+                    log_error(format!("(pending orders) Could not mark order #{} as failed: {}.", order.number, err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+                format!("Your {} order #{} on ${} could not be filled: {}", order.order_type, order.number, order.ticker, reason)
+            }
+        };
+
+        if let Ok(user_id) = order.user_id.parse::<u64>() {
+            let mut builder = MessageBuilder::new();
+            builder.push_line(notification);
+            send_private_message_or_console_error(ctx, UserId(user_id), &mut builder).await;
+        }
+    }
+}
+
+/// Fills an order through the same transactional path as the buy/sell commands.
+/// `None` when the order was cancelled meanwhile and nothing was traded.
+async fn fill_order(order: &PendingOrder, side: OrderSide, price: f64, currency: String) -> Result<Option<String>, String> {
+    let fx_rate: f64 = get_fx_rate(&currency).await.map_err(|err| format!("{}", err))?;
+
+    match side {
+        OrderSide::Buy => {
+            let quantity: f64 = order.quantity.unwrap_or(0.0);
+            match buy_stock_line(order.user_id.clone(), None, order.ticker.clone(), quantity, price, currency.clone(), fx_rate, Some(order.id), |_: &PortfolioUser| 0.0).await {
+                Ok(BuyOutcome::Bought { remaining_money }) => Ok(Some(format!(
+                    "Your {} order #{} was filled: bought {} ${} at {:.2} {}. Your money: €{:.2}",
+                    order.order_type, order.number, quantity, order.ticker, price, currency, remaining_money
+                ))),
+                Ok(BuyOutcome::InsufficientFunds { money, .. }) => Err(format!("not enough money (€{:.2} needed, €{:.2} available).", quantity * price * fx_rate, money)),
+                Ok(BuyOutcome::UserNotFound) => Err(String::from("account not found.")),
+                Ok(BuyOutcome::AccountFrozen) => Err(String::from("your account is frozen.")),
+                Ok(BuyOutcome::OrderClosed) => Ok(None),
+                Err(err) => Err(format!("{}", err)),
+            }
+        }
+        OrderSide::Sell => {
+            match sell_stock_lines(order.user_id.clone(), order.ticker.clone(), order.quantity, price, currency.clone(), fx_rate, Some(order.id)).await {
+                Ok(SellOutcome::Sold { lots, proceeds, remaining_money }) => {
+                    let sold: f64 = lots.iter().map(|lot| lot.quantity).sum();
+                    let profit: f64 = lots.iter().map(|lot| lot.profit).sum();
+                    Ok(Some(format!(
+                        "Your {} order #{} was filled: sold {} ${} at {:.2} {} for €{:.2} (realized €{:+.2}). Your money: €{:.2}",
+                        order.order_type, order.number, sold, order.ticker, price, currency, proceeds, profit, remaining_money
+                    )))
+                }
+                Ok(SellOutcome::Oversold { owned }) => Err(format!("you only own {} ${}.", owned, order.ticker)),
+                Ok(SellOutcome::UserNotFound) => Err(String::from("account not found.")),
+                Ok(SellOutcome::AccountFrozen) => Err(String::from("your account is frozen.")),
+                Ok(SellOutcome::OrderClosed) => Ok(None),
+                Err(err) => Err(format!("{}", err)),
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::{Europe::Brussels, Tz};
+
+use crate::constants::trading::{SNAPSHOT_CHECK_INTERVAL, SNAPSHOT_HOUR};
+use crate::persistence::edge::requests::create::{create_net_worth_snapshot, SnapshotPosition};
+use crate::persistence::edge::requests::read::get_all_portfolios;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::portfolio::{fetch_held_prices, net_worth, value_positions};
+
+/// Async loop which stores the net worth of every user and the value of their positions once a day, after the markets closed.
+///
+/// Install directly on a handler.
+pub async fn task_portfolio_snapshots() -> () {
+    tokio::spawn(async move {
+        let mut last_snapshot_day: Option<String> = None;
+
+        loop {
+            let now: DateTime<Tz> = Utc::now().with_timezone(&Brussels);
+            let today: String = now.format("%Y-%m-%d").to_string();
+
+            if now.hour() >= SNAPSHOT_HOUR && last_snapshot_day.as_ref() != Some(&today) {
+                take_snapshots(today.clone()).await;
+                last_snapshot_day = Some(today);
+            }
+
+            tokio::time::sleep(SNAPSHOT_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn take_snapshots(day: String) -> () {
+    let users = match get_all_portfolios().await {
+        Ok(users) => users,
+        Err(err) => {
+            log_error(format!("(snapshots) Could not query the portfolios: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+    let prices = fetch_held_prices(&users).await;
+
+    for user in users.iter() {
+        let worth: f64 = net_worth(user, &prices);
+        let positions: Vec<SnapshotPosition> = match user.portfolio.as_ref() {
+            Some(portfolio) => value_positions(&portfolio.lines, &prices)
+                .iter()
+                .map(|valuation| SnapshotPosition {
+                    ticker: valuation.position.ticker.clone(),
+                    quantity: valuation.position.quantity,
+                    market_value: valuation.market_value(),
+                    cost_basis: valuation.position.cost_basis,
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        if let Err(err) = create_net_worth_snapshot(user.unique_id.clone(), day.clone(), user.money, worth, positions).await {
+            log_error(format!("(snapshots) Could not store the snapshot of {}: {}.", user.unique_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, UserId},
+    utils::MessageBuilder,
+};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::constants::trading::ALERTS_CHECK_INTERVAL;
+use crate::persistence::edge::requests::delete::delete_price_alert_by_id;
+use crate::persistence::edge::requests::read::get_price_alerts;
+use crate::persistence::edge::requests::update::set_price_alert_armed;
+use crate::persistence::edge_models::PriceAlert;
+use crate::utils::alerts::AlertCondition;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::shortcuts::{send_or_console_err, send_private_message_or_console_error};
+use crate::utils::stock_utils::{get_stock_prices, StockInfo};
+
+/// Async loop which evaluates the users' price alerts against the current quotes.
+///
+/// Install directly on a handler.
+pub async fn task_price_alerts(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(ALERTS_CHECK_INTERVAL).await;
+            process_price_alerts(&ctx).await;
+        }
+    });
+}
+
+async fn process_price_alerts(ctx: &Context) -> () {
+    let alerts: Vec<PriceAlert> = match get_price_alerts(None).await {
+        Ok(alerts) => alerts,
+        Err(err) => {
+            log_error(format!("(price alerts) Could not query the alerts: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    if alerts.is_empty() {
+        return;
+    }
+
+    let tickers: BTreeSet<String> = alerts.iter().map(|alert| alert.ticker.clone()).collect();
+    let quotes = get_stock_prices(tickers.into_iter().collect()).await;
+
+    for alert in alerts.iter() {
+        let condition: AlertCondition = match AlertCondition::from_stored(&alert.condition, alert.threshold) {
+            Some(condition) => condition,
+            None => continue,
+        };
+        let info: &StockInfo = match quotes.get(&alert.ticker) {
+            Some(Ok(info)) => info,
+            _ => continue,
+        };
+        let is_met: bool = condition.is_met(info);
+
+        let result = if alert.armed && is_met {
+            notify_alert(ctx, alert, condition, info).await;
+            if alert.rearm {
+                set_price_alert_armed(alert.id, false).await
+            } else {
+                delete_price_alert_by_id(alert.id).await
+            }
+        } else if !alert.armed && !is_met {
+            // The condition went back to normal, the next crossing triggers again
+            set_price_alert_armed(alert.id, true).await
+        } else {
+            Ok(())
+        };
+
+        if let Err(err) = result {
+            log_error(format!("(price alerts) Could not update alert #{}: {}.", alert.number, err), LogErrorLevel::ERROR, String::from(""), true).await;
+        }
+    }
+}
+
+async fn notify_alert(ctx: &Context, alert: &PriceAlert, condition: AlertCondition, info: &StockInfo) -> () {
+    let mut builder = MessageBuilder::new();
+
+    if let Ok(user_id) = alert.user_id.parse::<u64>() {
+        builder.mention(&UserId(user_id)).push(" ");
+    }
+    builder
+        .push_bold(format!("Alert #{}: ${} {}", alert.number, alert.ticker, condition))
+        .push_line("")
+        .push_line(format!(
+            "Price ${:.2} ({:+.2}% today), 52-week high ${:.2}",
+            info.price, info.regular_market_change_percent, info.fifty_two_week_high
+        ));
+    if alert.rearm {
+        builder.push_italic_line("This alert re-arms once the condition is not met anymore.");
+    }
+
+    match alert.channel_id.as_ref().and_then(|channel_id| channel_id.parse::<u64>().ok()) {
+        Some(channel_id) => send_or_console_err(ctx, ChannelId(channel_id), &mut builder).await,
+        None => {
+            if let Ok(user_id) = alert.user_id.parse::<u64>() {
+                send_private_message_or_console_error(ctx, UserId(user_id), &mut builder).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use serenity::{client::Context, model::id::ChannelId, utils::MessageBuilder};
+use std::sync::Arc;
+
+use crate::constants::trading::{SEASON_CHECK_INTERVAL, SEASON_STANDINGS_SIZE};
+use crate::persistence::edge::requests::read::{get_due_seasons, get_season_participants};
+use crate::persistence::edge::requests::update::{finish_season, SeasonStanding};
+use crate::persistence::edge_models::{PortfolioUser, Season, SeasonParticipant};
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::portfolio::fetch_held_prices;
+use crate::utils::seasons::{format_standings, rank_participants, RankedParticipant};
+use crate::utils::shortcuts::send_or_console_err;
+
+/// Async loop which closes the seasons whose end date has passed and announces their final standings.
+///
+/// Install directly on a handler.
+pub async fn task_season_ends(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        loop {
+            match get_due_seasons().await {
+                Ok(seasons) => {
+                    for season in seasons.iter() {
+                        close_season(&ctx, season).await;
+                    }
+                }
+                Err(err) => {
+                    log_error(format!("(seasons) Could not query the seasons to close: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+                }
+            }
+
+            tokio::time::sleep(SEASON_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Values the season accounts at the current prices, archives the standings and announces them where the season started.
+pub async fn close_season(ctx: &Context, season: &Season) -> () {
+    let participants: Vec<SeasonParticipant> = match get_season_participants(season.number, None).await {
+        Ok(participants) => participants,
+        Err(err) => {
+            log_error(format!("(seasons) Could not query the participants of season #{}: {}.", season.number, err), LogErrorLevel::ERROR, season.channel_id.clone(), true).await;
+            return;
+        }
+    };
+
+    let accounts: Vec<PortfolioUser> = participants.iter().map(|participant| participant.as_portfolio_user()).collect();
+    let prices = fetch_held_prices(&accounts).await;
+    let ranked: Vec<RankedParticipant> = rank_participants(&participants, &prices);
+
+    let standings: Vec<SeasonStanding> = ranked
+        .iter()
+        .map(|participant| SeasonStanding {
+            user_id: participant.user_id.clone(),
+            net_worth: participant.net_worth,
+            rank: participant.rank,
+        })
+        .collect();
+
+    match finish_season(season.number, standings).await {
+        Ok(true) => {}
+        // Already closed, by an admin or a previous run
+        Ok(false) => return,
+        Err(err) => {
+            log_error(format!("(seasons) Could not close season #{}: {}.", season.number, err), LogErrorLevel::ERROR, season.channel_id.clone(), true).await;
+            return;
+        }
+    }
+
+    let mut builder = MessageBuilder::new();
+    builder.push_bold_line(format!("Season #{} is over!", season.number));
+    if ranked.is_empty() {
+        builder.push_line("Nobody took part.");
+    } else {
+        builder
+            .push_line(format!("Final standings, starting from €{:.2}:", season.starting_balance))
+            .push_line(format_standings(&ranked, season.starting_balance, SEASON_STANDINGS_SIZE))
+            .push_italic_line(format!("Browse them again with !season results {}.", season.number));
+    }
+
+    if let Ok(channel_id) = season.channel_id.parse::<u64>() {
+        send_or_console_err(ctx, ChannelId(channel_id), &mut builder).await;
+    }
+}
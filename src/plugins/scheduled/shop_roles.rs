@@ -0,0 +1,49 @@
+use serenity::client::Context;
+use std::sync::Arc;
+
+use crate::constants::economy::SHOP_EXPIRY_CHECK_INTERVAL;
+use crate::persistence::edge::requests::delete::delete_inventory_item;
+use crate::persistence::edge::requests::read::get_expired_temp_roles;
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::shortcuts::remove_member_role_or_gone;
+
+/// Async loop which takes back the temporary roles bought in the shops once their time is up.
+///
+/// Install directly on a handler.
+pub async fn task_temp_role_expiry(ctx: Arc<Context>) -> () {
+    tokio::spawn(async move {
+        loop {
+            take_back_expired_roles(&ctx).await;
+            tokio::time::sleep(SHOP_EXPIRY_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn take_back_expired_roles(ctx: &Context) -> () {
+    let expired = match get_expired_temp_roles().await {
+        Ok(expired) => expired,
+        Err(err) => {
+            log_error(format!("(shop roles) Could not query the expired roles: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+            return;
+        }
+    };
+
+    for entry in expired.iter() {
+        let ids = (
+            entry.guild_id.parse::<u64>(),
+            entry.user_id.parse::<u64>(),
+            entry.role_id.as_ref().map(|role_id| role_id.parse::<u64>()),
+        );
+        if let (Ok(guild_id), Ok(user_id), Some(Ok(role_id))) = ids {
+            // A member who left or a deleted role is done with, other failures are retried on the next check
+            if let Err(err) = remove_member_role_or_gone(ctx, guild_id, user_id, role_id, "Shop item expired").await {
+                log_error(format!("(shop roles) Could not take role {} back from {}, retrying later: {}.", role_id, user_id, err), LogErrorLevel::WARN, String::from(""), false).await;
+                continue;
+            }
+        }
+
+        if let Err(err) = delete_inventory_item(entry.user_id.clone(), entry.guild_id.clone(), entry.item_name.clone()).await {
+            log_error(format!("(shop roles) Could not remove \"{}\" from the inventory of {}: {}.", entry.item_name, entry.user_id, err), LogErrorLevel::ERROR, String::from(""), true).await;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use serenity::client::Context;
+use serenity::model::application::command::{Command, CommandOptionType};
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::application::interaction::{Interaction, InteractionResponseType};
+
+use crate::utils::logging::db_log::{log_error, LogErrorLevel};
+use crate::utils::stock_utils::{format_quote_details, normalize_ticker};
+use crate::utils::symbol_search::{describe_match, format_disambiguation, lookup_quotes, suggest_tickers};
+
+/// Discord refuses longer autocompletion choice names
+const CHOICE_NAME_MAX_LENGTH: usize = 100;
+
+/// Registers the slash commands globally. Registering an existing command updates it.
+pub async fn register_slash_commands(ctx: &Context) -> () {
+    let created = Command::create_global_application_command(&ctx.http, |command| {
+        command
+            .name("stock")
+            .description("Get the price of a stock, crypto pair or company")
+            .create_option(|option| {
+                option
+                    .name("ticker")
+                    .description("Ticker or company name")
+                    .kind(CommandOptionType::String)
+                    .required(true)
+                    .set_autocomplete(true)
+            })
+    })
+    .await;
+
+    if let Err(err) = created {
+        log_error(format!("(slash commands) Could not register /stock: {}.", err), LogErrorLevel::ERROR, String::from(""), true).await;
+    }
+}
+
+/// Answers the slash commands and their autocompletion.
+pub async fn handle_interaction(ctx: &Context, interaction: Interaction) -> () {
+    match interaction {
+        Interaction::Autocomplete(autocomplete) if autocomplete.data.name == "stock" => {
+            autocomplete_ticker(ctx, &autocomplete).await;
+        }
+        Interaction::ApplicationCommand(command) if command.data.name == "stock" => {
+            stock_command(ctx, &command).await;
+        }
+        _ => {}
+    }
+}
+
+/// Suggests tickers matching what was typed so far, from the cached symbol search.
+async fn autocomplete_ticker(ctx: &Context, autocomplete: &AutocompleteInteraction) -> () {
+    let typed: String = autocomplete
+        .data
+        .options
+        .iter()
+        .find(|option| option.focused)
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let suggestions = suggest_tickers(&typed).await;
+    let answered = autocomplete
+        .create_autocomplete_response(&ctx.http, |response| {
+            for suggestion in suggestions.iter() {
+                let name: String = describe_match(suggestion).chars().take(CHOICE_NAME_MAX_LENGTH).collect();
+                response.add_string_choice(name, suggestion.ticker.clone());
+            }
+            response
+        })
+        .await;
+
+    if let Err(err) = answered {
+        println!("Could not answer the /stock autocompletion: {}", err);
+    }
+}
+
+async fn stock_command(ctx: &Context, command: &ApplicationCommandInteraction) -> () {
+    let query: String = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "ticker")
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    // A picked suggestion is a ticker, anything else typed is resolved like !stocks does
+    let content: String = match lookup_quotes(&[normalize_ticker(&query)], &query).await {
+        Ok(lookup) => match (lookup.quotes.first(), lookup.ambiguous.first()) {
+            (Some(quote), _) => format!("**${} - {}**\n{}", quote.ticker, quote.name, format_quote_details(quote)),
+            (None, Some((query, candidates))) => format_disambiguation(query, candidates),
+            (None, None) => format!("Could not find any stock named {}.", query),
+        },
+        Err(error) => error.to_string(),
+    };
+
+    let answered = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.content(content))
+        })
+        .await;
+
+    if let Err(err) = answered {
+        log_error(format!("(slash commands) Could not answer /stock: {}.", err), LogErrorLevel::ERROR, command.channel_id.to_string(), true).await;
+    }
+}
@@ -0,0 +1,75 @@
+use crate::utils::stock_utils::StockInfo;
+
+/// What a price alert watches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertCondition {
+    /// Trade price at or above the threshold
+    Above(f64),
+    /// Trade price at or below the threshold
+    Below(f64),
+    /// Day change percent beyond the threshold, in the threshold's direction
+    Change(f64),
+    /// Trade price at or above the 52-week high
+    FiftyTwoWeekHigh,
+}
+
+impl AlertCondition {
+    /// Parses `above 200`, `below 150.5`, `change -5%` or `high52`.
+    pub fn parse(arguments: &[&str]) -> Result<AlertCondition, String> {
+        let kind: String = arguments.get(0).map(|kind| kind.to_lowercase()).unwrap_or_default();
+        let threshold: Option<f64> = arguments
+            .get(1)
+            .and_then(|raw| raw.trim_end_matches('%').replace(',', ".").parse::<f64>().ok())
+            .filter(|threshold| threshold.is_finite());
+
+        match (kind.as_str(), threshold) {
+            ("above", Some(threshold)) => Ok(AlertCondition::Above(threshold)),
+            ("below", Some(threshold)) => Ok(AlertCondition::Below(threshold)),
+            ("change", Some(threshold)) if threshold != 0.0 => Ok(AlertCondition::Change(threshold)),
+            ("high52" | "52wh" | "52w-high", _) => Ok(AlertCondition::FiftyTwoWeekHigh),
+            _ => Err("Usage: !alert AAPL above 200, !alert AAPL below 150, !alert NVDA change -5% or !alert MSFT high52".to_string()),
+        }
+    }
+
+    /// Rebuilds a condition from its stored form (Discord::PriceAlert.condition and .threshold).
+    pub fn from_stored(condition: &str, threshold: Option<f64>) -> Option<AlertCondition> {
+        match (condition, threshold) {
+            ("above", Some(threshold)) => Some(AlertCondition::Above(threshold)),
+            ("below", Some(threshold)) => Some(AlertCondition::Below(threshold)),
+            ("change", Some(threshold)) => Some(AlertCondition::Change(threshold)),
+            ("high52", _) => Some(AlertCondition::FiftyTwoWeekHigh),
+            _ => None,
+        }
+    }
+
+    /// Stored form of the condition: its name and threshold.
+    pub fn to_stored(&self) -> (String, Option<f64>) {
+        match self {
+            AlertCondition::Above(threshold) => (String::from("above"), Some(*threshold)),
+            AlertCondition::Below(threshold) => (String::from("below"), Some(*threshold)),
+            AlertCondition::Change(threshold) => (String::from("change"), Some(*threshold)),
+            AlertCondition::FiftyTwoWeekHigh => (String::from("high52"), None),
+        }
+    }
+
+    pub fn is_met(&self, info: &StockInfo) -> bool {
+        match self {
+            AlertCondition::Above(threshold) => info.price >= *threshold,
+            AlertCondition::Below(threshold) => info.price <= *threshold,
+            AlertCondition::Change(threshold) if *threshold < 0.0 => info.regular_market_change_percent <= *threshold,
+            AlertCondition::Change(threshold) => info.regular_market_change_percent >= *threshold,
+            AlertCondition::FiftyTwoWeekHigh => info.fifty_two_week_high > 0.0 && info.price >= info.fifty_two_week_high,
+        }
+    }
+}
+
+impl std::fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertCondition::Above(threshold) => write!(f, "above ${:.2}", threshold),
+            AlertCondition::Below(threshold) => write!(f, "below ${:.2}", threshold),
+            AlertCondition::Change(threshold) => write!(f, "day change {:+.2}%", threshold),
+            AlertCondition::FiftyTwoWeekHigh => write!(f, "new 52-week high"),
+        }
+    }
+}
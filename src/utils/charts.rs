@@ -0,0 +1,192 @@
+use chrono::DateTime;
+use image::{ImageOutputFormat, RgbImage};
+use lazy_static::lazy_static;
+use plotters::prelude::*;
+use plotters::style::{register_font, FontStyle};
+use std::io::Cursor;
+
+use crate::datastructs::stocks_config::read_stocks_config;
+use crate::utils::quotes::Candle;
+
+const CHART_WIDTH: u32 = 1000;
+const CHART_HEIGHT: u32 = 560;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartStyle {
+    Line,
+    Candlestick,
+}
+
+/// A purchase to mark on the chart: when, and at which price (quote currency).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuyPoint {
+    pub timestamp: i64,
+    pub price: f64,
+}
+
+lazy_static! {
+    /// Whether a font could be registered. Without one, charts are drawn without any text.
+    static ref FONT_AVAILABLE: bool = register_chart_font();
+}
+
+fn register_chart_font() -> bool {
+    let path: String = read_stocks_config().chart_font_path;
+
+    match std::fs::read(&path) {
+        // The font has to live as long as the bot
+        Ok(bytes) => register_font("sans-serif", FontStyle::Normal, Box::leak(bytes.into_boxed_slice())).is_ok(),
+        Err(err) => {
+            println!("Could not read the chart font {}, charts will have no labels: {}", path, err);
+            false
+        }
+    }
+}
+
+fn format_timestamp(timestamp: &i64, with_time: bool) -> String {
+    match DateTime::from_timestamp(*timestamp, 0) {
+        Some(date) if with_time => date.format("%m-%d %H:%M").to_string(),
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => String::new(),
+    }
+}
+
+/// Renders the price history as a PNG image, in memory.
+/// Purchases are marked with triangles and the average cost, if any, with a horizontal line.
+pub fn render_price_chart(title: &str, candles: &[Candle], buy_points: &[BuyPoint], average_cost: Option<f64>, style: ChartStyle) -> Result<Vec<u8>, String> {
+    if candles.is_empty() {
+        return Err("No price history to draw.".to_string());
+    }
+
+    let first: i64 = candles.first().map(|candle| candle.timestamp).unwrap_or(0);
+    let last: i64 = candles.last().map(|candle| candle.timestamp).unwrap_or(0);
+    let in_range: Vec<&BuyPoint> = buy_points.iter().filter(|point| point.timestamp >= first && point.timestamp <= last).collect();
+
+    let mut low: f64 = candles.iter().map(|candle| candle.low).fold(f64::INFINITY, f64::min);
+    let mut high: f64 = candles.iter().map(|candle| candle.high).fold(f64::NEG_INFINITY, f64::max);
+    for price in in_range.iter().map(|point| point.price).chain(average_cost) {
+        low = low.min(price);
+        high = high.max(price);
+    }
+    let margin: f64 = ((high - low) * 0.05).max(0.01);
+    let with_time: bool = last - first < 60 * 60 * 24 * 7;
+
+    let mut buffer: Vec<u8> = vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|err| err.to_string())?;
+
+        let mut builder = ChartBuilder::on(&root);
+        builder.margin(15);
+        if *FONT_AVAILABLE {
+            builder
+                .caption(title, ("sans-serif", 24))
+                .x_label_area_size(35)
+                .y_label_area_size(60);
+        }
+        let mut chart = builder
+            .build_cartesian_2d(first..last.max(first + 1), (low - margin)..(high + margin))
+            .map_err(|err| err.to_string())?;
+
+        // Axis labels need a font, so the mesh is only drawn when one could be registered
+        if *FONT_AVAILABLE {
+            let label_formatter = |timestamp: &i64| format_timestamp(timestamp, with_time);
+            chart
+                .configure_mesh()
+                .light_line_style(WHITE.mix(0.0))
+                .x_labels(6)
+                .y_labels(8)
+                .x_label_formatter(&label_formatter)
+                .draw()
+                .map_err(|err| err.to_string())?;
+        }
+
+        match style {
+            ChartStyle::Line => {
+                chart
+                    .draw_series(LineSeries::new(candles.iter().map(|candle| (candle.timestamp, candle.close)), BLUE.stroke_width(2)))
+                    .map_err(|err| err.to_string())?;
+            }
+            ChartStyle::Candlestick => {
+                let candle_width: u32 = ((CHART_WIDTH as f64 * 0.8 / candles.len() as f64) as u32).clamp(1, 15);
+                chart
+                    .draw_series(candles.iter().map(|candle| {
+                        CandleStick::new(candle.timestamp, candle.open, candle.high, candle.low, candle.close, GREEN.filled(), RED.filled(), candle_width)
+                    }))
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+
+        chart
+            .draw_series(in_range.iter().map(|point| TriangleMarker::new((point.timestamp, point.price), 8, MAGENTA.filled())))
+            .map_err(|err| err.to_string())?;
+
+        if let Some(cost) = average_cost {
+            chart
+                .draw_series(LineSeries::new(vec![(first, cost), (last, cost)], MAGENTA.mix(0.6).stroke_width(1)))
+                .map_err(|err| err.to_string())?;
+        }
+
+        root.present().map_err(|err| err.to_string())?;
+    }
+
+    encode_png(buffer)
+}
+
+/// Renders a series of values (for example a net worth per day) as a PNG line chart, in memory.
+pub fn render_value_chart(title: &str, values: &[(i64, f64)]) -> Result<Vec<u8>, String> {
+    if values.is_empty() {
+        return Err("No value to draw.".to_string());
+    }
+
+    let first: i64 = values.first().map(|(timestamp, _)| *timestamp).unwrap_or(0);
+    let last: i64 = values.last().map(|(timestamp, _)| *timestamp).unwrap_or(0);
+    let low: f64 = values.iter().map(|(_, value)| *value).fold(f64::INFINITY, f64::min);
+    let high: f64 = values.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+    let margin: f64 = ((high - low) * 0.05).max(0.01);
+
+    let mut buffer: Vec<u8> = vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (CHART_WIDTH, CHART_HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|err| err.to_string())?;
+
+        let mut builder = ChartBuilder::on(&root);
+        builder.margin(15);
+        if *FONT_AVAILABLE {
+            builder
+                .caption(title, ("sans-serif", 24))
+                .x_label_area_size(35)
+                .y_label_area_size(70);
+        }
+        let mut chart = builder
+            .build_cartesian_2d(first..last.max(first + 1), (low - margin)..(high + margin))
+            .map_err(|err| err.to_string())?;
+
+        if *FONT_AVAILABLE {
+            let label_formatter = |timestamp: &i64| format_timestamp(timestamp, false);
+            chart
+                .configure_mesh()
+                .light_line_style(WHITE.mix(0.0))
+                .x_labels(6)
+                .y_labels(8)
+                .x_label_formatter(&label_formatter)
+                .draw()
+                .map_err(|err| err.to_string())?;
+        }
+
+        chart
+            .draw_series(LineSeries::new(values.iter().copied(), BLUE.stroke_width(2)))
+            .map_err(|err| err.to_string())?;
+
+        root.present().map_err(|err| err.to_string())?;
+    }
+
+    encode_png(buffer)
+}
+
+fn encode_png(buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+    let image: RgbImage = RgbImage::from_raw(CHART_WIDTH, CHART_HEIGHT, buffer).ok_or("Could not build the chart image.".to_string())?;
+    let mut png: Cursor<Vec<u8>> = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageOutputFormat::Png).map_err(|err| err.to_string())?;
+
+    Ok(png.into_inner())
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::persistence::edge_models::{PortfolioUser, WatchlistEntry};
+use crate::utils::quotes::QuoteError;
+use crate::utils::stock_utils::StockInfo;
+
+/// Next earnings call of a ticker, as reported with its quote.
+#[derive(Debug, Clone)]
+pub struct EarningsCall {
+    pub ticker: String,
+    pub name: String,
+    /// Epoch seconds
+    pub start: i64,
+    /// End of the announced window (epoch seconds), when the exact date is not confirmed yet
+    pub end: Option<i64>,
+}
+
+impl EarningsCall {
+    /// Yahoo reports a window (start and end) until the company confirms the date, then both are the same.
+    /// Quotes without any earnings date (crypto, funds) have none.
+    pub fn from_quote(info: &StockInfo) -> Option<Self> {
+        let start: i64 = if info.earnings_timestamp_start > 0 {
+            info.earnings_timestamp_start
+        } else {
+            info.earning_call_date
+        };
+        if start <= 0 {
+            return None;
+        }
+
+        Some(EarningsCall {
+            ticker: info.ticker.clone(),
+            name: if info.name.is_empty() { info.short_name.clone() } else { info.name.clone() },
+            start,
+            end: Some(info.earnings_timestamp_end).filter(|end| *end > start),
+        })
+    }
+
+    /// Whether the call starts between `now` and `days` days from now.
+    pub fn is_within(&self, now: DateTime<Utc>, days: i64) -> bool {
+        let now: i64 = now.timestamp();
+        self.start >= now && self.start <= now + Duration::days(days).num_seconds()
+    }
+
+    /// "Thu 23 Oct 22:00", or the window when the date is not confirmed yet.
+    pub fn describe(&self, timezone: &Tz) -> String {
+        let format_epoch = |epoch: i64, format: &str| -> String {
+            match timezone.timestamp_opt(epoch, 0).single() {
+                Some(date) => date.format(format).to_string(),
+                None => String::from("?"),
+            }
+        };
+
+        match self.end {
+            Some(end) => format!("between {} and {} (not confirmed)", format_epoch(self.start, "%a %d %b"), format_epoch(end, "%a %d %b")),
+            None => format_epoch(self.start, "%a %d %b %H:%M"),
+        }
+    }
+}
+
+/// Users following each ticker: the ones holding it (long or short) and the ones watching it.
+pub fn followers_by_ticker(users: &[PortfolioUser], watchlist: &[WatchlistEntry]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut followers: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for user in users.iter() {
+        let held = user
+            .portfolio
+            .iter()
+            .flat_map(|portfolio| portfolio.lines.iter().map(|line| line.ticker.clone()))
+            .chain(user.shorts.iter().map(|lot| lot.ticker.clone()));
+        for ticker in held {
+            followers.entry(ticker).or_default().insert(user.unique_id.clone());
+        }
+    }
+    for entry in watchlist.iter() {
+        followers.entry(entry.ticker.clone()).or_default().insert(entry.user_id.clone());
+    }
+
+    followers
+}
+
+/// Earnings calls starting in the next `days` days, soonest first.
+pub fn upcoming_calls(quotes: &HashMap<String, Result<StockInfo, QuoteError>>, now: DateTime<Utc>, days: i64) -> Vec<EarningsCall> {
+    let mut calls: Vec<EarningsCall> = quotes
+        .values()
+        .filter_map(|quote| quote.as_ref().ok())
+        .filter_map(EarningsCall::from_quote)
+        .filter(|call| call.is_within(now, days))
+        .collect();
+    calls.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.ticker.cmp(&b.ticker)));
+
+    calls
+}
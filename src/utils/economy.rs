@@ -0,0 +1,69 @@
+use crate::constants::economy::{
+    DAILY_AMOUNT, DAILY_COOLDOWN_HOURS, DAILY_MAX_STREAK_BONUS, DAILY_STREAK_BONUS, DAILY_STREAK_HOURS,
+};
+
+/// Outcome of a !daily claim, computed from the previous claim.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DailyClaim {
+    Claimable { amount: f64, streak: i64 },
+    /// Epoch seconds at which the next claim is possible
+    TooEarly { next_claim: f64 },
+}
+
+/// What a claim made at `now` is worth. The streak goes on when the previous claim is less than
+/// DAILY_STREAK_HOURS old, and starts over otherwise.
+pub fn daily_claim(last_claim: Option<f64>, streak: i64, now: f64) -> DailyClaim {
+    let hour: f64 = 60.0 * 60.0;
+
+    let streak: i64 = match last_claim {
+        Some(last_claim) if now - last_claim < DAILY_COOLDOWN_HOURS as f64 * hour => {
+            return DailyClaim::TooEarly {
+                next_claim: last_claim + DAILY_COOLDOWN_HOURS as f64 * hour,
+            };
+        },
+        Some(last_claim) if now - last_claim < DAILY_STREAK_HOURS as f64 * hour => streak + 1,
+        _ => 1,
+    };
+
+    DailyClaim::Claimable {
+        amount: daily_amount(streak),
+        streak,
+    }
+}
+
+/// The first day of a streak earns DAILY_AMOUNT, every following day DAILY_STREAK_BONUS more, up to a cap.
+pub fn daily_amount(streak: i64) -> f64 {
+    let bonus_days: i64 = (streak - 1).clamp(0, DAILY_MAX_STREAK_BONUS);
+
+    DAILY_AMOUNT + DAILY_STREAK_BONUS * bonus_days as f64
+}
+
+/// A positive amount of money, rounded to the cent. "12,5" and "$12.5" are accepted.
+pub fn parse_amount(raw: &str) -> Option<f64> {
+    match raw.trim().trim_start_matches('$').replace(',', ".").parse::<f64>() {
+        Ok(amount) if amount.is_finite() => {
+            let amount: f64 = (amount * 100.0).round() / 100.0;
+            Some(amount).filter(|amount| *amount > 0.0)
+        },
+        _ => None,
+    }
+}
+
+/// Like parse_amount, but 0 is accepted too: the money an account can be set to.
+pub fn parse_balance(raw: &str) -> Option<f64> {
+    match raw.trim().trim_start_matches('$').replace(',', ".").parse::<f64>() {
+        Ok(balance) if balance.is_finite() && balance >= 0.0 => Some((balance * 100.0).round() / 100.0),
+        _ => None,
+    }
+}
+
+/// "in 3h 20m", for the next claim.
+pub fn format_wait(seconds: f64) -> String {
+    let minutes: i64 = (seconds / 60.0).ceil().max(1.0) as i64;
+
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("in {}m", minutes),
+        (hours, 0) => format!("in {}h", hours),
+        (hours, minutes) => format!("in {}h {}m", hours, minutes),
+    }
+}
@@ -0,0 +1,126 @@
+//! Exchange rates, used to convert quotes into the account currency.
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serenity::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::constants::trading::ACCOUNT_CURRENCY;
+use crate::datastructs::stocks_config::read_stocks_config;
+use crate::utils::quotes::{quote_provider, QuoteError};
+
+#[async_trait]
+pub trait FxRateProvider: Send + Sync {
+    /// How many units of `to` one unit of `from` is worth.
+    async fn get_rate(&self, from: &str, to: &str) -> Result<f64, QuoteError>;
+}
+
+/// Live rates, read from the quote provider as currency pairs ("USDEUR=X").
+pub struct QuoteFxRateProvider;
+
+#[async_trait]
+impl FxRateProvider for QuoteFxRateProvider {
+    async fn get_rate(&self, from: &str, to: &str) -> Result<f64, QuoteError> {
+        let pair: String = format!("{}{}=X", from, to);
+        let quotes = quote_provider().get_quotes(&[pair.clone()]).await?;
+
+        quotes
+            .into_iter()
+            .next()
+            .map(|quote| quote.price)
+            .filter(|rate| *rate > 0.0)
+            .ok_or(QuoteError::NotFound(pair))
+    }
+}
+
+#[derive(Deserialize)]
+struct FxRatesFixture {
+    base: String,
+    /// Value of one unit of each currency, in the base currency
+    rates: HashMap<String, f64>,
+}
+
+/// Static rates read from a file, see data/fixtures/fx_rates.json.
+pub struct FixtureFxRateProvider {
+    rates: Result<FxRatesFixture, QuoteError>,
+}
+
+impl FixtureFxRateProvider {
+    pub fn from_file(path: &str) -> Self {
+        let rates = std::fs::read_to_string(path)
+            .map_err(|err| QuoteError::Unavailable(format!("{}: {}", path, err)))
+            .and_then(|content| serde_json::from_str::<FxRatesFixture>(&content).map_err(|err| QuoteError::Parse(err.to_string())));
+
+        if let Err(err) = rates.as_ref() {
+            println!("Could not load the exchange rates fixture {}: {:?}", path, err);
+        }
+
+        FixtureFxRateProvider { rates }
+    }
+}
+
+#[async_trait]
+impl FxRateProvider for FixtureFxRateProvider {
+    async fn get_rate(&self, from: &str, to: &str) -> Result<f64, QuoteError> {
+        let fixture = self.rates.as_ref().map_err(|err| err.clone())?;
+        let in_base = |currency: &str| -> Option<f64> {
+            if currency == fixture.base {
+                Some(1.0)
+            } else {
+                fixture.rates.get(currency).copied().filter(|rate| *rate > 0.0)
+            }
+        };
+
+        match (in_base(from), in_base(to)) {
+            (Some(from_rate), Some(to_rate)) => Ok(from_rate / to_rate),
+            _ => Err(QuoteError::NotFound(format!("{}{}", from, to))),
+        }
+    }
+}
+
+/// Asks `primary` first, and `fallback` when it fails.
+pub struct FallbackFxRateProvider {
+    primary: Box<dyn FxRateProvider>,
+    fallback: Box<dyn FxRateProvider>,
+}
+
+#[async_trait]
+impl FxRateProvider for FallbackFxRateProvider {
+    async fn get_rate(&self, from: &str, to: &str) -> Result<f64, QuoteError> {
+        match self.primary.get_rate(from, to).await {
+            Ok(rate) => Ok(rate),
+            Err(_) => self.fallback.get_rate(from, to).await,
+        }
+    }
+}
+
+lazy_static! {
+    static ref FX_RATE_PROVIDER: Arc<dyn FxRateProvider> = Arc::new(FallbackFxRateProvider {
+        primary: Box::new(QuoteFxRateProvider),
+        fallback: Box::new(FixtureFxRateProvider::from_file(&read_stocks_config().fx_fixture_path)),
+    });
+}
+
+/// Splits quote currencies expressed in subunits (GBp, ZAc, ILA) into their main currency and a factor.
+pub fn normalize_currency(currency: &str) -> (String, f64) {
+    match currency {
+        "GBp" | "GBX" => (String::from("GBP"), 0.01),
+        "ZAc" => (String::from("ZAR"), 0.01),
+        "ILA" => (String::from("ILS"), 0.01),
+        other => (other.to_uppercase(), 1.0),
+    }
+}
+
+/// How many units of the account currency one unit of `currency` (as found in StockInfo.currency) is worth.
+///
+/// An empty currency is considered to already be the account currency.
+pub async fn get_fx_rate(currency: &str) -> Result<f64, QuoteError> {
+    let (currency, factor) = normalize_currency(currency);
+
+    if currency.is_empty() || currency == ACCOUNT_CURRENCY {
+        return Ok(factor);
+    }
+
+    let rate: f64 = FX_RATE_PROVIDER.get_rate(&currency, ACCOUNT_CURRENCY).await?;
+    Ok(rate * factor)
+}
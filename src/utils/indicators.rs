@@ -0,0 +1,406 @@
+/// Moving average windows shown by !ta, in sessions
+pub const SMA_WINDOWS: [usize; 3] = [20, 50, 200];
+pub const EMA_WINDOWS: [usize; 3] = [12, 26, 50];
+pub const RSI_PERIOD: usize = 14;
+pub const MACD_FAST: usize = 12;
+pub const MACD_SLOW: usize = 26;
+pub const MACD_SIGNAL: usize = 9;
+pub const BOLLINGER_WINDOW: usize = 20;
+pub const BOLLINGER_WIDTH: f64 = 2.0;
+/// Crossovers older than this many sessions are not worth mentioning
+pub const CROSSOVER_LOOKBACK: usize = 10;
+
+/// Every series below is aligned with the prices it is computed from: `None` until enough prices are known.
+pub type Series = Vec<Option<f64>>;
+
+/// Simple moving average over `window` prices.
+pub fn sma(values: &[f64], window: usize) -> Series {
+    if window == 0 {
+        return vec![None; values.len()];
+    }
+
+    let mut series: Series = Vec::with_capacity(values.len());
+    let mut sum: f64 = 0.0;
+
+    for (position, value) in values.iter().enumerate() {
+        sum += value;
+        if position >= window {
+            sum -= values[position - window];
+        }
+        series.push(if position + 1 >= window { Some(sum / window as f64) } else { None });
+    }
+
+    series
+}
+
+/// Exponential moving average, seeded with the simple average of the first `window` prices.
+pub fn ema(values: &[f64], window: usize) -> Series {
+    let mut series: Series = vec![None; values.len()];
+    if window == 0 || values.len() < window {
+        return series;
+    }
+
+    let alpha: f64 = 2.0 / (window as f64 + 1.0);
+    let mut current: f64 = values[..window].iter().sum::<f64>() / window as f64;
+    series[window - 1] = Some(current);
+
+    for (position, value) in values.iter().enumerate().skip(window) {
+        current += alpha * (value - current);
+        series[position] = Some(current);
+    }
+
+    series
+}
+
+/// EMA of a series which itself starts with missing values (the MACD line).
+fn ema_of_series(series: &Series, window: usize) -> Series {
+    let first: usize = match series.iter().position(|value| value.is_some()) {
+        Some(first) => first,
+        None => return vec![None; series.len()],
+    };
+    let known: Vec<f64> = series[first..].iter().map(|value| value.unwrap_or(0.0)).collect();
+
+    let mut result: Series = vec![None; first];
+    result.extend(ema(&known, window));
+    result
+}
+
+/// Relative strength index (0-100) with Wilder's smoothing.
+pub fn rsi(values: &[f64], period: usize) -> Series {
+    let mut series: Series = vec![None; values.len()];
+    if period == 0 || values.len() <= period {
+        return series;
+    }
+
+    let changes: Vec<f64> = values.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let mut gain: f64 = changes[..period].iter().filter(|change| **change > 0.0).sum::<f64>() / period as f64;
+    let mut loss: f64 = -changes[..period].iter().filter(|change| **change < 0.0).sum::<f64>() / period as f64;
+    series[period] = Some(rsi_value(gain, loss));
+
+    for (position, change) in changes.iter().enumerate().skip(period) {
+        gain = (gain * (period as f64 - 1.0) + change.max(0.0)) / period as f64;
+        loss = (loss * (period as f64 - 1.0) + (-change).max(0.0)) / period as f64;
+        series[position + 1] = Some(rsi_value(gain, loss));
+    }
+
+    series
+}
+
+fn rsi_value(gain: f64, loss: f64) -> f64 {
+    if loss == 0.0 {
+        // Flat prices are neutral, only gains are as strong as it gets
+        return if gain == 0.0 { 50.0 } else { 100.0 };
+    }
+
+    100.0 - 100.0 / (1.0 + gain / loss)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdPoint {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// MACD line (fast EMA - slow EMA), its signal line (EMA of the MACD line) and their difference.
+pub fn macd(values: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<Option<MacdPoint>> {
+    let fast_ema: Series = ema(values, fast);
+    let slow_ema: Series = ema(values, slow);
+    let line: Series = fast_ema.iter().zip(slow_ema.iter()).map(|(fast, slow)| Some((*fast)? - (*slow)?)).collect();
+    let signal_line: Series = ema_of_series(&line, signal);
+
+    line.iter()
+        .zip(signal_line.iter())
+        .map(|(macd, signal)| {
+            let (macd, signal) = ((*macd)?, (*signal)?);
+            Some(MacdPoint { macd, signal, histogram: macd - signal })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BollingerBand {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+/// Simple moving average plus and minus `width` standard deviations of the same window.
+pub fn bollinger(values: &[f64], window: usize, width: f64) -> Vec<Option<BollingerBand>> {
+    sma(values, window)
+        .iter()
+        .enumerate()
+        .map(|(position, middle)| {
+            let middle: f64 = (*middle)?;
+            let prices: &[f64] = &values[position + 1 - window..=position];
+            let variance: f64 = prices.iter().map(|price| (price - middle).powi(2)).sum::<f64>() / window as f64;
+            let deviation: f64 = variance.sqrt() * width;
+
+            Some(BollingerBand { lower: middle - deviation, middle, upper: middle + deviation })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossDirection {
+    /// The fast series went above the slow one
+    Up,
+    /// The fast series went below the slow one
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossover {
+    pub direction: CrossDirection,
+    /// 0 when it happened on the last session
+    pub sessions_ago: usize,
+}
+
+/// Most recent point where `fast` crossed `slow`, looking back at most `lookback` sessions.
+pub fn last_crossover(fast: &Series, slow: &Series, lookback: usize) -> Option<Crossover> {
+    let length: usize = fast.len().min(slow.len());
+    let first: usize = length.saturating_sub(lookback + 1);
+
+    (first.max(1)..length).rev().find_map(|position| {
+        let before: f64 = fast[position - 1]? - slow[position - 1]?;
+        let after: f64 = fast[position]? - slow[position]?;
+        let direction: CrossDirection = if before <= 0.0 && after > 0.0 {
+            CrossDirection::Up
+        } else if before >= 0.0 && after < 0.0 {
+            CrossDirection::Down
+        } else {
+            return None;
+        };
+
+        Some(Crossover { direction, sessions_ago: length - 1 - position })
+    })
+}
+
+/// Latest value of every indicator, along with a plain reading of them.
+#[derive(Debug, Clone)]
+pub struct TechnicalSummary {
+    pub price: f64,
+    pub sessions: usize,
+    /// (window, latest value), for SMA_WINDOWS and EMA_WINDOWS
+    pub smas: Vec<(usize, Option<f64>)>,
+    pub emas: Vec<(usize, Option<f64>)>,
+    pub rsi: Option<f64>,
+    pub macd: Option<MacdPoint>,
+    pub bollinger: Option<BollingerBand>,
+    pub signals: Vec<String>,
+}
+
+fn latest<T: Copy>(series: &[Option<T>]) -> Option<T> {
+    series.last().copied().flatten()
+}
+
+fn describe_crossover(crossover: &Crossover, up: &str, down: &str) -> String {
+    let what: &str = match crossover.direction {
+        CrossDirection::Up => up,
+        CrossDirection::Down => down,
+    };
+
+    match crossover.sessions_ago {
+        0 => format!("{} on the last session.", what),
+        1 => format!("{} 1 session ago.", what),
+        sessions => format!("{} {} sessions ago.", what, sessions),
+    }
+}
+
+/// Computes the indicators from daily closing prices, oldest first. None without any price.
+pub fn summarize(closes: &[f64]) -> Option<TechnicalSummary> {
+    let price: f64 = *closes.last()?;
+    let mut signals: Vec<String> = vec![];
+
+    let sma_series: Vec<(usize, Series)> = SMA_WINDOWS.iter().map(|window| (*window, sma(closes, *window))).collect();
+    let ema_series: Vec<(usize, Series)> = EMA_WINDOWS.iter().map(|window| (*window, ema(closes, *window))).collect();
+    let rsi_series: Series = rsi(closes, RSI_PERIOD);
+    let macd_series: Vec<Option<MacdPoint>> = macd(closes, MACD_FAST, MACD_SLOW, MACD_SIGNAL);
+    let bollinger_series: Vec<Option<BollingerBand>> = bollinger(closes, BOLLINGER_WINDOW, BOLLINGER_WIDTH);
+
+    // Golden and death crosses of the 50 and 200 session averages
+    let sma_50: Option<&Series> = sma_series.iter().find(|(window, _)| *window == 50).map(|(_, series)| series);
+    let sma_200: Option<&Series> = sma_series.iter().find(|(window, _)| *window == 200).map(|(_, series)| series);
+    if let (Some(sma_50), Some(sma_200)) = (sma_50, sma_200) {
+        if let Some(crossover) = last_crossover(sma_50, sma_200, CROSSOVER_LOOKBACK) {
+            signals.push(describe_crossover(&crossover, "Golden cross (SMA 50 above SMA 200)", "Death cross (SMA 50 below SMA 200)"));
+        }
+        if let Some(average) = latest(sma_200) {
+            signals.push(format!("The price is {} its 200 session average.", if price >= average { "above" } else { "below" }));
+        }
+    }
+
+    // Price crossing its 50 session average
+    if let Some(sma_50) = sma_50 {
+        let prices: Series = closes.iter().map(|close| Some(*close)).collect();
+        if let Some(crossover) = last_crossover(&prices, sma_50, CROSSOVER_LOOKBACK) {
+            signals.push(describe_crossover(&crossover, "Price crossed above its SMA 50", "Price crossed below its SMA 50"));
+        }
+    }
+
+    let macd_lines: Series = macd_series.iter().map(|point| point.map(|point| point.macd)).collect();
+    let signal_lines: Series = macd_series.iter().map(|point| point.map(|point| point.signal)).collect();
+    if let Some(crossover) = last_crossover(&macd_lines, &signal_lines, CROSSOVER_LOOKBACK) {
+        signals.push(describe_crossover(&crossover, "MACD crossed above its signal line (bullish)", "MACD crossed below its signal line (bearish)"));
+    }
+
+    let rsi: Option<f64> = latest(&rsi_series);
+    match rsi {
+        Some(rsi) if rsi >= 70.0 => signals.push(format!("RSI {:.0}: overbought.", rsi)),
+        Some(rsi) if rsi <= 30.0 => signals.push(format!("RSI {:.0}: oversold.", rsi)),
+        _ => {},
+    }
+
+    let band: Option<BollingerBand> = latest(&bollinger_series);
+    match band {
+        Some(band) if price > band.upper => signals.push(String::from("The price is above the upper Bollinger band.")),
+        Some(band) if price < band.lower => signals.push(String::from("The price is below the lower Bollinger band.")),
+        _ => {},
+    }
+
+    Some(TechnicalSummary {
+        price,
+        sessions: closes.len(),
+        smas: sma_series.iter().map(|(window, series)| (*window, latest(series))).collect(),
+        emas: ema_series.iter().map(|(window, series)| (*window, latest(series))).collect(),
+        rsi,
+        macd: latest(&macd_series),
+        bollinger: band,
+        signals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-6;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual: f64 = actual.unwrap_or_else(|| panic!("expected {}, got None", expected));
+        assert!((actual - expected).abs() < TOLERANCE, "expected {}, got {}", expected, actual);
+    }
+
+    /// Closes of Wilder's RSI example, as worked through by StockCharts
+    const WILDER_CLOSES: [f64; 19] = [
+        44.3389, 44.0902, 44.1497, 43.6124, 44.3278, 44.8264, 45.0955, 45.4245, 45.8433, 46.0826,
+        45.8931, 46.0328, 45.6140, 46.2820, 46.2820, 46.0028, 46.0328, 46.4116, 46.2222,
+    ];
+
+    #[test]
+    fn sma_averages_the_window() {
+        assert_eq!(sma(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+    }
+
+    #[test]
+    fn sma_of_too_few_prices_is_unknown() {
+        assert_eq!(sma(&[1.0, 2.0], 3), vec![None, None]);
+        assert_eq!(sma(&[1.0, 2.0], 0), vec![None, None]);
+        assert!(sma(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn ema_is_seeded_with_the_simple_average() {
+        let series: Series = ema(&[1.0, 3.0, 2.0, 5.0, 4.0, 6.0], 3);
+
+        assert_eq!(series[..2], [None, None]);
+        assert_close(series[2], 2.0);
+        assert_close(series[3], 3.5);
+        assert_close(series[4], 3.75);
+        assert_close(series[5], 4.875);
+    }
+
+    #[test]
+    fn ema_of_too_few_prices_is_unknown() {
+        assert_eq!(ema(&[1.0, 2.0], 3), vec![None, None]);
+        assert_eq!(ema(&[1.0, 2.0], 0), vec![None, None]);
+    }
+
+    #[test]
+    fn rsi_matches_wilder_reference() {
+        let series: Series = rsi(&WILDER_CLOSES, 14);
+
+        assert!(series[..14].iter().all(|value| value.is_none()));
+        for (position, expected) in [70.532789, 66.318562, 66.549830, 69.406305, 66.355169].iter().enumerate() {
+            let actual: f64 = series[14 + position].unwrap();
+            assert!((actual - expected).abs() < 1e-4, "RSI {} expected {}, got {}", position, expected, actual);
+        }
+    }
+
+    #[test]
+    fn rsi_of_flat_or_rising_prices() {
+        assert_close(latest(&rsi(&[10.0; 5], 3)), 50.0);
+        assert_close(latest(&rsi(&[1.0, 2.0, 3.0, 4.0], 3)), 100.0);
+    }
+
+    #[test]
+    fn rsi_needs_one_more_price_than_the_period() {
+        assert_eq!(rsi(&WILDER_CLOSES[..14], 14), vec![None; 14]);
+        assert!(rsi(&[], 14).is_empty());
+    }
+
+    #[test]
+    fn macd_is_the_difference_of_two_emas_and_its_signal() {
+        let series: Vec<Option<MacdPoint>> = macd(&[1.0, 3.0, 2.0, 5.0, 4.0, 6.0], 2, 3, 2);
+
+        assert!(series[..3].iter().all(|point| point.is_none()));
+        let third: MacdPoint = series[3].unwrap();
+        assert_close(Some(third.macd), 0.5);
+        assert_close(Some(third.signal), 0.25);
+        assert_close(Some(third.histogram), 0.25);
+        let last: MacdPoint = series[5].unwrap();
+        assert_close(Some(last.macd), 0.458_333_333);
+        assert_close(Some(last.signal), 0.388_888_889);
+        assert_close(Some(last.histogram), 0.069_444_444);
+    }
+
+    #[test]
+    fn macd_of_too_few_prices_is_unknown() {
+        assert_eq!(macd(&[1.0; 30], MACD_FAST, MACD_SLOW, MACD_SIGNAL), vec![None; 30]);
+    }
+
+    #[test]
+    fn bollinger_uses_the_population_deviation() {
+        let series: Vec<Option<BollingerBand>> = bollinger(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 8, 2.0);
+
+        assert!(series[..7].iter().all(|band| band.is_none()));
+        assert_eq!(series[7], Some(BollingerBand { lower: 1.0, middle: 5.0, upper: 9.0 }));
+    }
+
+    #[test]
+    fn bollinger_of_too_few_prices_is_unknown() {
+        assert_eq!(bollinger(&[1.0, 2.0], 3, 2.0), vec![None, None]);
+    }
+
+    #[test]
+    fn crossover_finds_the_latest_cross() {
+        let fast: Series = vec![Some(3.0), Some(1.0), Some(2.0), Some(3.0), Some(4.0)];
+        let slow: Series = vec![Some(2.5); 5];
+
+        assert_eq!(last_crossover(&fast, &slow, 10), Some(Crossover { direction: CrossDirection::Up, sessions_ago: 1 }));
+        assert_eq!(last_crossover(&fast, &slow, 0), None);
+
+        let falling: Series = fast.iter().map(|value| value.map(|value| 5.0 - value)).collect();
+        assert_eq!(last_crossover(&falling, &slow, 10), Some(Crossover { direction: CrossDirection::Down, sessions_ago: 1 }));
+    }
+
+    #[test]
+    fn crossover_skips_unknown_values() {
+        let fast: Series = vec![None, Some(1.0), Some(3.0)];
+        let slow: Series = vec![Some(2.0), None, Some(2.0)];
+
+        assert_eq!(last_crossover(&fast, &slow, 10), None);
+        assert_eq!(last_crossover(&Series::new(), &Series::new(), 10), None);
+    }
+
+    #[test]
+    fn summary_of_a_short_history() {
+        assert!(summarize(&[]).is_none());
+
+        let summary: TechnicalSummary = summarize(&[10.0, 11.0, 12.0]).unwrap();
+        assert_eq!(summary.price, 12.0);
+        assert_eq!(summary.sessions, 3);
+        assert!(summary.smas.iter().all(|(_, value)| value.is_none()));
+        assert!(summary.rsi.is_none() && summary.macd.is_none() && summary.bollinger.is_none());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::persistence::edge_models::{LedgerBalance, LedgerTransaction};
+
+/// One human readable line per ledger entry, for chat replies.
+pub fn format_transaction_line(transaction: &LedgerTransaction) -> String {
+    let mut line: String = format!("{} | {}", transaction.created_local, transaction.kind);
+
+    if let (Some(quantity), Some(ticker)) = (transaction.quantity, transaction.ticker.as_ref()) {
+        line = format!("{} {} ${}", line, quantity, ticker);
+    }
+    if let Some(price) = transaction.price {
+        line = format!("{} @ {:.2} {}", line, price, transaction.currency.clone().unwrap_or_default());
+        if let Some(fx_rate) = transaction.fx_rate.filter(|fx_rate| *fx_rate != 1.0) {
+            line = format!("{} (x{:.4})", line, fx_rate);
+        }
+    }
+    line = format!("{} | {:+.2}", line, transaction.amount);
+    if transaction.fees > 0.0 {
+        line = format!("{} (fees {:.2})", line, transaction.fees);
+    }
+    line = format!("{} | balance €{:.2}", line, transaction.balance_after);
+    if let Some(note) = transaction.note.as_ref() {
+        line = format!("{} | {}", line, note);
+    }
+
+    line
+}
+
+/// Exports ledger entries as CSV, in the given order.
+pub fn transactions_to_csv(transactions: &[LedgerTransaction]) -> String {
+    let mut csv: String = String::from("date,kind,ticker,quantity,price,currency,fx_rate,amount,fees,balance_after,counterparty,note\n");
+
+    for transaction in transactions.iter() {
+        let fields: Vec<String> = vec![
+            transaction.created_local.to_string(),
+            transaction.kind.clone(),
+            transaction.ticker.clone().unwrap_or_default(),
+            transaction.quantity.map(|quantity| quantity.to_string()).unwrap_or_default(),
+            transaction.price.map(|price| price.to_string()).unwrap_or_default(),
+            transaction.currency.clone().unwrap_or_default(),
+            transaction.fx_rate.map(|fx_rate| fx_rate.to_string()).unwrap_or_default(),
+            transaction.amount.to_string(),
+            transaction.fees.to_string(),
+            transaction.balance_after.to_string(),
+            transaction.counterparty_id.clone().unwrap_or_default(),
+            transaction.note.clone().unwrap_or_default(),
+        ];
+        let escaped: Vec<String> = fields.iter().map(|field| escape_csv_field(field)).collect();
+        csv.push_str(&escaped.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Differences under a cent are rounding noise
+const RECONCILIATION_TOLERANCE: f64 = 0.005;
+
+/// A user whose money does not match their ledger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceMismatch {
+    pub unique_id: String,
+    pub display_name: String,
+    pub money: f64,
+    /// Opening balance plus every ledger movement since
+    pub expected: f64,
+    /// Balance recorded with the latest entry, None without any entry
+    pub last_balance: Option<f64>,
+}
+
+impl BalanceMismatch {
+    pub fn difference(&self) -> f64 {
+        self.money - self.expected
+    }
+}
+
+/// Recomputes every balance from the ledger, which starts with an opening entry, and keeps the ones which differ
+/// from the stored money.
+pub fn find_mismatches(balances: &[LedgerBalance]) -> Vec<BalanceMismatch> {
+    balances
+        .iter()
+        .map(|balance| BalanceMismatch {
+            unique_id: balance.unique_id.clone(),
+            display_name: balance.display_name.clone(),
+            money: balance.money,
+            expected: balance.ledger_sum,
+            last_balance: balance.last_balance,
+        })
+        .filter(|mismatch| {
+            mismatch.difference().abs() > RECONCILIATION_TOLERANCE
+                || matches!(mismatch.last_balance, Some(last) if (mismatch.money - last).abs() > RECONCILIATION_TOLERANCE)
+        })
+        .collect()
+}
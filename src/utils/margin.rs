@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::constants::trading::MARGIN_WARNING_BUFFER;
+use crate::persistence::edge::requests::read::get_guild_trading_settings;
+use crate::persistence::edge_models::{GuildTradingSettings, PortfolioUser};
+use crate::utils::portfolio::{value_positions, value_shorts, HeldPrice};
+
+/// Short selling and margin rules of a guild. Both are disabled unless an admin turns them on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarginRules {
+    pub short_selling: bool,
+    pub margin: bool,
+    /// Yearly rate charged on borrowed money and on the value of borrowed shares
+    pub interest_rate: f64,
+    /// Part of the exposure the equity must cover to open a position
+    pub initial_margin: f64,
+    /// Part of the exposure the equity must keep covering, below it positions are liquidated
+    pub maintenance_margin: f64,
+}
+
+impl Default for MarginRules {
+    fn default() -> Self {
+        MarginRules {
+            short_selling: false,
+            margin: false,
+            interest_rate: 0.08,
+            initial_margin: 0.5,
+            maintenance_margin: 0.3,
+        }
+    }
+}
+
+impl MarginRules {
+    /// These rules with the highest rate and requirements of both. What is allowed stays decided by these rules.
+    pub fn stricter(&self, other: &MarginRules) -> MarginRules {
+        MarginRules {
+            short_selling: self.short_selling,
+            margin: self.margin,
+            interest_rate: self.interest_rate.max(other.interest_rate),
+            initial_margin: self.initial_margin.max(other.initial_margin),
+            maintenance_margin: self.maintenance_margin.max(other.maintenance_margin),
+        }
+    }
+}
+
+impl From<GuildTradingSettings> for MarginRules {
+    fn from(settings: GuildTradingSettings) -> Self {
+        MarginRules {
+            short_selling: settings.short_selling,
+            margin: settings.margin,
+            interest_rate: settings.interest_rate,
+            initial_margin: settings.initial_margin,
+            maintenance_margin: settings.maintenance_margin,
+        }
+    }
+}
+
+/// Rules of the guild, the disabled defaults outside of guilds or when they cannot be read.
+pub async fn guild_margin_rules(guild_id: Option<String>) -> MarginRules {
+    match guild_id {
+        Some(guild_id) => match get_guild_trading_settings(guild_id).await {
+            Ok(Some(settings)) => settings.into(),
+            _ => MarginRules::default(),
+        },
+        None => MarginRules::default(),
+    }
+}
+
+/// Rules a new margin trade of the user in a guild must meet. Users already borrowing under the rules of another
+/// guild, which their margin calls keep applying, must meet the stricter of both.
+pub async fn trade_margin_rules(user: &PortfolioUser, guild_id: Option<String>) -> MarginRules {
+    let rules: MarginRules = guild_margin_rules(guild_id.clone()).await;
+    let borrowing: bool = user.money < 0.0 || !user.shorts.is_empty();
+
+    match user.margin_guild_id.clone() {
+        Some(margin_guild_id) if borrowing && Some(&margin_guild_id) != guild_id.as_ref() => {
+            rules.stricter(&guild_margin_rules(Some(margin_guild_id)).await)
+        }
+        _ => rules,
+    }
+}
+
+/// How healthy a margin account is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarginState {
+    Healthy,
+    /// Close to the maintenance requirement, the user should add money or reduce their positions
+    Warning,
+    /// Below the maintenance requirement, positions must be liquidated
+    Liquidation,
+}
+
+/// The amounts the margin rules are applied to, in the account currency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarginAccount {
+    /// Negative when money was borrowed
+    pub cash: f64,
+    pub long_value: f64,
+    /// Cost of buying back every shorted share
+    pub short_value: f64,
+}
+
+impl MarginAccount {
+    pub fn equity(&self) -> f64 {
+        self.cash + self.long_value - self.short_value
+    }
+
+    /// Everything the account is exposed to, long and short.
+    pub fn exposure(&self) -> f64 {
+        self.long_value + self.short_value
+    }
+
+    pub fn borrowed_money(&self) -> f64 {
+        (-self.cash).max(0.0)
+    }
+
+    /// Money and shares the account owes, interest is charged on it.
+    pub fn borrowed(&self) -> f64 {
+        self.borrowed_money() + self.short_value
+    }
+
+    pub fn initial_requirement(&self, rules: &MarginRules) -> f64 {
+        self.exposure() * rules.initial_margin
+    }
+
+    pub fn maintenance_requirement(&self, rules: &MarginRules) -> f64 {
+        self.exposure() * rules.maintenance_margin
+    }
+
+    /// How much exposure (long or short) can still be opened while meeting the initial requirement.
+    ///
+    /// Buying or shorting does not change the equity, only the exposure, so this is the equity over the
+    /// initial margin minus the current exposure.
+    pub fn available_exposure(&self, rules: &MarginRules) -> f64 {
+        if rules.initial_margin <= 0.0 {
+            return f64::INFINITY;
+        }
+        (self.equity() / rules.initial_margin - self.exposure()).max(0.0)
+    }
+
+    /// How far below zero the money may go on a purchase, 0 when margin buying is disabled.
+    pub fn credit_limit(&self, rules: &MarginRules) -> f64 {
+        if !rules.margin {
+            return 0.0;
+        }
+        (self.available_exposure(rules) - self.cash.max(0.0)).max(0.0)
+    }
+
+    /// Exposure to close so that the initial requirement is met again. Everything when the equity is gone.
+    pub fn excess_exposure(&self, rules: &MarginRules) -> f64 {
+        if rules.initial_margin <= 0.0 {
+            return 0.0;
+        }
+        if self.equity() <= 0.0 {
+            return self.exposure();
+        }
+        (self.exposure() - self.equity() / rules.initial_margin).max(0.0)
+    }
+
+    /// Only accounts borrowing money or shares can get a margin call.
+    pub fn state(&self, rules: &MarginRules) -> MarginState {
+        if self.borrowed() <= 0.0 {
+            return MarginState::Healthy;
+        }
+
+        let maintenance: f64 = self.maintenance_requirement(rules);
+        if self.equity() < maintenance {
+            MarginState::Liquidation
+        } else if self.equity() < maintenance * MARGIN_WARNING_BUFFER {
+            MarginState::Warning
+        } else {
+            MarginState::Healthy
+        }
+    }
+}
+
+/// Values the long and short positions of a user with the known prices.
+pub fn margin_account(user: &PortfolioUser, prices: &HashMap<String, HeldPrice>) -> MarginAccount {
+    let long_value: f64 = match user.portfolio.as_ref() {
+        Some(portfolio) => value_positions(&portfolio.lines, prices)
+            .iter()
+            .map(|valuation| valuation.market_value())
+            .sum(),
+        None => 0.0,
+    };
+    let short_value: f64 = value_shorts(&user.shorts, prices)
+        .iter()
+        .map(|short| short.market_value())
+        .sum();
+
+    MarginAccount {
+        cash: user.money,
+        long_value,
+        short_value,
+    }
+}
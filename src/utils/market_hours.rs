@@ -0,0 +1,75 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::utils::quotes::AssetClass;
+use crate::utils::stock_utils::StockInfo;
+
+/// Whether a ticker can be traded right now, and if not when its exchange opens again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarketStatus {
+    Open,
+    /// `next_open` is unknown when the exchange timezone cannot be read
+    Closed { next_open: Option<DateTime<Utc>> },
+}
+
+impl MarketStatus {
+    pub fn is_open(&self) -> bool {
+        matches!(self, MarketStatus::Open)
+    }
+}
+
+/// Opening time of the regular session of an exchange, in its own timezone. Unknown exchanges get the US hours.
+fn session_opening(exchange_timezone: &str) -> NaiveTime {
+    let (hour, minute) = match exchange_timezone {
+        "Europe/London" => (8, 0),
+        "Europe/Paris" | "Europe/Brussels" | "Europe/Amsterdam" | "Europe/Berlin" | "Europe/Madrid" | "Europe/Milan"
+        | "Europe/Zurich" | "Europe/Stockholm" | "Europe/Oslo" | "Europe/Copenhagen" | "Europe/Helsinki" => (9, 0),
+        "Asia/Tokyo" => (9, 0),
+        "Asia/Hong_Kong" | "Asia/Shanghai" => (9, 30),
+        "Australia/Sydney" => (10, 0),
+        _ => (9, 30),
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0).expect("Invalid session opening time")
+}
+
+/// Crypto trades around the clock, everything else follows the market state reported with the quote.
+pub fn market_status(info: &StockInfo, now: DateTime<Utc>) -> MarketStatus {
+    if info.asset_class() == AssetClass::Crypto || info.market_state == "REGULAR" {
+        return MarketStatus::Open;
+    }
+
+    MarketStatus::Closed {
+        next_open: next_open(&info.exchange_timezone_name, now),
+    }
+}
+
+/// Next weekday session opening after `now`. Public holidays are not known, so this may be a day early.
+pub fn next_open(exchange_timezone: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let timezone: Tz = exchange_timezone.parse().ok()?;
+    let open: NaiveTime = session_opening(exchange_timezone);
+    let local_now: DateTime<Tz> = now.with_timezone(&timezone);
+
+    // A week always contains a weekday
+    (0..8).find_map(|days| {
+        let day = local_now.date_naive() + Duration::days(days);
+        if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            return None;
+        }
+        let opening: DateTime<Tz> = timezone.from_local_datetime(&day.and_time(open)).earliest()?;
+        Some(opening.with_timezone(&Utc)).filter(|opening| *opening > now)
+    })
+}
+
+/// "open" or "closed, opens <date> (<timezone>)", in the reader's timezone.
+pub fn describe_market_status(status: &MarketStatus, timezone: &Tz) -> String {
+    match status {
+        MarketStatus::Open => String::from("open"),
+        MarketStatus::Closed { next_open: Some(next_open) } => format!(
+            "closed, opens {} ({})",
+            next_open.with_timezone(timezone).format("%a %Y-%m-%d %H:%M"),
+            timezone.name()
+        ),
+        MarketStatus::Closed { next_open: None } => String::from("closed"),
+    }
+}
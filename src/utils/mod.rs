@@ -1,8 +1,25 @@
+pub mod alerts;
 pub mod apis;
 pub mod bot_reply;
+pub mod charts;
+pub mod earnings;
+pub mod economy;
+pub mod fx;
+pub mod indicators;
+pub mod ledger;
+pub mod margin;
+pub mod market_hours;
+pub mod orders;
+pub mod performance;
+pub mod period;
+pub mod portfolio;
+pub mod quotes;
 mod roller;
+pub mod seasons;
+pub mod shop;
 pub mod shortcuts;
 pub mod stock_utils;
+pub mod symbol_search;
 pub mod logging;
 
 pub use apis::igdb;
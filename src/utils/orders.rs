@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use crate::utils::stock_utils::{normalize_ticker, parse_quantity};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderType {
+    /// Buy at or below, sell at or above the trigger price
+    Limit,
+    /// Buy at or above, sell at or below the trigger price
+    Stop,
+    /// Filled at the first price once the market opens, for trades placed while it was closed
+    MarketOnOpen,
+}
+
+impl std::fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderSide::Buy => write!(f, "buy"),
+            OrderSide::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+impl FromStr for OrderSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "buy" => Ok(OrderSide::Buy),
+            "sell" => Ok(OrderSide::Sell),
+            other => Err(format!("Unknown order side \"{}\", expected buy or sell.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for OrderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderType::Limit => write!(f, "limit"),
+            OrderType::Stop => write!(f, "stop"),
+            OrderType::MarketOnOpen => write!(f, "market_on_open"),
+        }
+    }
+}
+
+impl FromStr for OrderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "limit" => Ok(OrderType::Limit),
+            "stop" => Ok(OrderType::Stop),
+            "market_on_open" => Ok(OrderType::MarketOnOpen),
+            other => Err(format!("Unknown order type \"{}\", expected limit or stop.", other)),
+        }
+    }
+}
+
+/// An order as typed by a user, e.g. `buy AAPL 5 limit 180` or `sell TSLA all stop 200`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub side: OrderSide,
+    pub ticker: String,
+    /// `None` means every share owned
+    pub quantity: Option<f64>,
+    pub order_type: OrderType,
+    pub trigger_price: f64,
+}
+
+pub fn parse_order_request(arguments: &[&str]) -> Result<OrderRequest, String> {
+    if arguments.len() != 5 {
+        return Err("Usage: !order buy AAPL 5 limit 180, or !order sell TSLA all stop 200".to_string());
+    }
+
+    let side: OrderSide = arguments[0].parse()?;
+    let ticker: String = normalize_ticker(arguments[1]);
+    let quantity: Option<f64> = match (side, arguments[2]) {
+        (OrderSide::Sell, raw) if raw.eq_ignore_ascii_case("all") => None,
+        (_, raw) => Some(parse_quantity(raw).ok_or(format!("Invalid quantity \"{}\".", raw))?),
+    };
+    let order_type: OrderType = arguments[3].parse()?;
+    if order_type == OrderType::MarketOnOpen {
+        return Err("Market-on-open orders are placed by !buy_stock and !sell_stock while the market is closed.".to_string());
+    }
+    let trigger_price: f64 = parse_quantity(arguments[4]).ok_or(format!("Invalid price \"{}\".", arguments[4]))?;
+
+    Ok(OrderRequest {
+        side,
+        ticker,
+        quantity,
+        order_type,
+        trigger_price,
+    })
+}
+
+/// Whether an order triggers at the given trade price. Only called while the market is open.
+pub fn should_fill(side: OrderSide, order_type: OrderType, trigger_price: Option<f64>, price: f64) -> bool {
+    match (side, order_type, trigger_price) {
+        (_, OrderType::MarketOnOpen, _) => true,
+        (OrderSide::Buy, OrderType::Limit, Some(trigger)) | (OrderSide::Sell, OrderType::Stop, Some(trigger)) => price <= trigger,
+        (OrderSide::Buy, OrderType::Stop, Some(trigger)) | (OrderSide::Sell, OrderType::Limit, Some(trigger)) => price >= trigger,
+        (_, _, None) => false,
+    }
+}
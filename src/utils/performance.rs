@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use crate::persistence::edge_models::PositionSnapshot;
+
+/// Ledger kinds bringing money into (or out of) an account from outside the market.
+pub const EXTERNAL_FLOW_KINDS: [&str; 4] = ["grant", "transfer", "daily", "purchase"];
+
+/// End of day net worth, along with the money that entered (positive) or left (negative) the account that day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyValue {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub net_worth: f64,
+    pub flow: f64,
+}
+
+/// How a position did over the period, exchange rate included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionReturn {
+    pub ticker: String,
+    pub change_percent: f64,
+}
+
+/// What 1 invested on the first day became on each day, cash flows excluded.
+///
+/// Each day's return is measured against the previous day's value once that day's flows are removed,
+/// so grants and transfers do not count as performance.
+pub fn return_index(values: &[DailyValue]) -> Vec<f64> {
+    let mut index: Vec<f64> = Vec::with_capacity(values.len());
+    let mut current: f64 = 1.0;
+
+    for (position, value) in values.iter().enumerate() {
+        if position > 0 {
+            let previous: f64 = values[position - 1].net_worth;
+            // Nothing was invested the previous day, there is no return to measure
+            if previous > 0.0 {
+                current *= (value.net_worth - value.flow) / previous;
+            }
+        }
+        index.push(current);
+    }
+
+    index
+}
+
+/// Time-weighted return over the whole index, in percent.
+pub fn time_weighted_return(index: &[f64]) -> f64 {
+    match index.last() {
+        Some(last) => (last - 1.0) * 100.0,
+        None => 0.0,
+    }
+}
+
+/// Largest fall from a peak of the index, in percent (0 or negative).
+pub fn max_drawdown(index: &[f64]) -> f64 {
+    let mut peak: f64 = f64::NEG_INFINITY;
+    let mut drawdown: f64 = 0.0;
+
+    for value in index.iter() {
+        peak = peak.max(*value);
+        if peak > 0.0 {
+            drawdown = drawdown.min((value - peak) / peak * 100.0);
+        }
+    }
+
+    drawdown
+}
+
+/// Profit of every position over the period, relative to what was in it, best first.
+///
+/// The profit is how far the market value moved past the cost basis between the first and the last snapshot.
+/// Splits change neither, so they do not show up as a loss. Money added to a position counts as being in it.
+pub fn position_returns(snapshots: &[PositionSnapshot]) -> Vec<PositionReturn> {
+    // First and last (market value, cost basis) of each ticker, snapshots being ordered by day
+    let mut values: BTreeMap<String, ((f64, f64), (f64, f64))> = BTreeMap::new();
+
+    for snapshot in snapshots.iter().filter(|snapshot| snapshot.quantity > 0.0) {
+        let value: (f64, f64) = (snapshot.market_value, snapshot.cost_basis);
+        values
+            .entry(snapshot.ticker.clone())
+            .and_modify(|(_, last)| *last = value)
+            .or_insert((value, value));
+    }
+
+    let mut returns: Vec<PositionReturn> = values
+        .into_iter()
+        .filter_map(|(ticker, ((first_value, first_cost), (last_value, last_cost)))| {
+            let profit: f64 = (last_value - last_cost) - (first_value - first_cost);
+            let invested: f64 = first_value + (last_cost - first_cost).max(0.0);
+            if invested <= 0.0 {
+                return None;
+            }
+
+            Some(PositionReturn {
+                ticker,
+                change_percent: profit / invested * 100.0,
+            })
+        })
+        .collect();
+    returns.sort_by(|a, b| b.change_percent.total_cmp(&a.change_percent));
+
+    returns
+}
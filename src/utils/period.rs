@@ -0,0 +1,51 @@
+use chrono::{Duration, Utc};
+
+/// Longest window a user can ask for, about a century
+const MAX_PERIOD_DAYS: i64 = 36_500;
+
+/// A time window ending now, as typed by users ("7d", "2w", "1m", "1y", "all"...).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    All,
+    Last(Duration),
+}
+
+impl Period {
+    /// Start of the window as epoch seconds, `None` when unbounded or before the earliest date chrono can hold.
+    pub fn since_epoch(&self) -> Option<f64> {
+        match self {
+            Period::All => None,
+            Period::Last(duration) => Utc::now().checked_sub_signed(*duration).map(|since| since.timestamp() as f64),
+        }
+    }
+}
+
+/// Parses a period such as "7d", "2w", "3m", "1y", "week", "month" or "all".
+/// `None` when it cannot be read or spans more than `MAX_PERIOD_DAYS`.
+pub fn parse_period(raw: &str) -> Option<Period> {
+    let raw = raw.trim().to_lowercase();
+
+    match raw.as_str() {
+        "all" => return Some(Period::All),
+        "day" | "daily" | "today" => return Some(Period::Last(Duration::days(1))),
+        "week" | "weekly" => return Some(Period::Last(Duration::weeks(1))),
+        "month" | "monthly" => return Some(Period::Last(Duration::days(30))),
+        "year" | "yearly" => return Some(Period::Last(Duration::days(365))),
+        _ => {}
+    }
+
+    let split_at = raw.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = raw.split_at(split_at);
+    let amount: i64 = amount.parse().ok().filter(|amount| *amount > 0)?;
+
+    let days_per_unit: i64 = match unit {
+        "d" => 1,
+        "w" => 7,
+        "m" | "mo" => 30,
+        "y" => 365,
+        _ => return None,
+    };
+    let days: i64 = amount.checked_mul(days_per_unit).filter(|days| *days <= MAX_PERIOD_DAYS)?;
+
+    Duration::try_days(days).map(Period::Last)
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::persistence::edge_models::{PortfolioLines, PortfolioUser, ShortLot};
+use crate::utils::fx::get_fx_rate;
+use crate::utils::stock_utils::get_stock_prices;
+
+/// Every lot of a ticker merged into a single holding.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub ticker: String,
+    /// Quote currency, empty for lines bought before currencies were tracked
+    pub currency: String,
+    pub quantity: f64,
+    /// Total amount paid for the shares still held, in the account currency
+    pub cost_basis: f64,
+    /// Total amount paid for the shares still held, in the quote currency
+    pub native_cost_basis: f64,
+    /// Sum of each lot's quantity times its purchase exchange rate
+    pub rate_weighted_quantity: f64,
+}
+
+impl Position {
+    /// Average price paid per share, in the quote currency
+    pub fn average_cost(&self) -> f64 {
+        if self.quantity > 0.0 {
+            self.native_cost_basis / self.quantity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Current price of a ticker in its quote currency, and the rate converting it into the account currency.
+#[derive(Debug, Clone, Copy)]
+pub struct HeldPrice {
+    pub price: f64,
+    pub fx_rate: f64,
+}
+
+/// A position valued at the current trade price, if the price could be fetched.
+#[derive(Debug, Clone)]
+pub struct PositionValuation {
+    pub position: Position,
+    pub price: Option<HeldPrice>,
+}
+
+impl PositionValuation {
+    /// In the account currency. Falls back to the cost basis when the price is unknown, so totals stay meaningful.
+    pub fn market_value(&self) -> f64 {
+        match self.price {
+            Some(held) => held.price * held.fx_rate * self.position.quantity,
+            None => self.position.cost_basis,
+        }
+    }
+
+    pub fn unrealized_profit(&self) -> f64 {
+        self.market_value() - self.position.cost_basis
+    }
+
+    pub fn unrealized_profit_percent(&self) -> f64 {
+        if self.position.cost_basis > 0.0 {
+            self.unrealized_profit() / self.position.cost_basis * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Part of the unrealized profit due to the stock price, valued at the purchase exchange rates.
+    pub fn price_impact(&self) -> f64 {
+        match self.price {
+            Some(held) => held.price * self.position.rate_weighted_quantity - self.position.cost_basis,
+            None => 0.0,
+        }
+    }
+
+    /// Part of the unrealized profit due to the exchange rate moving since the purchases.
+    pub fn fx_impact(&self) -> f64 {
+        self.unrealized_profit() - self.price_impact()
+    }
+}
+
+/// Every short lot of a ticker merged together and valued at the current trade price, if it could be fetched.
+#[derive(Debug, Clone)]
+pub struct ShortValuation {
+    pub ticker: String,
+    /// Quote currency, empty when unknown
+    pub currency: String,
+    /// Borrowed shares still owed
+    pub quantity: f64,
+    /// Money received when the shares were sold, in the account currency
+    pub proceeds: f64,
+    /// Money received when the shares were sold, in the quote currency
+    pub native_proceeds: f64,
+    pub price: Option<HeldPrice>,
+}
+
+impl ShortValuation {
+    /// What buying the shares back would cost, in the account currency. Falls back to the proceeds when the price is unknown.
+    pub fn market_value(&self) -> f64 {
+        match self.price {
+            Some(held) => held.price * held.fx_rate * self.quantity,
+            None => self.proceeds,
+        }
+    }
+
+    /// A short earns what the price lost since it was sold.
+    pub fn unrealized_profit(&self) -> f64 {
+        self.proceeds - self.market_value()
+    }
+
+    /// Average price the shares were sold at, in the quote currency
+    pub fn average_price(&self) -> f64 {
+        if self.quantity > 0.0 {
+            self.native_proceeds / self.quantity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Groups short lots by ticker, sorted alphabetically, and values them with the known prices.
+pub fn value_shorts(lots: &[ShortLot], prices: &HashMap<String, HeldPrice>) -> Vec<ShortValuation> {
+    let mut shorts: BTreeMap<String, ShortValuation> = BTreeMap::new();
+
+    for lot in lots.iter() {
+        let short = shorts.entry(lot.ticker.clone()).or_insert(ShortValuation {
+            ticker: lot.ticker.clone(),
+            currency: String::new(),
+            quantity: 0.0,
+            proceeds: 0.0,
+            native_proceeds: 0.0,
+            price: prices.get(&lot.ticker).copied(),
+        });
+        if let Some(currency) = lot.currency.as_ref() {
+            short.currency = currency.clone();
+        }
+        short.quantity += lot.quantity;
+        short.proceeds += lot.quantity * lot.sold_at * lot.fx_rate;
+        short.native_proceeds += lot.quantity * lot.sold_at;
+    }
+
+    shorts.into_values().collect()
+}
+
+/// Groups portfolio lines by ticker, sorted alphabetically.
+pub fn aggregate_positions(lines: &[PortfolioLines]) -> Vec<Position> {
+    let mut positions: BTreeMap<String, Position> = BTreeMap::new();
+
+    for line in lines.iter() {
+        let position = positions.entry(line.ticker.clone()).or_insert(Position {
+            ticker: line.ticker.clone(),
+            currency: String::new(),
+            quantity: 0.0,
+            cost_basis: 0.0,
+            native_cost_basis: 0.0,
+            rate_weighted_quantity: 0.0,
+        });
+        if let Some(currency) = line.currency.as_ref() {
+            position.currency = currency.clone();
+        }
+        position.quantity += line.quantity;
+        position.cost_basis += line.quantity * line.bought_at * line.fx_rate;
+        position.native_cost_basis += line.quantity * line.bought_at;
+        position.rate_weighted_quantity += line.quantity * line.fx_rate;
+    }
+
+    positions.into_values().collect()
+}
+
+/// Values the given lines with the known prices. Tickers missing from `prices` are valued at cost.
+pub fn value_positions(lines: &[PortfolioLines], prices: &HashMap<String, HeldPrice>) -> Vec<PositionValuation> {
+    aggregate_positions(lines)
+        .into_iter()
+        .map(|position| PositionValuation {
+            price: prices.get(&position.ticker).copied(),
+            position,
+        })
+        .collect()
+}
+
+/// Fetches, in one batch, the current price of everything held by these users, with its exchange rate.
+pub async fn fetch_held_prices(users: &[PortfolioUser]) -> HashMap<String, HeldPrice> {
+    let tickers: BTreeSet<String> = users
+        .iter()
+        .filter_map(|user| user.portfolio.as_ref())
+        .flat_map(|portfolio| portfolio.lines.iter().map(|line| line.ticker.clone()))
+        .chain(users.iter().flat_map(|user| user.shorts.iter().map(|lot| lot.ticker.clone())))
+        .collect();
+
+    let quotes = get_stock_prices(tickers.into_iter().collect()).await;
+    // One rate per currency, not per ticker
+    let mut rates: HashMap<String, Option<f64>> = HashMap::new();
+    let mut prices: HashMap<String, HeldPrice> = HashMap::new();
+
+    for (ticker, quote) in quotes.into_iter() {
+        let quote = match quote {
+            Ok(quote) => quote,
+            Err(_) => continue,
+        };
+        if !rates.contains_key(&quote.currency) {
+            rates.insert(quote.currency.clone(), get_fx_rate(&quote.currency).await.ok());
+        }
+        if let Some(Some(fx_rate)) = rates.get(&quote.currency) {
+            prices.insert(ticker, HeldPrice { price: quote.price, fx_rate: *fx_rate });
+        }
+    }
+
+    prices
+}
+
+/// Cash plus the market value of every position, minus what buying back the shorted shares would cost.
+pub fn net_worth(user: &PortfolioUser, prices: &HashMap<String, HeldPrice>) -> f64 {
+    let invested: f64 = match user.portfolio.as_ref() {
+        Some(portfolio) => value_positions(&portfolio.lines, prices)
+            .iter()
+            .map(|valuation| valuation.market_value())
+            .sum(),
+        None => 0.0,
+    };
+    let owed: f64 = value_shorts(&user.shorts, prices)
+        .iter()
+        .map(|short| short.market_value())
+        .sum();
+
+    user.money + invested - owed
+}
@@ -0,0 +1,127 @@
+use serenity::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{Candle, CorporateAction, HistoryRange, QuoteError, QuoteProvider, SymbolMatch};
+use crate::utils::stock_utils::StockInfo;
+
+/// Distinct searches kept at most, autocompletion sends one per keystroke
+const MAX_CACHED_SEARCHES: usize = 1_000;
+
+/// Keeps quotes for `ttl` so that commands and jobs do not hammer the underlying provider.
+/// Symbol searches barely change and back the autocompletion, they are kept for `search_ttl`
+/// and at most `MAX_CACHED_SEARCHES` of them.
+pub struct CachedQuoteProvider<P: QuoteProvider> {
+    inner: P,
+    ttl: Duration,
+    search_ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, StockInfo)>>,
+    history_cache: Mutex<HashMap<(String, HistoryRange), (Instant, Vec<Candle>)>>,
+    search_cache: Mutex<HashMap<String, (Instant, Vec<SymbolMatch>)>>,
+}
+
+impl<P: QuoteProvider> CachedQuoteProvider<P> {
+    pub fn new(inner: P, ttl: Duration, search_ttl: Duration) -> Self {
+        CachedQuoteProvider {
+            inner,
+            ttl,
+            search_ttl,
+            cache: Mutex::new(HashMap::new()),
+            history_cache: Mutex::new(HashMap::new()),
+            search_cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: QuoteProvider> QuoteProvider for CachedQuoteProvider<P> {
+    async fn get_quotes(&self, tickers: &[String]) -> Result<Vec<StockInfo>, QuoteError> {
+        let mut fresh: HashMap<String, StockInfo> = HashMap::new();
+        let mut missing: Vec<String> = vec![];
+
+        {
+            let cache = self.cache.lock().unwrap();
+            for ticker in tickers.iter() {
+                match cache.get(ticker) {
+                    Some((fetched, quote)) if fetched.elapsed() < self.ttl => {
+                        fresh.insert(ticker.clone(), quote.clone());
+                    }
+                    _ => missing.push(ticker.clone()),
+                }
+            }
+        }
+
+        // Only the expired or unknown tickers are asked for, in a single batch
+        if !missing.is_empty() {
+            let fetched: Vec<StockInfo> = self.inner.get_quotes(&missing).await?;
+            let mut cache = self.cache.lock().unwrap();
+            for quote in fetched.into_iter() {
+                cache.insert(quote.ticker.clone(), (Instant::now(), quote.clone()));
+                fresh.insert(quote.ticker.clone(), quote);
+            }
+        }
+
+        Ok(tickers.iter().filter_map(|ticker| fresh.get(ticker).cloned()).collect())
+    }
+
+    async fn get_history(&self, ticker: &str, range: HistoryRange) -> Result<Vec<Candle>, QuoteError> {
+        let key: (String, HistoryRange) = (ticker.to_string(), range);
+
+        let cached: Option<Vec<Candle>> = {
+            let history_cache = self.history_cache.lock().unwrap();
+            history_cache
+                .get(&key)
+                .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+                .map(|(_, candles)| candles.clone())
+        };
+        if let Some(candles) = cached {
+            return Ok(candles);
+        }
+
+        let candles: Vec<Candle> = self.inner.get_history(ticker, range).await?;
+        self.history_cache.lock().unwrap().insert(key, (Instant::now(), candles.clone()));
+
+        Ok(candles)
+    }
+
+    /// Only asked for once a day, not worth caching.
+    async fn get_corporate_actions(&self, ticker: &str, range: HistoryRange) -> Result<Vec<CorporateAction>, QuoteError> {
+        self.inner.get_corporate_actions(ticker, range).await
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>, QuoteError> {
+        // "Apple" and "apple " are the same search
+        let key: String = query.trim().to_lowercase();
+
+        let cached: Option<Vec<SymbolMatch>> = {
+            let search_cache = self.search_cache.lock().unwrap();
+            search_cache
+                .get(&key)
+                .filter(|(fetched, _)| fetched.elapsed() < self.search_ttl)
+                .map(|(_, matches)| matches.clone())
+        };
+        if let Some(matches) = cached {
+            return Ok(matches);
+        }
+
+        let matches: Vec<SymbolMatch> = self.inner.search(&key).await?;
+        {
+            let mut search_cache = self.search_cache.lock().unwrap();
+            search_cache.retain(|_, (fetched, _)| fetched.elapsed() < self.search_ttl);
+            // Still full of fresh searches, the oldest one makes room
+            if search_cache.len() >= MAX_CACHED_SEARCHES {
+                let oldest: Option<String> = search_cache
+                    .iter()
+                    .min_by_key(|(_, (fetched, _))| *fetched)
+                    .map(|(query, _)| query.clone());
+                if let Some(oldest) = oldest {
+                    search_cache.remove(&oldest);
+                }
+            }
+            search_cache.insert(key, (Instant::now(), matches.clone()));
+        }
+
+        Ok(matches)
+    }
+}
@@ -0,0 +1,104 @@
+use serenity::async_trait;
+use std::collections::HashMap;
+
+use super::yahoo::{parse_chart, parse_chart_events};
+use super::{has_usable_price, is_valid_ticker, Candle, CorporateAction, HistoryRange, QuoteError, QuoteProvider, SymbolMatch};
+use crate::utils::stock_utils::{Root, StockInfo};
+
+/// Replays quotes recorded from the yahoo finance API, so the stocks feature works offline.
+///
+/// The quotes file holds a regular yahoo quote response, see data/fixtures/quotes.json.
+/// The history directory holds one yahoo chart response per ticker, see data/fixtures/history.
+pub struct FixtureQuoteProvider {
+    quotes: Result<HashMap<String, StockInfo>, QuoteError>,
+    history_dir: String,
+}
+
+impl FixtureQuoteProvider {
+    pub fn from_files(path: &str, history_dir: &str) -> Self {
+        let quotes = std::fs::read_to_string(path)
+            .map_err(|err| QuoteError::Unavailable(format!("{}: {}", path, err)))
+            .and_then(|content| Self::parse(&content));
+
+        if let Err(err) = quotes.as_ref() {
+            println!("Could not load the quotes fixture {}: {:?}", path, err);
+        }
+
+        FixtureQuoteProvider {
+            quotes,
+            history_dir: history_dir.to_string(),
+        }
+    }
+
+    fn read_chart(&self, ticker: &str) -> Result<String, QuoteError> {
+        if !is_valid_ticker(ticker) {
+            return Err(QuoteError::NotFound(ticker.to_string()));
+        }
+
+        let path: String = format!("{}/{}.json", self.history_dir, ticker);
+        std::fs::read_to_string(&path).map_err(|_| QuoteError::NotFound(ticker.to_string()))
+    }
+
+    fn parse(content: &str) -> Result<HashMap<String, StockInfo>, QuoteError> {
+        let root: Root = serde_json::from_str(content).map_err(|err| QuoteError::Parse(err.to_string()))?;
+
+        Ok(root
+            .quote_response
+            .result
+            .into_iter()
+            .map(|quote| (quote.ticker.clone(), quote))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl QuoteProvider for FixtureQuoteProvider {
+    async fn get_quotes(&self, tickers: &[String]) -> Result<Vec<StockInfo>, QuoteError> {
+        let quotes = self.quotes.as_ref().map_err(|err| err.clone())?;
+
+        Ok(tickers
+            .iter()
+            .filter_map(|ticker| quotes.get(ticker).filter(|quote| has_usable_price(quote)).cloned())
+            .collect())
+    }
+
+    /// The same recording is returned whatever the range.
+    async fn get_history(&self, ticker: &str, _range: HistoryRange) -> Result<Vec<Candle>, QuoteError> {
+        parse_chart(&self.read_chart(ticker)?, ticker)
+    }
+
+    /// The events of the recorded chart, whatever the range.
+    async fn get_corporate_actions(&self, ticker: &str, _range: HistoryRange) -> Result<Vec<CorporateAction>, QuoteError> {
+        parse_chart_events(&self.read_chart(ticker)?, ticker)
+    }
+
+    /// Searches the recorded quotes: tickers starting with the query first, then names containing it.
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>, QuoteError> {
+        let quotes = self.quotes.as_ref().map_err(|err| err.clone())?;
+        let query: String = query.trim().to_lowercase();
+
+        let mut matches: Vec<(bool, &StockInfo)> = quotes
+            .values()
+            .filter_map(|quote| {
+                if quote.ticker.to_lowercase().starts_with(&query) {
+                    Some((true, quote))
+                } else if quote.name.to_lowercase().contains(&query) || quote.short_name.to_lowercase().contains(&query) {
+                    Some((false, quote))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.ticker.cmp(&b.1.ticker)));
+
+        Ok(matches
+            .into_iter()
+            .map(|(_, quote)| SymbolMatch {
+                ticker: quote.ticker.clone(),
+                name: quote.name.clone(),
+                exchange: quote.full_exchange_name.clone(),
+                kind: quote.quote_type.clone(),
+            })
+            .collect())
+    }
+}
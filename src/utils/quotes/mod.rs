@@ -0,0 +1,230 @@
+//! Stock quote providers.
+//!
+//! Every quote goes through the provider returned by [`quote_provider`], chosen from data/stocks.ron:
+//! the yahoo finance API or a recorded fixture, both behind a TTL cache.
+mod cache;
+mod fixture;
+mod yahoo;
+
+use lazy_static::lazy_static;
+use serenity::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::datastructs::stocks_config::{read_stocks_config, QuoteSource};
+use crate::utils::stock_utils::StockInfo;
+
+pub use cache::CachedQuoteProvider;
+pub use fixture::FixtureQuoteProvider;
+pub use yahoo::YahooQuoteProvider;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuoteError {
+    /// The provider could not be reached (or its fixture could not be read)
+    Unavailable(String),
+    /// The provider answered with an HTTP error status
+    Status(u16),
+    /// The provider answered something that is not a quote response
+    Parse(String),
+    /// None of the requested tickers is known
+    NotFound(String),
+}
+
+impl std::fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuoteError::Unavailable(_) => write!(f, "Could not get the stock price, the quote service is unreachable."),
+            QuoteError::Status(status) => write!(f, "Could not get the stock price (quote service error {}).", status),
+            QuoteError::Parse(_) => write!(f, "Could not read the stock price sent by the quote service."),
+            QuoteError::NotFound(tickers) => write!(f, "Could not find any stock named {}.", tickers),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// Quote currencies crypto pairs can be traded in
+const CRYPTO_QUOTE_CURRENCIES: [&str; 8] = ["EUR", "USD", "GBP", "CHF", "JPY", "CAD", "AUD", "USDT"];
+
+/// Kind of instrument behind a ticker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AssetClass {
+    Equity,
+    /// Traded around the clock, written as a BASE-QUOTE pair such as BTC-EUR
+    Crypto,
+}
+
+impl AssetClass {
+    /// Tells crypto pairs (BTC-EUR, ETH-USD) from equities, including the ones with a share class suffix (BRK-B).
+    pub fn of_ticker(ticker: &str) -> AssetClass {
+        match ticker.split_once('-') {
+            Some((base, quote))
+                if !base.is_empty() && base.chars().all(|c| c.is_ascii_alphanumeric()) && CRYPTO_QUOTE_CURRENCIES.contains(&quote) =>
+            {
+                AssetClass::Crypto
+            }
+            _ => AssetClass::Equity,
+        }
+    }
+}
+
+impl std::fmt::Display for AssetClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetClass::Equity => write!(f, "stocks"),
+            AssetClass::Crypto => write!(f, "crypto"),
+        }
+    }
+}
+
+/// One period of price history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// Start of the period, epoch seconds
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// A dividend or a stock split, as reported with the price history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CorporateAction {
+    /// `amount` paid per share, in the quote currency
+    Dividend { ex_date: i64, amount: f64 },
+    /// Every `denominator` shares become `numerator` shares
+    Split { ex_date: i64, numerator: f64, denominator: f64 },
+}
+
+impl CorporateAction {
+    /// Epoch seconds
+    pub fn ex_date(&self) -> i64 {
+        match self {
+            CorporateAction::Dividend { ex_date, .. } => *ex_date,
+            CorporateAction::Split { ex_date, .. } => *ex_date,
+        }
+    }
+}
+
+/// An instrument found by a symbol search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolMatch {
+    pub ticker: String,
+    pub name: String,
+    /// Exchange display name, such as NASDAQ
+    pub exchange: String,
+    /// Instrument type display name, such as Equity or ETF
+    pub kind: String,
+}
+
+/// How far back the price history goes, as understood by the yahoo chart API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistoryRange {
+    OneDay,
+    FiveDays,
+    OneMonth,
+    ThreeMonths,
+    SixMonths,
+    OneYear,
+    TwoYears,
+    FiveYears,
+    YearToDate,
+    Max,
+}
+
+impl HistoryRange {
+    pub fn parse(raw: &str) -> Option<HistoryRange> {
+        match raw.to_lowercase().as_str() {
+            "1d" => Some(HistoryRange::OneDay),
+            "5d" => Some(HistoryRange::FiveDays),
+            "1mo" | "1m" => Some(HistoryRange::OneMonth),
+            "3mo" | "3m" => Some(HistoryRange::ThreeMonths),
+            "6mo" | "6m" => Some(HistoryRange::SixMonths),
+            "1y" => Some(HistoryRange::OneYear),
+            "2y" => Some(HistoryRange::TwoYears),
+            "5y" => Some(HistoryRange::FiveYears),
+            "ytd" => Some(HistoryRange::YearToDate),
+            "max" => Some(HistoryRange::Max),
+            _ => None,
+        }
+    }
+
+    /// Candle size fitting the range, so charts keep a readable number of points.
+    pub fn interval(&self) -> &'static str {
+        match self {
+            HistoryRange::OneDay => "5m",
+            HistoryRange::FiveDays => "30m",
+            HistoryRange::TwoYears | HistoryRange::FiveYears => "1wk",
+            HistoryRange::Max => "1mo",
+            _ => "1d",
+        }
+    }
+}
+
+impl std::fmt::Display for HistoryRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryRange::OneDay => write!(f, "1d"),
+            HistoryRange::FiveDays => write!(f, "5d"),
+            HistoryRange::OneMonth => write!(f, "1mo"),
+            HistoryRange::ThreeMonths => write!(f, "3mo"),
+            HistoryRange::SixMonths => write!(f, "6mo"),
+            HistoryRange::OneYear => write!(f, "1y"),
+            HistoryRange::TwoYears => write!(f, "2y"),
+            HistoryRange::FiveYears => write!(f, "5y"),
+            HistoryRange::YearToDate => write!(f, "ytd"),
+            HistoryRange::Max => write!(f, "max"),
+        }
+    }
+}
+
+#[async_trait]
+pub trait QuoteProvider: Send + Sync {
+    /// Looks up several tickers at once. Unknown tickers, and the ones quoted without a usable price, are missing from
+    /// the result rather than failing the batch.
+    async fn get_quotes(&self, tickers: &[String]) -> Result<Vec<StockInfo>, QuoteError>;
+
+    /// Price history of a ticker, oldest candle first.
+    async fn get_history(&self, ticker: &str, range: HistoryRange) -> Result<Vec<Candle>, QuoteError>;
+
+    /// Dividends and splits of a ticker over the range, oldest first.
+    async fn get_corporate_actions(&self, ticker: &str, range: HistoryRange) -> Result<Vec<CorporateAction>, QuoteError>;
+
+    /// Instruments whose ticker or company name matches the query, best match first.
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>, QuoteError>;
+}
+
+/// Tickers are made of these characters only, anything else (such as "../") cannot name a chart, be it a file or a URL.
+fn is_valid_ticker(ticker: &str) -> bool {
+    !ticker.is_empty() && ticker.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || matches!(c, '.' | '^' | '=' | '-'))
+}
+
+/// A quote without a `regularMarketPrice` parses as 0, which must never be traded or valued at.
+fn has_usable_price(quote: &StockInfo) -> bool {
+    quote.price.is_finite() && quote.price > 0.0
+}
+
+lazy_static! {
+    static ref QUOTE_PROVIDER: Arc<dyn QuoteProvider> = build_quote_provider();
+}
+
+fn build_quote_provider() -> Arc<dyn QuoteProvider> {
+    let config = read_stocks_config();
+    let ttl: Duration = Duration::from_secs(config.quote_cache_ttl_secs);
+    let search_ttl: Duration = Duration::from_secs(config.search_cache_ttl_secs);
+
+    match config.quote_source {
+        QuoteSource::Yahoo => Arc::new(CachedQuoteProvider::new(YahooQuoteProvider::new(), ttl, search_ttl)),
+        QuoteSource::Fixture => Arc::new(CachedQuoteProvider::new(
+            FixtureQuoteProvider::from_files(&config.fixture_path, &config.history_fixture_dir),
+            ttl,
+            search_ttl,
+        )),
+    }
+}
+
+/// The configured quote provider, shared by every stocks command and job.
+pub fn quote_provider() -> Arc<dyn QuoteProvider> {
+    QUOTE_PROVIDER.clone()
+}
@@ -0,0 +1,287 @@
+use serde::Deserialize;
+use serenity::async_trait;
+use std::collections::HashMap;
+
+use super::{has_usable_price, is_valid_ticker, Candle, CorporateAction, HistoryRange, QuoteError, QuoteProvider, SymbolMatch};
+use crate::utils::stock_utils::{Root, StockInfo};
+
+const QUOTE_URL: &'static str = "https://query1.finance.yahoo.com/v7/finance/quote";
+const CHART_URL: &'static str = "https://query1.finance.yahoo.com/v8/finance/chart";
+const SEARCH_URL: &'static str = "https://query2.finance.yahoo.com/v1/finance/search";
+/// Matches asked for per search, enough for a disambiguation list or an autocompletion
+const SEARCH_RESULTS: usize = 10;
+
+#[derive(Deserialize)]
+struct SearchRoot {
+    #[serde(default)]
+    quotes: Vec<SearchQuote>,
+}
+
+/// Search results also hold news and other entries without a symbol
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchQuote {
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    longname: Option<String>,
+    #[serde(default)]
+    shortname: Option<String>,
+    #[serde(default)]
+    exch_disp: Option<String>,
+    #[serde(default)]
+    type_disp: Option<String>,
+}
+
+/// Reads a yahoo search API answer into symbol matches, in the order yahoo ranked them.
+pub(super) fn parse_search(body: &str) -> Result<Vec<SymbolMatch>, QuoteError> {
+    let root: SearchRoot = serde_json::from_str(body).map_err(|err| QuoteError::Parse(err.to_string()))?;
+
+    Ok(root
+        .quotes
+        .into_iter()
+        .filter_map(|quote| {
+            let ticker: String = quote.symbol?;
+            Some(SymbolMatch {
+                name: quote.longname.or(quote.shortname).unwrap_or_else(|| ticker.clone()),
+                ticker,
+                exchange: quote.exch_disp.unwrap_or_default(),
+                kind: quote.type_disp.unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct ChartRoot {
+    chart: ChartResponse,
+}
+
+#[derive(Deserialize)]
+struct ChartResponse {
+    #[serde(default)]
+    result: Option<Vec<ChartResult>>,
+}
+
+#[derive(Deserialize)]
+struct ChartResult {
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    indicators: ChartIndicators,
+    /// Only present when asked for with the "events" parameter
+    #[serde(default)]
+    events: Option<ChartEvents>,
+}
+
+/// Events are keyed by their date, which is repeated in the event itself
+#[derive(Deserialize)]
+struct ChartEvents {
+    #[serde(default)]
+    dividends: HashMap<String, ChartDividend>,
+    #[serde(default)]
+    splits: HashMap<String, ChartSplit>,
+}
+
+#[derive(Deserialize)]
+struct ChartDividend {
+    amount: f64,
+    date: i64,
+}
+
+#[derive(Deserialize)]
+struct ChartSplit {
+    date: i64,
+    numerator: f64,
+    denominator: f64,
+}
+
+#[derive(Deserialize)]
+struct ChartIndicators {
+    quote: Vec<ChartQuote>,
+}
+
+/// Yahoo leaves holes (null) in the series when there was no trade
+#[derive(Deserialize)]
+struct ChartQuote {
+    #[serde(default)]
+    open: Vec<Option<f64>>,
+    #[serde(default)]
+    high: Vec<Option<f64>>,
+    #[serde(default)]
+    low: Vec<Option<f64>>,
+    #[serde(default)]
+    close: Vec<Option<f64>>,
+}
+
+/// Reads a yahoo chart API answer into candles, skipping the periods without trades.
+pub(super) fn parse_chart(body: &str, ticker: &str) -> Result<Vec<Candle>, QuoteError> {
+    let root: ChartRoot = serde_json::from_str(body).map_err(|err| QuoteError::Parse(err.to_string()))?;
+    let result: ChartResult = root
+        .chart
+        .result
+        .and_then(|results| results.into_iter().next())
+        .ok_or(QuoteError::NotFound(ticker.to_string()))?;
+    let quote: &ChartQuote = result.indicators.quote.first().ok_or(QuoteError::NotFound(ticker.to_string()))?;
+
+    let value_at = |series: &Vec<Option<f64>>, index: usize| series.get(index).copied().flatten();
+    let candles: Vec<Candle> = result
+        .timestamp
+        .iter()
+        .enumerate()
+        .filter_map(|(index, timestamp)| {
+            Some(Candle {
+                timestamp: *timestamp,
+                open: value_at(&quote.open, index)?,
+                high: value_at(&quote.high, index)?,
+                low: value_at(&quote.low, index)?,
+                close: value_at(&quote.close, index)?,
+            })
+        })
+        .collect();
+
+    Ok(candles)
+}
+
+/// Reads the dividends and splits of a yahoo chart API answer, oldest first.
+pub(super) fn parse_chart_events(body: &str, ticker: &str) -> Result<Vec<CorporateAction>, QuoteError> {
+    let root: ChartRoot = serde_json::from_str(body).map_err(|err| QuoteError::Parse(err.to_string()))?;
+    let result: ChartResult = root
+        .chart
+        .result
+        .and_then(|results| results.into_iter().next())
+        .ok_or(QuoteError::NotFound(ticker.to_string()))?;
+
+    let mut actions: Vec<CorporateAction> = vec![];
+    if let Some(events) = result.events {
+        actions.extend(events.dividends.values().map(|dividend| CorporateAction::Dividend {
+            ex_date: dividend.date,
+            amount: dividend.amount,
+        }));
+        actions.extend(
+            events
+                .splits
+                .values()
+                .filter(|split| split.numerator > 0.0 && split.denominator > 0.0)
+                .map(|split| CorporateAction::Split {
+                    ex_date: split.date,
+                    numerator: split.numerator,
+                    denominator: split.denominator,
+                }),
+        );
+    }
+    actions.sort_by_key(|action| action.ex_date());
+
+    Ok(actions)
+}
+
+/// Live quotes from the yahoo finance v7 API.
+pub struct YahooQuoteProvider {
+    client: reqwest::Client,
+}
+
+impl YahooQuoteProvider {
+    pub fn new() -> Self {
+        YahooQuoteProvider {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Raw v8 chart answer, with the dividends and splits when `with_events` is set.
+    async fn fetch_chart(&self, ticker: &str, range: HistoryRange, with_events: bool) -> Result<String, QuoteError> {
+        // The ticker is a path segment of the URL
+        if !is_valid_ticker(ticker) {
+            return Err(QuoteError::NotFound(ticker.to_string()));
+        }
+
+        let mut query: Vec<(&str, String)> = vec![("range", range.to_string()), ("interval", range.interval().to_string())];
+        if with_events {
+            query.push(("events", String::from("div,splits")));
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/{}", CHART_URL, ticker))
+            .query(&query)
+            .send()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))?;
+
+        if response.status().as_u16() == 404 {
+            return Err(QuoteError::NotFound(ticker.to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(QuoteError::Status(response.status().as_u16()));
+        }
+
+        response
+            .text()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl QuoteProvider for YahooQuoteProvider {
+    async fn get_quotes(&self, tickers: &[String]) -> Result<Vec<StockInfo>, QuoteError> {
+        if tickers.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let response = self
+            .client
+            .get(QUOTE_URL)
+            .query(&[("symbols", tickers.join(","))])
+            .send()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(QuoteError::Status(response.status().as_u16()));
+        }
+
+        let response_body: String = response
+            .text()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))?;
+        let root: Root = serde_json::from_str(&response_body).map_err(|err| QuoteError::Parse(err.to_string()))?;
+
+        Ok(root.quote_response.result.into_iter().filter(has_usable_price).collect())
+    }
+
+    async fn get_history(&self, ticker: &str, range: HistoryRange) -> Result<Vec<Candle>, QuoteError> {
+        let response_body: String = self.fetch_chart(ticker, range, false).await?;
+
+        parse_chart(&response_body, ticker)
+    }
+
+    async fn get_corporate_actions(&self, ticker: &str, range: HistoryRange) -> Result<Vec<CorporateAction>, QuoteError> {
+        let response_body: String = self.fetch_chart(ticker, range, true).await?;
+
+        parse_chart_events(&response_body, ticker)
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>, QuoteError> {
+        let response = self
+            .client
+            .get(SEARCH_URL)
+            .query(&[
+                ("q", query.to_string()),
+                ("quotesCount", SEARCH_RESULTS.to_string()),
+                ("newsCount", String::from("0")),
+            ])
+            .send()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(QuoteError::Status(response.status().as_u16()));
+        }
+
+        let response_body: String = response
+            .text()
+            .await
+            .map_err(|err| QuoteError::Unavailable(err.to_string()))?;
+
+        parse_search(&response_body)
+    }
+}
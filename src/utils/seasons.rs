@@ -0,0 +1,85 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Brussels;
+use std::collections::HashMap;
+
+use crate::persistence::edge_models::SeasonParticipant;
+use crate::utils::portfolio::{net_worth, HeldPrice};
+
+/// Place of a participant in the standings of a season.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedParticipant {
+    pub user_id: String,
+    pub display_name: String,
+    pub net_worth: f64,
+    /// Starts at 1
+    pub rank: i64,
+}
+
+/// Values every season account with the known prices, best first.
+pub fn rank_participants(participants: &[SeasonParticipant], prices: &HashMap<String, HeldPrice>) -> Vec<RankedParticipant> {
+    let mut ranked: Vec<RankedParticipant> = participants
+        .iter()
+        .map(|participant| RankedParticipant {
+            user_id: participant.user_id.clone(),
+            display_name: participant.display_name.clone(),
+            net_worth: net_worth(&participant.as_portfolio_user(), prices),
+            rank: 0,
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.net_worth.total_cmp(&a.net_worth));
+    for (position, participant) in ranked.iter_mut().enumerate() {
+        participant.rank = position as i64 + 1;
+    }
+
+    ranked
+}
+
+/// Standings stored when the season ended. Participants who joined without being ranked are left out.
+pub fn archived_standings(participants: &[SeasonParticipant]) -> Vec<RankedParticipant> {
+    participants
+        .iter()
+        .filter_map(|participant| match (participant.final_rank, participant.final_net_worth) {
+            (Some(rank), Some(net_worth)) => Some(RankedParticipant {
+                user_id: participant.user_id.clone(),
+                display_name: participant.display_name.clone(),
+                net_worth,
+                rank,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A YYYY-MM-DD end date, meaning the end of that day in Europe/Brussels. It must be in the future.
+pub fn parse_season_end(raw: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let day: NaiveDate = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map_err(|_| format!("\"{}\" is not a date, use YYYY-MM-DD.", raw))?;
+    let end = day
+        .and_hms_opt(23, 59, 59)
+        .and_then(|end| Brussels.from_local_datetime(&end).latest())
+        .ok_or_else(|| format!("{} does not exist in Europe/Brussels.", raw))?
+        .with_timezone(&Utc);
+
+    if end <= now {
+        return Err(String::from("The end date must be in the future."));
+    }
+
+    Ok(end)
+}
+
+/// One line per participant: rank, name, net worth and return on the starting balance.
+pub fn format_standings(ranked: &[RankedParticipant], starting_balance: f64, limit: usize) -> String {
+    ranked
+        .iter()
+        .take(limit)
+        .map(|participant| {
+            let change: f64 = if starting_balance > 0.0 {
+                (participant.net_worth / starting_balance - 1.0) * 100.0
+            } else {
+                0.0
+            };
+            format!("{}. {}: €{:.2} ({:+.2}%)", participant.rank, participant.display_name, participant.net_worth, change)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
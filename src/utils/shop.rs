@@ -0,0 +1,100 @@
+use chrono::TimeZone;
+use chrono_tz::Tz;
+
+use crate::persistence::edge_models::{InventoryEntry, ShopItem};
+
+/// What a shop item gives (Discord::ShopItem.kind).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShopItemKind {
+    /// A role kept forever
+    Role,
+    /// A role taken back after `duration_hours`
+    TempRole,
+    /// A coloured role, only one colour is worn at a time
+    Colour,
+    /// Stacks in the inventory, used up with !use
+    Consumable,
+}
+
+impl ShopItemKind {
+    /// Accepts what administrators type in !shop_item add, and the stored names.
+    pub fn parse(raw: &str) -> Option<ShopItemKind> {
+        match raw.to_lowercase().as_str() {
+            "role" => Some(ShopItemKind::Role),
+            "temp_role" | "temprole" | "temp" => Some(ShopItemKind::TempRole),
+            "colour" | "color" => Some(ShopItemKind::Colour),
+            "consumable" | "item" => Some(ShopItemKind::Consumable),
+            _ => None,
+        }
+    }
+
+    pub fn gives_role(&self) -> bool {
+        !matches!(self, ShopItemKind::Consumable)
+    }
+}
+
+impl std::fmt::Display for ShopItemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShopItemKind::Role => write!(f, "role"),
+            ShopItemKind::TempRole => write!(f, "temp_role"),
+            ShopItemKind::Colour => write!(f, "colour"),
+            ShopItemKind::Consumable => write!(f, "consumable"),
+        }
+    }
+}
+
+/// Item names are matched without case, and may not contain spaces.
+pub fn normalize_item_name(raw: &str) -> String {
+    raw.trim().to_lowercase()
+}
+
+/// "#ff8800", "ff8800" or "0xff8800" into 0xff8800.
+pub fn parse_colour(raw: &str) -> Option<u32> {
+    let hex: &str = raw.trim().trim_start_matches('#').trim_start_matches("0x");
+    if hex.len() != 6 {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// One line of !shop: name, price, what it gives and how many are left.
+pub fn describe_item(item: &ShopItem) -> String {
+    let gives: String = match (ShopItemKind::parse(&item.kind), item.role_id.as_ref()) {
+        (Some(ShopItemKind::Role), Some(role_id)) => format!("role <@&{}>", role_id),
+        (Some(ShopItemKind::TempRole), Some(role_id)) => format!("role <@&{}> for {}h", role_id, item.duration_hours.unwrap_or(0)),
+        (Some(ShopItemKind::Colour), _) => format!("colour #{:06x}", item.colour.unwrap_or(0)),
+        _ => String::from("consumable"),
+    };
+    let mut line: String = format!("**{}** €{:.2}: {}", item.name, item.price, gives);
+
+    if let Some(stock) = item.stock {
+        line = format!("{} ({} left)", line, stock.max(0));
+    }
+    if !item.enabled {
+        line = format!("{} [disabled]", line);
+    }
+    if let Some(description) = item.description.as_ref().filter(|description| !description.is_empty()) {
+        line = format!("{}\n    {}", line, description);
+    }
+
+    line
+}
+
+/// One line of !inventory, temporary roles with their expiry in the user's timezone.
+pub fn describe_inventory_entry(entry: &InventoryEntry, timezone: &Tz) -> String {
+    match ShopItemKind::parse(&entry.kind) {
+        Some(ShopItemKind::Consumable) => format!("**{}** x{}", entry.item_name, entry.quantity),
+        Some(ShopItemKind::TempRole) => {
+            let expires: String = entry
+                .expires_epoch
+                .and_then(|epoch| timezone.timestamp_opt(epoch as i64, 0).single())
+                .map(|date| date.format("%a %Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| String::from("?"));
+            format!("**{}** (role until {})", entry.item_name, expires)
+        },
+        Some(ShopItemKind::Colour) => format!("**{}** (colour)", entry.item_name),
+        _ => format!("**{}** (role)", entry.item_name),
+    }
+}
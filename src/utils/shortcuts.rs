@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use serenity::http::CacheHttp;
+use serenity::http::{CacheHttp, HttpError};
 use serenity::model::channel::Message;
 use serenity::model::id::{MessageId, UserId};
 use serenity::{model::id::ChannelId, prelude::*, utils::MessageBuilder};
@@ -154,3 +154,40 @@ pub async fn delete_message(context: &Context, channel_id: ChannelId, message_id
         .unwrap();
     let _ = message_object.delete(context.http()).await;
 }
+
+/// Discord error codes of a guild, member, role or user which does not exist (anymore)
+const UNKNOWN_ENTITY_CODES: [isize; 4] = [10004, 10007, 10011, 10013];
+
+/// Whether Discord refused because what was asked for does not exist, in which case retrying is pointless.
+fn is_unknown_entity(error: &SerenityError) -> bool {
+    match error {
+        SerenityError::Http(http_error) => match http_error.as_ref() {
+            HttpError::UnsuccessfulRequest(response) => UNKNOWN_ENTITY_CODES.contains(&response.error.code),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Takes a role off a member. A member who left or a deleted role counts as done,
+/// the other errors (rate limits, missing permissions, outages) are worth a retry.
+pub async fn remove_member_role_or_gone(
+    context: &Context,
+    guild_id: u64,
+    user_id: u64,
+    role_id: u64,
+    reason: &str,
+) -> Result<(), SerenityError> {
+    match context.http.remove_member_role(guild_id, user_id, role_id, Some(reason)).await {
+        Err(err) if !is_unknown_entity(&err) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes a role of a guild. A role which is already gone counts as done.
+pub async fn delete_role_or_gone(context: &Context, guild_id: u64, role_id: u64) -> Result<(), SerenityError> {
+    match context.http.delete_role(guild_id, role_id).await {
+        Err(err) if !is_unknown_entity(&err) => Err(err),
+        _ => Ok(()),
+    }
+}
@@ -1,21 +1,28 @@
 use serde::Deserialize;
 use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::utils::quotes::{quote_provider, AssetClass, Candle, HistoryRange, QuoteError};
 
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[serde(default)]
 pub struct Root {
     pub quote_response: QuoteResponse,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[serde(default)]
 pub struct QuoteResponse {
     pub result: Vec<StockInfo>,
     pub error: Value,
 }
 
+/// Missing fields fall back to their default value, so a change in the API answer does not break every quote.
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[serde(default)]
 pub struct StockInfo {
     pub language: String,
     pub region: String,
@@ -109,49 +116,169 @@ pub struct StockInfo {
     pub ticker: String,
 }
 
-/**
- * Function that gets the stock price of a given stock, using the yahoo finance API.
- * The function takes a string as an argument, which is the stock ticker.
- */
-pub async fn get_stock_price(ticker: String) -> Result<StockInfo, String> {
-    // Perform the request using reqwest
-    let response = reqwest::get(&format!(
-        "https://query1.finance.yahoo.com/v7/finance/quote?symbols={}",
-        ticker
-    ))
-    .await;
-    // Check if the request was successful
-    if response.is_err() {
-        return Err("Could not get the stock price.".to_string());
+impl StockInfo {
+    pub fn asset_class(&self) -> AssetClass {
+        match self.quote_type.as_str() {
+            "CRYPTOCURRENCY" => AssetClass::Crypto,
+            _ => AssetClass::of_ticker(&self.ticker),
+        }
+    }
+}
+
+/// Gets the stock info of a given ticker from the configured quote provider.
+pub async fn get_stock_price(ticker: String) -> Result<StockInfo, QuoteError> {
+    let quotes: Vec<StockInfo> = quote_provider().get_quotes(&[ticker.clone()]).await?;
+
+    quotes
+        .into_iter()
+        .next()
+        .ok_or(QuoteError::NotFound(ticker))
+}
+
+/// Gets the stock info of several tickers in a single request. Unknown tickers are simply missing from the result.
+pub async fn get_stock_quotes(tickers: Vec<String>) -> Result<Vec<StockInfo>, QuoteError> {
+    let quotes: Vec<StockInfo> = quote_provider().get_quotes(&tickers).await?;
+
+    if quotes.is_empty() && !tickers.is_empty() {
+        return Err(QuoteError::NotFound(tickers.join(", ")));
+    }
+
+    Ok(quotes)
+}
+
+/// Gets the stock info of several tickers in a single request, keyed by ticker.
+pub async fn get_stock_prices(tickers: Vec<String>) -> HashMap<String, Result<StockInfo, QuoteError>> {
+    match quote_provider().get_quotes(&tickers).await {
+        Ok(quotes) => {
+            let mut by_ticker: HashMap<String, StockInfo> = quotes
+                .into_iter()
+                .map(|quote| (quote.ticker.clone(), quote))
+                .collect();
+
+            tickers
+                .into_iter()
+                .map(|ticker| {
+                    let quote = by_ticker.remove(&ticker).ok_or(QuoteError::NotFound(ticker.clone()));
+                    (ticker, quote)
+                })
+                .collect()
+        }
+        Err(error) => tickers.into_iter().map(|ticker| (ticker, Err(error.clone()))).collect(),
+    }
+}
+
+/// Gets the price history of a ticker from the configured quote provider, oldest candle first.
+pub async fn get_stock_history(ticker: String, range: HistoryRange) -> Result<Vec<Candle>, QuoteError> {
+    let candles: Vec<Candle> = quote_provider().get_history(&ticker, range).await?;
+
+    if candles.is_empty() {
+        return Err(QuoteError::NotFound(ticker));
+    }
+
+    Ok(candles)
+}
+
+/// Strips the optional leading "$" users like to put in front of a ticker and uppercases it.
+/// Crypto pairs may also be written with a slash (BTC/EUR).
+pub fn normalize_ticker(raw: &str) -> String {
+    raw.trim().trim_start_matches('$').replace('/', "-").to_uppercase()
+}
+
+/// Parses a quantity of shares. Fractional quantities are accepted, zero, negative and non-finite ones are not.
+pub fn parse_quantity(raw: &str) -> Option<f64> {
+    match raw.trim().replace(',', ".").parse::<f64>() {
+        Ok(quantity) if quantity.is_finite() && quantity > 0.0 => Some(quantity),
+        _ => None,
     }
-    // Get the response
-    let response = response.unwrap();
-    // Check if the response was successful
-    if !response.status().is_success() {
-        return Err("Could not get the stock price.".to_string());
+}
+
+/// Formats quotes as a compact monospaced table: price, day change, day range and 52-week range.
+pub fn format_quotes_table(quotes: &[StockInfo]) -> String {
+    let mut table: String = format!(
+        "{:<8} {:>10} {:>8} {:>19} {:>19}\n",
+        "Ticker", "Price", "Day %", "Day range", "52w range"
+    );
+
+    for quote in quotes.iter() {
+        table.push_str(&format!(
+            "{:<8} {:>10.2} {:>+7.2}% {:>19} {:>19}\n",
+            quote.ticker,
+            quote.price,
+            quote.regular_market_change_percent,
+            quote.regular_market_day_range,
+            quote.fifty_two_week_range
+        ));
     }
-    // Get the response body
-    let response_body = response.text().await;
-    // Check if the response body was successful
-    if response_body.is_err() {
-        return Err("Could not get the stock price.".to_string());
+
+    format!("```\n{}```", table)
+}
+
+/// Formats quotes side by side for comparison: price, day change, P/E and market cap.
+pub fn format_comparison_table(quotes: &[StockInfo]) -> String {
+    let mut table: String = format!(
+        "{:<9} {:>10} {:<4} {:>8} {:>7} {:>8}\n",
+        "Ticker", "Price", "Cur", "Day %", "P/E", "Mkt cap"
+    );
+
+    for quote in quotes.iter() {
+        table.push_str(&format!(
+            "{:<9} {:>10.2} {:<4} {:>+7.2}% {:>7} {:>8}\n",
+            quote.ticker,
+            quote.price,
+            quote.currency,
+            quote.regular_market_change_percent,
+            format_ratio(quote.trailing_pe),
+            format_large_number(quote.market_cap)
+        ));
     }
 
-    // Unwrap the response body and print it to the console for debug purposes
-    let response_body = response_body.unwrap();
+    format!("```\n{}```", table)
+}
 
-    println!("{:#}", response_body);
+/// Every detailed field of a quote, one per line.
+pub fn format_quote_details(quote: &StockInfo) -> String {
+    [
+        format!("Price: {:.2} {} ({:+.2}% today)", quote.price, quote.currency, quote.regular_market_change_percent),
+        format!("P/E: {} trailing, {} forward", format_ratio(quote.trailing_pe), format_ratio(quote.forward_pe)),
+        format!("EPS: {:.2} trailing, {:.2} forward", quote.eps_trailing_twelve_months, quote.eps_forward),
+        format!("Market cap: {}", format_large_number(quote.market_cap)),
+        format!("50-day average: {}", format_average(quote.price, quote.fifty_day_average)),
+        format!("200-day average: {}", format_average(quote.price, quote.two_hundred_day_average)),
+        format!("52-week range: {}", quote.fifty_two_week_range),
+        format!("Dividend yield: {:.2}%", quote.trailing_annual_dividend_yield * 100.0),
+        format!("Analysts: {}", if quote.rating.is_empty() { "-" } else { &quote.rating }),
+    ]
+    .join("\n")
+}
 
-    // Parse the response body into a StockInfo struct
-    let stock_info: Root = serde_json::from_str(&response_body).unwrap();
+/// The moving average and how far the price is from it
+fn format_average(price: f64, average: f64) -> String {
+    if average > 0.0 {
+        format!("{:.2} (price {:+.2}%)", average, (price / average - 1.0) * 100.0)
+    } else {
+        String::from("-")
+    }
+}
 
-    let reply_length = stock_info.quote_response.result.len();
-    if reply_length == 0 {
-        return Err("Could not get the stock price.".to_string());
+/// "-" when the ratio is not reported (0) or meaningless (negative earnings)
+fn format_ratio(ratio: f64) -> String {
+    if ratio > 0.0 {
+        format!("{:.2}", ratio)
+    } else {
+        String::from("-")
     }
+}
 
-    // Return the price of the stock
-    return Ok(stock_info.quote_response.result[0].clone());
+/// 2950000000000 -> "2.95T"
+pub fn format_large_number(value: i64) -> String {
+    let value: f64 = value as f64;
+    match value.abs() {
+        v if v >= 1e12 => format!("{:.2}T", value / 1e12),
+        v if v >= 1e9 => format!("{:.2}B", value / 1e9),
+        v if v >= 1e6 => format!("{:.2}M", value / 1e6),
+        v if v > 0.0 => format!("{:.0}", value),
+        _ => String::from("-"),
+    }
 }
 
 // Function that transforms an epoch timestamp into a human readable date
@@ -0,0 +1,147 @@
+use crate::utils::quotes::{quote_provider, QuoteError, SymbolMatch};
+use crate::utils::stock_utils::{normalize_ticker, StockInfo};
+
+/// What a ticker or company name typed by a user points to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TickerResolution {
+    Resolved(SymbolMatch),
+    /// Several instruments match, the user has to pick one
+    Ambiguous(Vec<SymbolMatch>),
+    NotFound,
+}
+
+/// Matches listed when a name is ambiguous
+pub const DISAMBIGUATION_SIZE: usize = 5;
+/// Choices Discord accepts in an autocompletion
+pub const AUTOCOMPLETE_SIZE: usize = 25;
+
+/// Picks the instrument a query means among the search results.
+///
+/// An exact ticker wins, as does a single result. Otherwise the query is only resolved when exactly one
+/// company name starts with it ("microsoft" but not "apple", which also matches Apple Hospitality REIT).
+pub fn pick_match(query: &str, matches: Vec<SymbolMatch>) -> TickerResolution {
+    let ticker: String = normalize_ticker(query);
+    let name: String = query.trim().to_lowercase();
+
+    if let Some(exact) = matches.iter().find(|candidate| candidate.ticker == ticker) {
+        return TickerResolution::Resolved(exact.clone());
+    }
+
+    match matches.len() {
+        0 => TickerResolution::NotFound,
+        1 => TickerResolution::Resolved(matches[0].clone()),
+        _ => {
+            let starting: Vec<&SymbolMatch> = matches
+                .iter()
+                .filter(|candidate| candidate.name.to_lowercase().starts_with(&name))
+                .collect();
+            match starting.as_slice() {
+                [only] => TickerResolution::Resolved((*only).clone()),
+                _ => TickerResolution::Ambiguous(matches.into_iter().take(DISAMBIGUATION_SIZE).collect()),
+            }
+        }
+    }
+}
+
+/// Resolves a company name (or a ticker the quote provider did not know) through the symbol search.
+pub async fn resolve_ticker(query: &str) -> Result<TickerResolution, QuoteError> {
+    let matches: Vec<SymbolMatch> = quote_provider().search(query).await?;
+
+    Ok(pick_match(query, matches))
+}
+
+/// Quotes found for what the user typed, tickers and company names alike.
+#[derive(Debug, Clone, Default)]
+pub struct QuoteLookup {
+    pub quotes: Vec<StockInfo>,
+    /// Queries matching several instruments, with the candidates
+    pub ambiguous: Vec<(String, Vec<SymbolMatch>)>,
+    /// Queries matching nothing
+    pub missing: Vec<String>,
+}
+
+/// Looks the tickers up in one batch, then resolves the unknown ones as company names.
+///
+/// When none of the words is a ticker, `phrase` (every word together) is tried first as a single name,
+/// so that "bank of america" is not looked up word by word.
+pub async fn lookup_quotes(tickers: &[String], phrase: &str) -> Result<QuoteLookup, QuoteError> {
+    let mut lookup: QuoteLookup = QuoteLookup::default();
+    lookup.quotes = quote_provider().get_quotes(tickers).await?;
+
+    let mut unknown: Vec<String> = tickers
+        .iter()
+        .filter(|ticker| !lookup.quotes.iter().any(|quote| &quote.ticker == *ticker))
+        .cloned()
+        .collect();
+
+    if lookup.quotes.is_empty() && tickers.len() > 1 {
+        match resolve_ticker(phrase).await? {
+            TickerResolution::Resolved(candidate) => {
+                lookup.quotes = quote_provider().get_quotes(&[candidate.ticker]).await?;
+                return Ok(lookup);
+            }
+            TickerResolution::Ambiguous(candidates) => {
+                lookup.ambiguous.push((phrase.to_string(), candidates));
+                return Ok(lookup);
+            }
+            TickerResolution::NotFound => {}
+        }
+    }
+
+    for query in unknown.drain(..) {
+        match resolve_ticker(&query).await? {
+            TickerResolution::Resolved(candidate) => {
+                if lookup.quotes.iter().any(|quote| quote.ticker == candidate.ticker) {
+                    continue;
+                }
+                match quote_provider().get_quotes(&[candidate.ticker]).await?.into_iter().next() {
+                    Some(quote) => lookup.quotes.push(quote),
+                    None => lookup.missing.push(query),
+                }
+            }
+            TickerResolution::Ambiguous(candidates) => lookup.ambiguous.push((query, candidates)),
+            TickerResolution::NotFound => lookup.missing.push(query),
+        }
+    }
+
+    Ok(lookup)
+}
+
+/// Tickers to suggest while the user is typing, best match first. Errors simply suggest nothing.
+pub async fn suggest_tickers(prefix: &str) -> Vec<SymbolMatch> {
+    if prefix.trim().is_empty() {
+        return vec![];
+    }
+
+    match quote_provider().search(prefix).await {
+        Ok(matches) => matches.into_iter().take(AUTOCOMPLETE_SIZE).collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// "AAPL - Apple Inc. (Equity, NASDAQ)"
+pub fn describe_match(candidate: &SymbolMatch) -> String {
+    let details: Vec<&str> = [candidate.kind.as_str(), candidate.exchange.as_str()]
+        .into_iter()
+        .filter(|detail| !detail.is_empty())
+        .collect();
+
+    if details.is_empty() {
+        format!("{} - {}", candidate.ticker, candidate.name)
+    } else {
+        format!("{} - {} ({})", candidate.ticker, candidate.name, details.join(", "))
+    }
+}
+
+/// Numbered list of the candidates, for a disambiguation reply.
+pub fn format_disambiguation(query: &str, candidates: &[SymbolMatch]) -> String {
+    let mut lines: Vec<String> = vec![format!("\"{}\" matches several instruments, use one of these tickers:", query.trim())];
+    lines.extend(
+        candidates
+            .iter()
+            .enumerate()
+            .map(|(position, candidate)| format!("{}. {}", position + 1, describe_match(candidate))),
+    );
+
+    lines.join("\n")
+}